@@ -1,6 +1,7 @@
 pub mod loader;
 pub mod object;
 pub mod process;
+pub mod repair;
 
 #[derive(Default, Clone, Copy, Debug, PartialEq)]
 pub struct IndexedTriangle([usize; 3]);
@@ -38,4 +39,8 @@ impl std::ops::IndexMut<usize> for IndexedLine {
 pub struct SliceInput {
     pub objects: Vec<object::ObjectMesh>,
     pub masks: Vec<object::ObjectMesh>,
+    ///Volumes that force support under the parts of the objects inside of them
+    pub support_enforcers: Vec<object::ObjectMesh>,
+    ///Volumes in which no support is generated
+    pub support_blockers: Vec<object::ObjectMesh>,
 }
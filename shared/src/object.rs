@@ -5,7 +5,7 @@ use std::{
 
 use glam::{vec3, Mat4, Vec3};
 
-use crate::IndexedTriangle;
+use crate::{repair::RepairReport, IndexedTriangle};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ObjectVertex(Vec3);
@@ -77,6 +77,11 @@ impl ObjectMesh {
         (min, max)
     }
 
+    ///Merges duplicate vertices, removes degenerate triangles, fixes the winding and closes small holes
+    pub fn repair(&mut self) -> RepairReport {
+        crate::repair::repair(&mut self.vertices, &mut self.triangles)
+    }
+
     pub fn transform(&mut self, transform: Mat4) {
         *self = transform * self.clone();
     }
@@ -1,4 +1,4 @@
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 
 use atomic_float::AtomicF32;
 use parking_lot::RwLock;
@@ -9,6 +9,10 @@ pub struct Process {
     progress: AtomicF32,
     finished: AtomicBool,
     closed: AtomicBool,
+    stage_start: AtomicF32,
+    stage_end: AtomicF32,
+    stage_steps: AtomicUsize,
+    stage_done: AtomicUsize,
 }
 
 impl Process {
@@ -18,6 +22,10 @@ impl Process {
             progress: AtomicF32::new(0.0),
             finished: AtomicBool::new(false),
             closed: AtomicBool::new(false),
+            stage_start: AtomicF32::new(0.0),
+            stage_end: AtomicF32::new(0.0),
+            stage_steps: AtomicUsize::new(0),
+            stage_done: AtomicUsize::new(0),
         }
     }
 
@@ -30,6 +38,48 @@ impl Process {
         *self.task.write() = task;
     }
 
+    /// Starts a stage that moves the progress from start to end over the given amount of steps.
+    /// Called by the thread driving the process, the steps can be done from any thread.
+    pub fn start_stage(&self, task: String, start: f32, end: f32, steps: usize) {
+        self.set_task(task);
+
+        self.stage_done
+            .store(0, std::sync::atomic::Ordering::Relaxed);
+        self.stage_steps
+            .store(steps, std::sync::atomic::Ordering::Relaxed);
+        self.stage_start
+            .store(start, std::sync::atomic::Ordering::Relaxed);
+        self.stage_end
+            .store(end, std::sync::atomic::Ordering::Relaxed);
+
+        self.set_progress(start);
+    }
+
+    /// Marks one step of the current stage as done, this is lock free for worker threads.
+    pub fn step(&self) {
+        let done = self
+            .stage_done
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            + 1;
+        let steps = self.stage_steps.load(std::sync::atomic::Ordering::Relaxed);
+
+        if steps == 0 {
+            return;
+        }
+
+        let start = self.stage_start.load(std::sync::atomic::Ordering::Relaxed);
+        let end = self.stage_end.load(std::sync::atomic::Ordering::Relaxed);
+
+        let progress = start + (end - start) * (done.min(steps) as f32 / steps as f32);
+
+        // steps finish out of order on worker threads, the progress must not go back
+        let _ = self.progress.fetch_update(
+            std::sync::atomic::Ordering::Relaxed,
+            std::sync::atomic::Ordering::Relaxed,
+            |current| (progress > current).then_some(progress),
+        );
+    }
+
     pub fn get(&self) -> f32 {
         self.progress.load(std::sync::atomic::Ordering::Relaxed)
     }
@@ -0,0 +1,407 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{object::ObjectVertex, IndexedTriangle};
+
+///Vertices closer than this distance in mm are merged
+const MERGE_DISTANCE: f32 = 0.0001;
+
+///Boundary loops with at most this many edges are closed
+const MAX_HOLE_EDGES: usize = 32;
+
+///Summary of the changes made while repairing a mesh
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RepairReport {
+    pub merged_vertices: usize,
+    ///Triangles without area or with the same vertices as another triangle
+    pub removed_triangles: usize,
+    pub flipped_triangles: usize,
+    pub closed_holes: usize,
+    ///Edges shared by more than two triangles, these can't be repaired
+    pub non_manifold_edges: usize,
+}
+
+impl RepairReport {
+    pub fn is_clean(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = [
+            (self.merged_vertices, "merged vertices"),
+            (self.removed_triangles, "removed degenerate triangles"),
+            (self.flipped_triangles, "flipped triangles"),
+            (self.closed_holes, "closed holes"),
+            (self.non_manifold_edges, "non-manifold edges"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| format!("{} {}", count, name))
+        .collect();
+
+        if parts.is_empty() {
+            write!(f, "No repairs needed")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+pub(crate) fn repair(
+    vertices: &mut Vec<ObjectVertex>,
+    triangles: &mut Vec<IndexedTriangle>,
+) -> RepairReport {
+    let merged_vertices = merge_vertices(vertices, triangles);
+    let removed_triangles = remove_degenerate_triangles(vertices, triangles);
+    let mut flipped_triangles = fix_winding(vertices, triangles);
+    let closed_holes = close_holes(triangles);
+
+    //the filled holes can turn an open surface into a closed one with a different orientation
+    if closed_holes > 0 {
+        flipped_triangles += fix_winding(vertices, triangles);
+    }
+
+    RepairReport {
+        merged_vertices,
+        removed_triangles,
+        flipped_triangles,
+        closed_holes,
+        non_manifold_edges: count_non_manifold_edges(triangles),
+    }
+}
+
+fn triangle_edges(triangle: &IndexedTriangle) -> [(usize, usize); 3] {
+    [
+        (triangle[0], triangle[1]),
+        (triangle[1], triangle[2]),
+        (triangle[2], triangle[0]),
+    ]
+}
+
+fn undirected(edge: (usize, usize)) -> (usize, usize) {
+    (edge.0.min(edge.1), edge.0.max(edge.1))
+}
+
+///Merges every vertex into the first vertex closer than the merge distance.
+///The vertices are sorted into cells of the merge distance, so only the neighbouring cells have to be searched.
+fn merge_vertices(vertices: &mut Vec<ObjectVertex>, triangles: &mut [IndexedTriangle]) -> usize {
+    let mut cells: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    let mut merged: Vec<ObjectVertex> = Vec::with_capacity(vertices.len());
+
+    let remap: Vec<usize> = vertices
+        .iter()
+        .map(|vertex| {
+            let cell = (
+                (vertex.x / MERGE_DISTANCE).floor() as i64,
+                (vertex.y / MERGE_DISTANCE).floor() as i64,
+                (vertex.z / MERGE_DISTANCE).floor() as i64,
+            );
+
+            //close vertices can lie on both sides of a cell border
+            let close = (-1..=1)
+                .flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (dx, dy, dz))))
+                .filter_map(|(dx, dy, dz)| cells.get(&(cell.0 + dx, cell.1 + dy, cell.2 + dz)))
+                .flatten()
+                .copied()
+                .find(|index| merged[*index].distance(**vertex) <= MERGE_DISTANCE);
+
+            close.unwrap_or_else(|| {
+                merged.push(*vertex);
+                cells.entry(cell).or_default().push(merged.len() - 1);
+                merged.len() - 1
+            })
+        })
+        .collect();
+
+    triangles.iter_mut().for_each(|triangle| {
+        triangle
+            .0
+            .iter_mut()
+            .for_each(|index| *index = remap[*index])
+    });
+
+    let count = vertices.len() - merged.len();
+    *vertices = merged;
+
+    count
+}
+
+fn remove_degenerate_triangles(
+    vertices: &[ObjectVertex],
+    triangles: &mut Vec<IndexedTriangle>,
+) -> usize {
+    let count = triangles.len();
+    let mut faces: HashSet<[usize; 3]> = HashSet::new();
+
+    triangles.retain(|triangle| {
+        let [a, b, c] = triangle.0;
+
+        if a == b || b == c || a == c {
+            return false;
+        }
+
+        let area = (*vertices[b] - *vertices[a])
+            .cross(*vertices[c] - *vertices[a])
+            .length();
+
+        if area <= MERGE_DISTANCE * MERGE_DISTANCE {
+            return false;
+        }
+
+        //the same face twice, independent of its winding
+        let mut face = triangle.0;
+        face.sort_unstable();
+
+        faces.insert(face)
+    });
+
+    count - triangles.len()
+}
+
+fn signed_volume(vertices: &[ObjectVertex], triangle: &IndexedTriangle) -> f32 {
+    vertices[triangle[0]].dot(vertices[triangle[1]].cross(*vertices[triangle[2]])) / 6.0
+}
+
+///Orients all triangles of a connected surface the same way, with the normals pointing outwards
+fn fix_winding(vertices: &[ObjectVertex], triangles: &mut [IndexedTriangle]) -> usize {
+    let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+
+    for (index, triangle) in triangles.iter().enumerate() {
+        for edge in triangle_edges(triangle) {
+            edges.entry(undirected(edge)).or_default().push(index);
+        }
+    }
+
+    let mut flipped = vec![false; triangles.len()];
+    let mut visited = vec![false; triangles.len()];
+
+    for start in 0..triangles.len() {
+        if visited[start] {
+            continue;
+        }
+
+        visited[start] = true;
+
+        let mut component = vec![start];
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(current) = queue.pop_front() {
+            for edge in triangle_edges(&triangles[current]) {
+                let neighbours = &edges[&undirected(edge)];
+
+                //only manifold edges give a reliable orientation
+                if neighbours.len() != 2 {
+                    continue;
+                }
+
+                let neighbour = if neighbours[0] == current {
+                    neighbours[1]
+                } else {
+                    neighbours[0]
+                };
+
+                if visited[neighbour] {
+                    continue;
+                }
+
+                visited[neighbour] = true;
+
+                //consistent neighbours use the shared edge in the opposite direction
+                if triangle_edges(&triangles[neighbour]).contains(&edge) {
+                    triangles[neighbour].0.swap(1, 2);
+                    flipped[neighbour] = !flipped[neighbour];
+                }
+
+                component.push(neighbour);
+                queue.push_back(neighbour);
+            }
+        }
+
+        let volume: f32 = component
+            .iter()
+            .map(|index| signed_volume(vertices, &triangles[*index]))
+            .sum();
+
+        if volume < 0.0 {
+            for index in component {
+                triangles[index].0.swap(1, 2);
+                flipped[index] = !flipped[index];
+            }
+        }
+    }
+
+    flipped.into_iter().filter(|flipped| *flipped).count()
+}
+
+///Fills small boundary loops with a triangle fan
+fn close_holes(triangles: &mut Vec<IndexedTriangle>) -> usize {
+    let directed_edges: HashSet<(usize, usize)> =
+        triangles.iter().flat_map(triangle_edges).collect();
+
+    //the hole runs against the direction of the triangles bordering it
+    let mut next: HashMap<usize, usize> = HashMap::new();
+    let mut branching: HashSet<usize> = HashSet::new();
+
+    for (a, b) in directed_edges.iter() {
+        if !directed_edges.contains(&(*b, *a)) && next.insert(*b, *a).is_some() {
+            branching.insert(*b);
+        }
+    }
+
+    let mut starts: Vec<usize> = next.keys().copied().collect();
+    starts.sort_unstable();
+
+    let mut used: HashSet<usize> = HashSet::new();
+    let mut closed = 0;
+
+    for start in starts {
+        if used.contains(&start) || branching.contains(&start) {
+            continue;
+        }
+
+        let mut hole = vec![start];
+        let mut current = start;
+
+        let is_loop = loop {
+            match next.get(&current) {
+                Some(following) if *following == start => break true,
+                Some(following)
+                    if !branching.contains(following)
+                        && !hole.contains(following)
+                        && hole.len() < MAX_HOLE_EDGES =>
+                {
+                    hole.push(*following);
+                    current = *following;
+                }
+                _ => break false,
+            }
+        };
+
+        used.extend(hole.iter().copied());
+
+        if is_loop && hole.len() >= 3 {
+            for index in 1..hole.len() - 1 {
+                triangles.push(IndexedTriangle([hole[0], hole[index], hole[index + 1]]));
+            }
+
+            closed += 1;
+        }
+    }
+
+    closed
+}
+
+fn count_non_manifold_edges(triangles: &[IndexedTriangle]) -> usize {
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+
+    for edge in triangles.iter().flat_map(triangle_edges) {
+        *edges.entry(undirected(edge)).or_default() += 1;
+    }
+
+    edges.values().filter(|count| **count > 2).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///A unit cube with the vertices of every triangle stored separately
+    fn cube_soup() -> (Vec<ObjectVertex>, Vec<IndexedTriangle>) {
+        let corners = [
+            (0.0, 0.0, 0.0),
+            (1.0, 0.0, 0.0),
+            (1.0, 1.0, 0.0),
+            (0.0, 1.0, 0.0),
+            (0.0, 0.0, 1.0),
+            (1.0, 0.0, 1.0),
+            (1.0, 1.0, 1.0),
+            (0.0, 1.0, 1.0),
+        ];
+
+        let faces = [
+            [0, 2, 1],
+            [0, 3, 2],
+            [4, 5, 6],
+            [4, 6, 7],
+            [0, 1, 5],
+            [0, 5, 4],
+            [1, 2, 6],
+            [1, 6, 5],
+            [2, 3, 7],
+            [2, 7, 6],
+            [3, 0, 4],
+            [3, 4, 7],
+        ];
+
+        let mut vertices = vec![];
+        let mut triangles = vec![];
+
+        for face in faces {
+            let start = vertices.len();
+
+            for corner in face {
+                let (x, y, z) = corners[corner];
+                vertices.push(ObjectVertex::new(x, y, z));
+            }
+
+            triangles.push(IndexedTriangle([start, start + 1, start + 2]));
+        }
+
+        (vertices, triangles)
+    }
+
+    #[test]
+    fn clean_cube_only_merges_vertices() {
+        let (mut vertices, mut triangles) = cube_soup();
+
+        let report = repair(&mut vertices, &mut triangles);
+
+        assert_eq!(
+            report,
+            RepairReport {
+                merged_vertices: 36 - 8,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn close_vertices_on_both_sides_of_a_cell_border_are_merged() {
+        let mut vertices = vec![
+            ObjectVertex::new(0.000099, 0.0, 0.0),
+            ObjectVertex::new(0.000101, 0.0, 0.0),
+            ObjectVertex::new(1.0, 0.0, 0.0),
+        ];
+        let mut triangles = vec![IndexedTriangle([1, 2, 0])];
+
+        assert_eq!(merge_vertices(&mut vertices, &mut triangles), 1);
+        assert_eq!(vertices.len(), 2);
+        assert_eq!(triangles[0].0, [0, 1, 0]);
+    }
+
+    #[test]
+    fn broken_cube_is_repaired() {
+        let (mut vertices, mut triangles) = cube_soup();
+
+        //flip a face, remove one and add a degenerate one
+        triangles[0].0.swap(1, 2);
+        triangles.remove(3);
+        triangles.push(IndexedTriangle([0, 0, 1]));
+
+        let report = repair(&mut vertices, &mut triangles);
+
+        assert_eq!(report.flipped_triangles, 1);
+        assert_eq!(report.removed_triangles, 1);
+        assert_eq!(report.closed_holes, 1);
+        assert_eq!(report.non_manifold_edges, 0);
+        assert_eq!(triangles.len(), 12);
+
+        let volume: f32 = triangles
+            .iter()
+            .map(|triangle| signed_volume(&vertices, triangle))
+            .sum();
+
+        assert!((volume - 1.0).abs() < 0.0001);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    settings::{AdaptiveLayerHeightSettings, Settings},
+    tower::{TowerFace, TriangleTower},
+};
+
+///Faces with a smaller height range are flat and can't produce a stair-step error
+const FLAT_FACE_EPSILON: f32 = 0.0001;
+
+///Calculates the height of every layer from the slope of the model surfaces.
+///Returns None if adaptive layer heights are disabled.
+pub fn compute_layer_heights(
+    towers: &[TriangleTower],
+    max_height: f32,
+    settings: &Settings,
+) -> Option<Vec<f32>> {
+    let adaptive = settings.adaptive_layer_height.as_ref()?;
+
+    let mut faces: Vec<TowerFace> = towers
+        .iter()
+        .flat_map(|tower| tower.faces().iter().copied())
+        .filter(|face| face.max_z - face.min_z > FLAT_FACE_EPSILON)
+        .collect();
+
+    faces.sort_by(|a, b| {
+        a.min_z
+            .partial_cmp(&b.min_z)
+            .expect("STL ERROR: No Points should have NAN values")
+    });
+
+    Some(layer_heights_from_faces(
+        &faces,
+        max_height,
+        adaptive,
+        |layer, height| settings.get_layer_height_override(layer, height),
+    ))
+}
+
+///Faces have to be sorted by their lowest point.
+///Layers with a height set by a layer range keep that height.
+fn layer_heights_from_faces(
+    faces: &[TowerFace],
+    max_height: f32,
+    adaptive: &AdaptiveLayerHeightSettings,
+    layer_height_override: impl Fn(usize, f32) -> Option<f32>,
+) -> Vec<f32> {
+    //Faces further away can't limit the current layer even after smoothing
+    let look_ahead = ((adaptive.max_layer_height - adaptive.min_layer_height)
+        / adaptive.max_height_change)
+        .ceil()
+        * adaptive.max_layer_height
+        + adaptive.max_layer_height;
+
+    let mut heights: Vec<f32> = vec![];
+    let mut active_faces: Vec<&TowerFace> = vec![];
+    let mut next_face = 0;
+    let mut z = 0.0;
+
+    while z < max_height {
+        if let Some(height) = layer_height_override(heights.len(), z) {
+            heights.push(height);
+            z += height;
+            continue;
+        }
+
+        while next_face < faces.len() && faces[next_face].min_z < z + look_ahead {
+            active_faces.push(&faces[next_face]);
+            next_face += 1;
+        }
+
+        active_faces.retain(|face| face.max_z > z);
+
+        let mut height = match heights.last() {
+            Some(last) => adaptive
+                .max_layer_height
+                .min(last + adaptive.max_height_change),
+            None => adaptive.max_layer_height,
+        };
+
+        for face in active_faces.iter() {
+            //the stair-step error of a layer on a face is the layer height times the z component of the normal
+            let allowed_height = adaptive.max_deviation / face.normal_z;
+
+            //faces above are reached in at least this many layers, lowering the height one change per layer
+            let layers_until_face = ((face.min_z - z).max(0.0) / adaptive.max_layer_height).floor();
+
+            height = height.min(allowed_height + layers_until_face * adaptive.max_height_change);
+        }
+
+        let height = height.max(adaptive.min_layer_height);
+
+        heights.push(height);
+        z += height;
+    }
+
+    heights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heights(faces: &[TowerFace], max_height: f32) -> Vec<f32> {
+        layer_heights_from_faces(
+            faces,
+            max_height,
+            &AdaptiveLayerHeightSettings::default(),
+            |_, _| None,
+        )
+    }
+
+    #[test]
+    fn vertical_walls_use_max_height() {
+        let faces = [TowerFace {
+            min_z: 0.0,
+            max_z: 10.0,
+            normal_z: 0.0,
+        }];
+
+        let heights = heights(&faces, 10.0);
+
+        assert!(heights
+            .iter()
+            .all(|height| *height == AdaptiveLayerHeightSettings::default().max_layer_height));
+        assert!(heights.iter().sum::<f32>() >= 10.0);
+    }
+
+    #[test]
+    fn shallow_slopes_use_min_height_and_transitions_are_smooth() {
+        let settings = AdaptiveLayerHeightSettings::default();
+
+        let faces = [
+            TowerFace {
+                min_z: 0.0,
+                max_z: 10.0,
+                normal_z: 0.0,
+            },
+            TowerFace {
+                min_z: 5.0,
+                max_z: 6.0,
+                normal_z: 0.95,
+            },
+        ];
+
+        let heights = heights(&faces, 10.0);
+
+        let mut z = 0.0;
+        for height in heights.iter() {
+            if z > 5.0 && z + height < 6.0 {
+                assert_eq!(*height, settings.min_layer_height);
+            }
+            z += height;
+        }
+
+        assert!(heights
+            .windows(2)
+            .all(|pair| (pair[0] - pair[1]).abs() <= settings.max_height_change + 0.0001));
+    }
+
+    #[test]
+    fn layer_range_overrides_are_kept() {
+        let faces = [TowerFace {
+            min_z: 0.0,
+            max_z: 10.0,
+            normal_z: 0.5,
+        }];
+
+        let heights = layer_heights_from_faces(
+            &faces,
+            10.0,
+            &AdaptiveLayerHeightSettings::default(),
+            |layer, _| (layer == 0).then_some(0.3),
+        );
+
+        assert_eq!(heights[0], 0.3);
+        assert!(heights[1..].iter().all(|height| *height == 0.1));
+    }
+}
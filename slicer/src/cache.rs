@@ -0,0 +1,125 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use shared::{object::ObjectMesh, SliceInput};
+
+use crate::{settings::Settings, tower::TriangleTower, Object};
+
+///Results of the early stages of the slicer.
+///A stage is reused as long as the meshes and the settings that stage depends on don't change.
+#[derive(Debug, Default)]
+pub struct SliceCache {
+    pub(crate) towers: Option<CachedTowers>,
+    pub(crate) slices: Option<CachedSlices>,
+}
+
+impl SliceCache {
+    ///Drops all cached stages, the next slice starts from scratch
+    pub fn clear(&mut self) {
+        self.towers = None;
+        self.slices = None;
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CachedTowers {
+    pub key: u64,
+    pub objects: Vec<TriangleTower>,
+    pub masks: Vec<TriangleTower>,
+    pub support_enforcers: Vec<TriangleTower>,
+    pub support_blockers: Vec<TriangleTower>,
+}
+
+#[derive(Debug)]
+pub(crate) struct CachedSlices {
+    pub key: u64,
+    ///The sliced objects and masks before any pass was run on them
+    pub objects: Vec<Object>,
+    pub masks: Vec<Object>,
+    pub support_enforcers: Vec<Object>,
+    pub support_blockers: Vec<Object>,
+}
+
+///The towers only depend on the meshes
+pub(crate) fn towers_key(input: &SliceInput) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    input.objects.len().hash(&mut hasher);
+    input
+        .objects
+        .iter()
+        .for_each(|mesh| hash_mesh(mesh, &mut hasher));
+
+    for meshes in [
+        &input.masks,
+        &input.support_enforcers,
+        &input.support_blockers,
+    ] {
+        meshes.len().hash(&mut hasher);
+        meshes.iter().for_each(|mesh| hash_mesh(mesh, &mut hasher));
+    }
+
+    hasher.finish()
+}
+
+///The slices depend on the towers and every setting that changes the height of the layers
+pub(crate) fn slices_key(towers_key: u64, settings: &Settings) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    towers_key.hash(&mut hasher);
+
+    let layer_heights = settings
+        .layer_settings
+        .iter()
+        .map(|(range, partial)| (range, partial.layer_height))
+        .collect::<Vec<_>>();
+
+    serde_json::to_string(&(
+        settings.layer_height,
+        &settings.adaptive_layer_height,
+        layer_heights,
+    ))
+    .expect("Settings should always serialize")
+    .hash(&mut hasher);
+
+    hasher.finish()
+}
+
+fn hash_mesh(mesh: &ObjectMesh, hasher: &mut DefaultHasher) {
+    mesh.vertices().len().hash(hasher);
+    for vertex in mesh.vertices() {
+        vertex.x.to_bits().hash(hasher);
+        vertex.y.to_bits().hash(hasher);
+        vertex.z.to_bits().hash(hasher);
+    }
+
+    mesh.triangles().len().hash(hasher);
+    for triangle in mesh.triangles() {
+        triangle[0].hash(hasher);
+        triangle[1].hash(hasher);
+        triangle[2].hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices_key_ignores_later_stage_settings() {
+        let settings = Settings::default();
+
+        let mut faster = settings.clone();
+        faster.speed.travel *= 2.0;
+        faster.infill_percentage = 0.5;
+
+        let mut thinner = settings.clone();
+        thinner.layer_height /= 2.0;
+
+        assert_eq!(slices_key(1, &settings), slices_key(1, &faster));
+        assert_ne!(slices_key(1, &settings), slices_key(1, &thinner));
+        assert_ne!(slices_key(1, &settings), slices_key(2, &settings));
+    }
+}
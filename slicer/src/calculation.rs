@@ -28,6 +28,13 @@ pub fn calculate_values(moves: &[Command], settings: &Settings) -> CalculatedVal
                 end,
                 width,
                 thickness,
+            }
+            | Command::MoveAndExtrudeSpiral {
+                start,
+                end,
+                width,
+                thickness,
+                ..
             } => {
                 let x_diff = end.x - start.x;
                 let y_diff = end.y - start.y;
@@ -95,7 +102,8 @@ pub fn calculate_values(moves: &[Command], settings: &Settings) -> CalculatedVal
             Command::NoAction
             | Command::LayerChange { .. }
             | Command::ChangeObject { .. }
-            | Command::ChangeType { .. } => {}
+            | Command::ChangeType { .. }
+            | Command::SetToolAxis { .. } => {}
         }
     }
 
@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+
+use geo::Coord;
+use glam::Vec3;
+
+use crate::{error::SlicerErrors, Command, PrintheadSettings, Settings};
+
+///Number of height map cells along one side of a block
+const BLOCK_CELLS: i32 = 16;
+
+///Number of segments used to approximate the nozzle cone rings
+const CONE_SEGMENTS: usize = 16;
+
+///The parts the printhead is made of
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeadPart {
+    ///The cone of the nozzle
+    Nozzle,
+
+    ///The heater block above the nozzle
+    HeaterBlock,
+
+    ///The carriage holding the hotend
+    Carriage,
+}
+
+impl std::fmt::Display for HeadPart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeadPart::Nozzle => write!(f, "nozzle"),
+            HeadPart::HeaterBlock => write!(f, "heater block"),
+            HeadPart::Carriage => write!(f, "carriage"),
+        }
+    }
+}
+
+///A convex shape in the tool frame, the nozzle tip is the origin and the tool axis is +Z
+#[derive(Clone, Copy, Debug)]
+enum HeadShape {
+    ///A truncated cone along the tool axis
+    Cone {
+        bottom: f32,
+        top: f32,
+        bottom_radius: f32,
+        top_radius: f32,
+    },
+
+    ///A box centered on the tool axis
+    Box {
+        bottom: f32,
+        top: f32,
+        half_width: f32,
+        half_depth: f32,
+    },
+}
+
+impl HeadShape {
+    fn contains(&self, point: Vec3) -> bool {
+        match *self {
+            HeadShape::Cone {
+                bottom,
+                top,
+                bottom_radius,
+                top_radius,
+            } => {
+                if point.z <= bottom || point.z >= top {
+                    return false;
+                }
+
+                let radius = bottom_radius
+                    + (top_radius - bottom_radius) * (point.z - bottom) / (top - bottom);
+                point.x * point.x + point.y * point.y < radius * radius
+            }
+            HeadShape::Box {
+                bottom,
+                top,
+                half_width,
+                half_depth,
+            } => {
+                point.z > bottom
+                    && point.z < top
+                    && point.x.abs() < half_width
+                    && point.y.abs() < half_depth
+            }
+        }
+    }
+
+    ///The vertices of the convex hull of the shape
+    fn vertices(&self) -> Vec<Vec3> {
+        match *self {
+            HeadShape::Cone {
+                bottom,
+                top,
+                bottom_radius,
+                top_radius,
+            } => (0..CONE_SEGMENTS)
+                .flat_map(|i| {
+                    let angle = i as f32 * std::f32::consts::TAU / CONE_SEGMENTS as f32;
+                    let (sin, cos) = angle.sin_cos();
+                    [
+                        Vec3::new(cos * bottom_radius, sin * bottom_radius, bottom),
+                        Vec3::new(cos * top_radius, sin * top_radius, top),
+                    ]
+                })
+                .collect(),
+            HeadShape::Box {
+                bottom,
+                top,
+                half_width,
+                half_depth,
+            } => [bottom, top]
+                .into_iter()
+                .flat_map(|z| {
+                    [
+                        Vec3::new(-half_width, -half_depth, z),
+                        Vec3::new(half_width, -half_depth, z),
+                        Vec3::new(half_width, half_depth, z),
+                        Vec3::new(-half_width, half_depth, z),
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+fn head_shapes(printhead: &PrintheadSettings) -> [(HeadPart, HeadShape); 3] {
+    let heater_block_bottom = printhead.nozzle_cone_height;
+    let heater_block_top = heater_block_bottom + printhead.heater_block_height;
+    let carriage_bottom = heater_block_top + printhead.carriage_offset;
+
+    [
+        (
+            HeadPart::Nozzle,
+            HeadShape::Cone {
+                bottom: printhead.tip_clearance,
+                top: printhead.nozzle_cone_height,
+                bottom_radius: printhead.nozzle_tip_diameter / 2.0,
+                top_radius: printhead.nozzle_cone_diameter / 2.0,
+            },
+        ),
+        (
+            HeadPart::HeaterBlock,
+            HeadShape::Box {
+                bottom: heater_block_bottom,
+                top: heater_block_top,
+                half_width: printhead.heater_block_width / 2.0,
+                half_depth: printhead.heater_block_depth / 2.0,
+            },
+        ),
+        (
+            HeadPart::Carriage,
+            HeadShape::Box {
+                bottom: carriage_bottom,
+                top: carriage_bottom + printhead.carriage_height,
+                half_width: printhead.carriage_width / 2.0,
+                half_depth: printhead.carriage_depth / 2.0,
+            },
+        ),
+    ]
+}
+
+///Orthonormal frame of the printhead at a specific position
+struct ToolFrame {
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl ToolFrame {
+    fn new(origin: Vec3, axis: Vec3) -> Self {
+        let w = axis.normalize();
+
+        //Keep the head's x direction as close to the machine's x axis as possible
+        let u = (Vec3::X - w * Vec3::X.dot(w))
+            .try_normalize()
+            .unwrap_or_else(|| (Vec3::Y - w * Vec3::Y.dot(w)).normalize());
+        let v = w.cross(u);
+
+        ToolFrame { origin, u, v, w }
+    }
+
+    fn to_local(&self, point: Vec3) -> Vec3 {
+        let d = point - self.origin;
+        Vec3::new(d.dot(self.u), d.dot(self.v), d.dot(self.w))
+    }
+
+    fn to_world(&self, point: Vec3) -> Vec3 {
+        self.origin + self.u * point.x + self.v * point.y + self.w * point.z
+    }
+}
+
+///The printed volume so far, stored as the top height of columns of material
+struct HeightMap {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), f32>,
+    blocks: HashMap<(i32, i32), f32>,
+    max_height: f32,
+}
+
+impl HeightMap {
+    fn new(cell_size: f32) -> Self {
+        HeightMap {
+            cell_size,
+            cells: HashMap::new(),
+            blocks: HashMap::new(),
+            max_height: 0.0,
+        }
+    }
+
+    fn cell_of(&self, x: f32, y: f32) -> (i32, i32) {
+        (
+            (x / self.cell_size).floor() as i32,
+            (y / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn cell_center(&self, (x, y): (i32, i32)) -> Coord<f32> {
+        Coord {
+            x: (x as f32 + 0.5) * self.cell_size,
+            y: (y as f32 + 0.5) * self.cell_size,
+        }
+    }
+
+    ///Add an extruded bead from start to end with its top at height z
+    fn deposit(&mut self, start: Coord<f32>, end: Coord<f32>, width: f32, z: f32) {
+        let radius = width / 2.0 + self.cell_size / 2.0;
+        let (min_x, min_y) = self.cell_of(start.x.min(end.x) - radius, start.y.min(end.y) - radius);
+        let (max_x, max_y) = self.cell_of(start.x.max(end.x) + radius, start.y.max(end.y) + radius);
+
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                if distance_to_segment(self.cell_center((x, y)), start, end) > radius {
+                    continue;
+                }
+
+                let cell = self.cells.entry((x, y)).or_insert(0.0);
+                *cell = cell.max(z);
+
+                let block = self
+                    .blocks
+                    .entry((x.div_euclid(BLOCK_CELLS), y.div_euclid(BLOCK_CELLS)))
+                    .or_insert(0.0);
+                *block = block.max(z);
+            }
+        }
+
+        self.max_height = self.max_height.max(z);
+    }
+
+    ///Test if the shape placed in the frame intersects any column of printed material
+    fn intersects(&self, shape: &HeadShape, frame: &ToolFrame) -> bool {
+        let (min, max) = shape
+            .vertices()
+            .into_iter()
+            .map(|vertex| frame.to_world(vertex))
+            .fold(
+                (Vec3::INFINITY, Vec3::NEG_INFINITY),
+                |(min, max), vertex| (min.min(vertex), max.max(vertex)),
+            );
+
+        if min.z > self.max_height {
+            return false;
+        }
+
+        let (min_x, min_y) = self.cell_of(min.x, min.y);
+        let (max_x, max_y) = self.cell_of(max.x, max.y);
+
+        for block_x in min_x.div_euclid(BLOCK_CELLS)..=max_x.div_euclid(BLOCK_CELLS) {
+            for block_y in min_y.div_euclid(BLOCK_CELLS)..=max_y.div_euclid(BLOCK_CELLS) {
+                match self.blocks.get(&(block_x, block_y)) {
+                    Some(height) if *height >= min.z => {}
+                    _ => continue,
+                }
+
+                let cells_x = (block_x * BLOCK_CELLS).max(min_x)
+                    ..=(block_x * BLOCK_CELLS + BLOCK_CELLS - 1).min(max_x);
+                let cells_y = (block_y * BLOCK_CELLS).max(min_y)
+                    ..=(block_y * BLOCK_CELLS + BLOCK_CELLS - 1).min(max_y);
+
+                for x in cells_x {
+                    for y in cells_y.clone() {
+                        let height = match self.cells.get(&(x, y)) {
+                            Some(height) if *height >= min.z => *height,
+                            _ => continue,
+                        };
+
+                        if self.column_intersects(
+                            shape,
+                            frame,
+                            (x, y),
+                            min.z.max(0.0),
+                            height.min(max.z),
+                        ) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    ///Sample the column of a cell between the 2 heights
+    fn column_intersects(
+        &self,
+        shape: &HeadShape,
+        frame: &ToolFrame,
+        cell: (i32, i32),
+        bottom: f32,
+        top: f32,
+    ) -> bool {
+        let center = self.cell_center(cell);
+        let samples = ((top - bottom) / self.cell_size).ceil().max(0.0) as usize;
+
+        (0..=samples).any(|i| {
+            let z = (bottom + i as f32 * self.cell_size).min(top);
+            shape.contains(frame.to_local(Vec3::new(center.x, center.y, z)))
+        })
+    }
+}
+
+fn distance_to_segment(point: Coord<f32>, start: Coord<f32>, end: Coord<f32>) -> f32 {
+    let segment = end - start;
+    let length_sq = segment.x * segment.x + segment.y * segment.y;
+
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((point.x - start.x) * segment.x + (point.y - start.y) * segment.y) / length_sq)
+            .clamp(0.0, 1.0)
+    };
+
+    let closest = start + segment * t;
+    let d = point - closest;
+    (d.x * d.x + d.y * d.y).sqrt()
+}
+
+///Checks all moves for collisions of the printhead with the printed volume and the print bed.
+///Move indices count the moves since the last layer change, starting at 0.
+pub fn check_collisions(cmds: &[Command], settings: &Settings) -> Vec<SlicerErrors> {
+    let printhead = match settings.printhead.as_ref() {
+        Some(printhead) => printhead,
+        None => return vec![],
+    };
+
+    //Moves with a vertical tool axis can never reach below the current layer
+    if !cmds
+        .iter()
+        .any(|cmd| matches!(cmd, Command::SetToolAxis { .. }))
+    {
+        return vec![];
+    }
+
+    let shapes = head_shapes(printhead);
+    let step = settings.nozzle_diameter;
+
+    let mut height_map = HeightMap::new(settings.nozzle_diameter);
+    let mut collisions = vec![];
+
+    let mut axis = Vec3::Z;
+    let mut current_z = 0.0;
+    let mut current_pos = Coord { x: 0.0, y: 0.0 };
+    let mut layer = 0;
+    let mut move_index = 0;
+
+    for cmd in cmds {
+        let (start, end) = match cmd {
+            Command::MoveTo { end } => (current_pos, *end),
+            Command::MoveAndExtrude { start, end, .. }
+            | Command::MoveAndExtrudeFiber { start, end, .. }
+            | Command::Arc { start, end, .. } => (*start, *end),
+            Command::MoveAndExtrudeSpiral {
+                start, end, end_z, ..
+            } => {
+                current_z = *end_z;
+                (*start, *end)
+            }
+            Command::LayerChange { z, index } => {
+                current_z = *z;
+                layer = *index;
+                move_index = 0;
+                continue;
+            }
+            Command::SetToolAxis { axis: new_axis } => {
+                axis = new_axis.try_normalize().unwrap_or(Vec3::Z);
+                continue;
+            }
+            _ => continue,
+        };
+
+        if axis.angle_between(Vec3::Z) > f32::EPSILON.sqrt() {
+            let d = end - start;
+            let samples = ((d.x * d.x + d.y * d.y).sqrt() / step).ceil().max(1.0) as usize;
+
+            let collision = (1..=samples).find_map(|i| {
+                let position = start + d * (i as f32 / samples as f32);
+                let frame = ToolFrame::new(Vec3::new(position.x, position.y, current_z), axis);
+
+                shapes.iter().find_map(|(part, shape)| {
+                    let hits_bed = shape
+                        .vertices()
+                        .into_iter()
+                        .any(|vertex| frame.to_world(vertex).z < 0.0);
+
+                    if hits_bed {
+                        Some(SlicerErrors::PrintheadBedCollision {
+                            layer,
+                            move_index,
+                            part: part.to_string(),
+                        })
+                    } else if height_map.intersects(shape, &frame) {
+                        Some(SlicerErrors::PrintheadCollision {
+                            layer,
+                            move_index,
+                            part: part.to_string(),
+                        })
+                    } else {
+                        None
+                    }
+                })
+            });
+
+            collisions.extend(collision);
+        }
+
+        match cmd {
+            Command::MoveAndExtrude { width, .. }
+            | Command::MoveAndExtrudeFiber { width, .. }
+            | Command::MoveAndExtrudeSpiral { width, .. }
+            | Command::Arc { width, .. } => height_map.deposit(start, end, *width, current_z),
+            _ => {}
+        }
+
+        current_pos = end;
+        move_index += 1;
+    }
+
+    collisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wall(height: usize) -> Vec<Command> {
+        let mut cmds = vec![];
+        for layer in 0..height {
+            cmds.push(Command::LayerChange {
+                z: (layer + 1) as f32 * 0.2,
+                index: layer,
+            });
+            cmds.push(Command::MoveAndExtrude {
+                start: Coord { x: 100.0, y: 90.0 },
+                end: Coord { x: 100.0, y: 110.0 },
+                thickness: 0.2,
+                width: 0.4,
+            });
+        }
+        cmds
+    }
+
+    #[test]
+    fn vertical_head_does_not_collide() {
+        let mut cmds = wall(20);
+        cmds.insert(0, Command::SetToolAxis { axis: Vec3::Z });
+        cmds.push(Command::MoveTo {
+            end: Coord { x: 101.0, y: 100.0 },
+        });
+
+        assert!(check_collisions(&cmds, &Settings::default()).is_empty());
+    }
+
+    #[test]
+    fn tilted_head_hits_printed_wall() {
+        let mut cmds = wall(50);
+        cmds.push(Command::LayerChange { z: 5.0, index: 50 });
+        cmds.push(Command::MoveTo {
+            end: Coord { x: 102.0, y: 100.0 },
+        });
+        cmds.push(Command::SetToolAxis {
+            axis: Vec3::new(-0.5, 0.0, 0.75_f32.sqrt()),
+        });
+        cmds.push(Command::MoveTo {
+            end: Coord { x: 102.0, y: 101.0 },
+        });
+
+        let collisions = check_collisions(&cmds, &Settings::default());
+        assert_eq!(
+            collisions,
+            vec![SlicerErrors::PrintheadCollision {
+                layer: 50,
+                move_index: 1,
+                part: HeadPart::Nozzle.to_string(),
+            }]
+        );
+    }
+}
@@ -1,25 +1,27 @@
 use std::collections::HashMap;
 
 use ordered_float::OrderedFloat;
+use shared::process::Process;
 
 use crate::optimizer::*;
 use crate::*;
 
 pub trait CommandPass {
-    fn pass(cmds: &mut Vec<Command>, settings: &Settings);
+    fn pass(cmds: &mut Vec<Command>, settings: &Settings, process: &Process);
 }
 
 pub struct OptimizePass {}
 
 impl CommandPass for OptimizePass {
-    fn pass(cmds: &mut Vec<Command>, settings: &Settings) {
+    fn pass(cmds: &mut Vec<Command>, settings: &Settings, process: &Process) {
         let mut size = cmds.len();
 
         while {
             //arc_optomizer(cmds);
             state_optomizer(cmds);
             unary_optimizer(cmds);
-            binary_optimizer(cmds, settings);
+            //steps at every layer change, later rounds only add steps past the end of the stage
+            binary_optimizer(cmds, settings, process);
 
             cmds.len() != size
         } {
@@ -31,142 +33,22 @@ impl CommandPass for OptimizePass {
 pub struct SlowDownLayerPass {}
 
 impl CommandPass for SlowDownLayerPass {
-    fn pass(cmds: &mut Vec<Command>, settings: &Settings) {
-        let mut layer_height = 0.0;
+    fn pass(cmds: &mut Vec<Command>, settings: &Settings, process: &Process) {
         //Slow down on small layers
-        let mut current_speed = 0.0;
-        let mut current_pos = Coord { x: 0.0, y: 0.0 };
-
         {
-            let reduction: Vec<(f32, usize, usize)> = cmds
-                .iter()
-                .enumerate()
-                .batching(|it| {
-                    //map from speed to length at that speed
-                    let mut map: HashMap<OrderedFloat<f32>, f32> = HashMap::new();
-                    let mut non_move_time = 0.0;
-
-                    let start_z_height = layer_height;
-                    let mut return_none = false;
-
-                    let mut start_index = None;
-                    let mut end_index = 0;
-                    while layer_height == start_z_height && !return_none {
-                        if let Some((index, cmd)) = it.next() {
-                            start_index = start_index.or(Some(index));
-                            end_index = index;
-                            match cmd {
-                                Command::MoveTo { end } => {
-                                    let x_diff = end.x - current_pos.x;
-                                    let y_diff = end.y - current_pos.y;
-                                    let d = ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
-                                    current_pos = *end;
-                                    if current_speed != 0.0 {
-                                        non_move_time += d / current_speed;
-                                    }
-                                }
-                                Command::MoveAndExtrude {
-                                    start,
-                                    end,
-                                    width: _width,
-                                    thickness: _thickness,
-                                }
-                                | Command::MoveAndExtrudeFiber {
-                                    start,
-                                    end,
-                                    width: _width,
-                                    thickness: _thickness,
-                                    ..
-                                } => {
-                                    let x_diff = end.x - start.x;
-                                    let y_diff = end.y - start.y;
-                                    let d = ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
-                                    current_pos = *end;
-                                    *map.entry(OrderedFloat(current_speed)).or_insert(0.0) += d;
-                                }
-                                Command::SetState { new_state } => {
-                                    if let Some(speed) = new_state.movement_speed {
-                                        current_speed = speed
-                                    }
-                                    if new_state.retract != RetractionType::NoRetract {
-                                        non_move_time +=
-                                            settings.retract_length / settings.retract_speed;
-                                        non_move_time +=
-                                            settings.retract_lift_z / settings.speed.travel;
-                                    }
-                                }
-                                Command::Delay { msec } => {
-                                    non_move_time += *msec as f32 / 1000.0;
-                                }
-                                Command::Arc {
-                                    start, end, center, ..
-                                } => {
-                                    let x_diff = end.x - start.x;
-                                    let y_diff = end.y - start.y;
-                                    let cord_length =
-                                        ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
-                                    let x_diff_r = end.x - center.x;
-                                    let y_diff_r = end.y - center.y;
-                                    let radius =
-                                        ((x_diff_r * x_diff_r) + (y_diff_r * y_diff_r)).sqrt();
-
-                                    //Divide the chord length by double the radius.
-                                    let t = cord_length / (2.0 * radius);
-                                    //println!("{}",t);
-                                    //Find the inverse sine of the result (in radians).
-                                    //Double the result of the inverse sine to get the central angle in radians.
-                                    let central = t.asin() * 2.0;
-                                    //Once you have the central angle in radians, multiply it by the radius to get the arc length.
-                                    let extrusion_length = central * radius;
-
-                                    current_pos = *end;
-                                    *map.entry(OrderedFloat(current_speed)).or_insert(0.0) +=
-                                        extrusion_length;
-                                }
-                                Command::LayerChange { z, .. } => {
-                                    layer_height = *z;
-                                }
-                                Command::NoAction
-                                | Command::ChangeObject { .. }
-                                | Command::ChangeType { .. } => {}
-                            }
-                        } else {
-                            return_none = true;
-                        }
-                    }
+            let reduction: Vec<(f32, usize, usize)> = layer_times(cmds, settings)
+                .into_iter()
+                .filter_map(|layer| {
+                    process.step();
 
-                    if return_none {
-                        if map.is_empty() {
-                            None
-                        } else {
-                            Some((
-                                map,
-                                non_move_time,
-                                start_index
-                                    .expect("For map to have values, start index must be set"),
-                                end_index,
-                            ))
-                        }
-                    } else {
-                        Some((
-                            map,
-                            non_move_time,
-                            start_index
-                                .expect("For return_none to be false, start index must be set"),
-                            end_index,
-                        ))
-                    }
-                })
-                .filter_map(|(map, time, start, end)| {
-                    let mut total_time = time
-                        + map
-                            .iter()
-                            .map(|(speed, len)| len / speed.into_inner())
-                            .sum::<f32>();
+                    let mut total_time = layer.total_time();
 
                     let min_time = settings.fan.slow_down_threshold;
-                    if total_time < min_time && !map.is_empty() {
-                        let mut sorted = map.into_iter().collect::<Vec<(OrderedFloat<f32>, f32)>>();
+                    if layer.is_short(settings) {
+                        let mut sorted = layer
+                            .lengths
+                            .into_iter()
+                            .collect::<Vec<(OrderedFloat<f32>, f32)>>();
                         sorted.sort_by(|a, b| a.0.cmp(&b.0));
 
                         let max_speed: f32;
@@ -190,7 +72,7 @@ impl CommandPass for SlowDownLayerPass {
                                 //println!("tt: {:.5}", total_time);
                             }
                         }
-                        Some((max_speed, start, end))
+                        Some((max_speed, layer.start, layer.end))
                     } else {
                         None
                     }
@@ -211,3 +93,391 @@ impl CommandPass for SlowDownLayerPass {
         }
     }
 }
+
+pub struct FanPass {}
+
+impl CommandPass for FanPass {
+    fn pass(cmds: &mut Vec<Command>, settings: &Settings, process: &Process) {
+        let fan = &settings.fan;
+
+        //layers that are slowed down to cool get the maximum fan speed
+        let short_layers: Vec<(usize, usize)> = layer_times(cmds, settings)
+            .into_iter()
+            .filter(|layer| layer.is_short(settings))
+            .map(|layer| (layer.start, layer.end))
+            .collect();
+
+        let mut layer_fan = 0.0;
+        let mut disabled = true;
+        let mut bridging = false;
+        //fan speed requested by the moves, like the overrides of overhanging perimeters, zero once they end
+        let mut move_fan = 0.0;
+        let mut current_fan = None;
+
+        let mut fan_cmds = Vec::with_capacity(cmds.len());
+
+        for (index, mut cmd) in cmds.drain(..).enumerate() {
+            match &mut cmd {
+                Command::LayerChange { index: layer, .. } => {
+                    let short = short_layers
+                        .iter()
+                        .any(|(start, end)| (*start..=*end).contains(&(index + 1)));
+
+                    disabled = *layer < fan.disable_fan_for_layers;
+                    layer_fan = if short {
+                        fan.layer_fan_speed(*layer).max(fan.max_fan_speed)
+                    } else {
+                        fan.layer_fan_speed(*layer)
+                    };
+                    move_fan = 0.0;
+
+                    process.step();
+                }
+                Command::ChangeType { print_type } => {
+                    bridging = *print_type == MovePrintType::Bridging;
+                }
+                Command::SetState { new_state } => {
+                    if let Some(fan_speed) = new_state.fan_speed.take() {
+                        move_fan = fan_speed;
+                    }
+                }
+                _ => {}
+            }
+
+            fan_cmds.push(cmd);
+
+            let fan_speed = if disabled {
+                0.0
+            } else {
+                let bridge_fan = fan.bridge_fan_speed.filter(|_| bridging).unwrap_or(0.0);
+
+                layer_fan.max(move_fan).max(bridge_fan)
+            };
+
+            if current_fan != Some(fan_speed) {
+                fan_cmds.push(fan_state(fan_speed));
+                current_fan = Some(fan_speed);
+            }
+        }
+
+        *cmds = fan_cmds;
+    }
+}
+
+///The estimated print time of a layer
+struct LayerTime {
+    ///Map from speed to the length extruded at that speed
+    lengths: HashMap<OrderedFloat<f32>, f32>,
+
+    ///Time spent on travels, retractions and delays
+    non_move_time: f32,
+
+    ///Index of the first command of the layer
+    start: usize,
+
+    ///Index of the command ending the layer
+    end: usize,
+}
+
+impl LayerTime {
+    fn total_time(&self) -> f32 {
+        self.non_move_time
+            + self
+                .lengths
+                .iter()
+                .map(|(speed, len)| len / speed.into_inner())
+                .sum::<f32>()
+    }
+
+    ///Layers printed faster than the slow down threshold are slowed down to cool
+    fn is_short(&self, settings: &Settings) -> bool {
+        !self.lengths.is_empty() && self.total_time() < settings.fan.slow_down_threshold
+    }
+}
+
+fn layer_times(cmds: &[Command], settings: &Settings) -> Vec<LayerTime> {
+    let mut layer_height = 0.0;
+    let mut current_speed = 0.0;
+    let mut current_pos = Coord { x: 0.0, y: 0.0 };
+
+    cmds.iter()
+        .enumerate()
+        .batching(|it| {
+            //map from speed to length at that speed
+            let mut map: HashMap<OrderedFloat<f32>, f32> = HashMap::new();
+            let mut non_move_time = 0.0;
+
+            let start_z_height = layer_height;
+            let mut return_none = false;
+
+            let mut start_index = None;
+            let mut end_index = 0;
+            while layer_height == start_z_height && !return_none {
+                if let Some((index, cmd)) = it.next() {
+                    start_index = start_index.or(Some(index));
+                    end_index = index;
+                    match cmd {
+                        Command::MoveTo { end } => {
+                            let x_diff = end.x - current_pos.x;
+                            let y_diff = end.y - current_pos.y;
+                            let d = ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
+                            current_pos = *end;
+                            if current_speed != 0.0 {
+                                non_move_time += d / current_speed;
+                            }
+                        }
+                        Command::MoveAndExtrude {
+                            start,
+                            end,
+                            width: _width,
+                            thickness: _thickness,
+                        }
+                        | Command::MoveAndExtrudeFiber {
+                            start,
+                            end,
+                            width: _width,
+                            thickness: _thickness,
+                            ..
+                        }
+                        | Command::MoveAndExtrudeSpiral {
+                            start,
+                            end,
+                            width: _width,
+                            thickness: _thickness,
+                            ..
+                        } => {
+                            let x_diff = end.x - start.x;
+                            let y_diff = end.y - start.y;
+                            let d = ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
+                            current_pos = *end;
+                            *map.entry(OrderedFloat(current_speed)).or_insert(0.0) += d;
+                        }
+                        Command::SetState { new_state } => {
+                            if let Some(speed) = new_state.movement_speed {
+                                current_speed = speed
+                            }
+                            if new_state.retract != RetractionType::NoRetract {
+                                non_move_time += settings.retract_length / settings.retract_speed;
+                                non_move_time += settings.retract_lift_z / settings.speed.travel;
+                            }
+                        }
+                        Command::Delay { msec } => {
+                            non_move_time += *msec as f32 / 1000.0;
+                        }
+                        Command::Arc {
+                            start, end, center, ..
+                        } => {
+                            let x_diff = end.x - start.x;
+                            let y_diff = end.y - start.y;
+                            let cord_length = ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
+                            let x_diff_r = end.x - center.x;
+                            let y_diff_r = end.y - center.y;
+                            let radius = ((x_diff_r * x_diff_r) + (y_diff_r * y_diff_r)).sqrt();
+
+                            //Divide the chord length by double the radius.
+                            let t = cord_length / (2.0 * radius);
+                            //println!("{}",t);
+                            //Find the inverse sine of the result (in radians).
+                            //Double the result of the inverse sine to get the central angle in radians.
+                            let central = t.asin() * 2.0;
+                            //Once you have the central angle in radians, multiply it by the radius to get the arc length.
+                            let extrusion_length = central * radius;
+
+                            current_pos = *end;
+                            *map.entry(OrderedFloat(current_speed)).or_insert(0.0) +=
+                                extrusion_length;
+                        }
+                        Command::LayerChange { z, .. } => {
+                            layer_height = *z;
+                        }
+                        Command::NoAction
+                        | Command::ChangeObject { .. }
+                        | Command::ChangeType { .. }
+                        | Command::SetToolAxis { .. } => {}
+                    }
+                } else {
+                    return_none = true;
+                }
+            }
+
+            if return_none {
+                if map.is_empty() {
+                    None
+                } else {
+                    Some((
+                        map,
+                        non_move_time,
+                        start_index.expect("For map to have values, start index must be set"),
+                        end_index,
+                    ))
+                }
+            } else {
+                Some((
+                    map,
+                    non_move_time,
+                    start_index.expect("For return_none to be false, start index must be set"),
+                    end_index,
+                ))
+            }
+        })
+        .map(|(lengths, non_move_time, start, end)| LayerTime {
+            lengths,
+            non_move_time,
+            start,
+            end,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extrude(x: f32) -> Command {
+        Command::MoveAndExtrude {
+            start: Coord { x: 0.0, y: 0.0 },
+            end: Coord { x, y: 0.0 },
+            thickness: 0.2,
+            width: 0.4,
+        }
+    }
+
+    fn layer(index: usize) -> Vec<Command> {
+        vec![
+            Command::LayerChange {
+                z: (index + 1) as f32 * 0.2,
+                index,
+            },
+            Command::SetState {
+                new_state: StateChange {
+                    movement_speed: Some(10.0),
+                    ..Default::default()
+                },
+            },
+            Command::ChangeType {
+                print_type: MovePrintType::WallOuter,
+            },
+            extrude(50.0),
+        ]
+    }
+
+    fn fan_speeds(cmds: &[Command]) -> Vec<f32> {
+        cmds.iter()
+            .filter_map(|cmd| match cmd {
+                Command::SetState { new_state } => new_state.fan_speed,
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn commands() -> Vec<Command> {
+        let mut cmds = vec![Command::ChangeObject { object: 0 }];
+
+        cmds.extend(layer(0));
+        cmds.extend(layer(1));
+        cmds.extend(layer(2));
+        cmds.extend([
+            Command::ChangeType {
+                print_type: MovePrintType::Bridging,
+            },
+            extrude(10.0),
+            Command::ChangeType {
+                print_type: MovePrintType::WallOuter,
+            },
+            extrude(10.0),
+        ]);
+        cmds.extend(layer(3));
+        cmds.extend([
+            Command::SetState {
+                new_state: StateChange {
+                    fan_speed: Some(90.0),
+                    ..Default::default()
+                },
+            },
+            extrude(10.0),
+            Command::SetState {
+                new_state: StateChange {
+                    fan_speed: Some(30.0),
+                    ..Default::default()
+                },
+            },
+            extrude(10.0),
+        ]);
+
+        cmds
+    }
+
+    #[test]
+    fn fan_ramps_up_with_bridge_and_overhang_overrides() {
+        let mut settings = Settings::default();
+        settings.fan.fan_speed = 60.0;
+        settings.fan.slow_down_threshold = 0.0;
+
+        let mut cmds = commands();
+        FanPass::pass(&mut cmds, &settings, &Process::new());
+
+        assert_eq!(
+            fan_speeds(&cmds),
+            vec![0.0, 20.0, 40.0, 100.0, 40.0, 60.0, 90.0, 60.0]
+        );
+    }
+
+    #[test]
+    fn short_layers_get_the_maximum_fan_speed() {
+        let mut settings = Settings::default();
+        settings.fan.fan_speed = 60.0;
+        settings.fan.slow_down_threshold = 1000.0;
+
+        let mut cmds = commands();
+        FanPass::pass(&mut cmds, &settings, &Process::new());
+
+        //the first layer keeps the fan off
+        assert_eq!(fan_speeds(&cmds), vec![0.0, 100.0]);
+    }
+
+    #[test]
+    fn fan_returns_to_the_ramp_after_an_overhang_above_the_raft() {
+        let raft = RaftSettings::default();
+        let mut settings = Settings {
+            raft: Some(raft.clone()),
+            overhangs: Some(OverhangSettings::default()),
+            ..Settings::default()
+        };
+        settings.fan.fan_speed = 60.0;
+        settings.fan.slow_down_threshold = 0.0;
+        //the first layers of the object still ramp up the fan
+        settings.fan.ramp_up_layers = raft.layer_count() + 4;
+
+        //the second layer of the object, the index counts the raft layers
+        let layer = settings.raft_layers() + 1;
+        let height = settings.raft_height() + 0.4;
+        let layer_settings = settings.get_printed_layer_settings(layer, height - 0.1);
+
+        let wall = |x: f32, y: f32, overhang: Option<usize>| Move {
+            end: Coord { x, y },
+            width: 0.4,
+            move_type: MoveType::WithoutFiber(MovePrintType::WallOuter),
+            overhang,
+        };
+        let chain = MoveChain {
+            start_point: Coord { x: 0.0, y: 0.0 },
+            moves: vec![wall(10.0, 0.0, Some(3)), wall(10.0, 10.0, None)],
+            is_loop: false,
+        };
+
+        let mut cmds = vec![
+            Command::ChangeObject { object: 0 },
+            Command::LayerChange {
+                z: height,
+                index: layer,
+            },
+        ];
+        cmds.extend(chain.create_commands(&layer_settings, 0.2));
+
+        FanPass::pass(&mut cmds, &settings, &Process::new());
+
+        let layer_fan = settings.fan.layer_fan_speed(layer);
+        assert!(layer_fan > 0.0 && layer_fan < 60.0);
+        assert_eq!(fan_speeds(&cmds), vec![0.0, layer_fan, 100.0, layer_fan]);
+    }
+}
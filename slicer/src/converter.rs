@@ -1,5 +1,7 @@
 use std::io::{BufWriter, Write};
 
+use geo::Coord;
+
 use super::{settings::Settings, Command, RetractionType};
 
 pub fn convert(
@@ -69,25 +71,27 @@ pub fn convert(
                 width,
                 ..
             } => {
-                let x_diff = end.x - start.x;
-                let y_diff = end.y - start.y;
-                let length = ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
-
-                //let extrusion_width = width + (thickness * (1.0 - std::f32::consts::FRAC_PI_4));
-
-                let extrusion_volume = (((width - thickness) * thickness)
-                    + (std::f32::consts::PI * (thickness / 2.0) * (thickness / 2.0)))
-                    * length;
-                /*let extrusion_volume = width*thickness*length;*/
-
-                let filament_area = (std::f32::consts::PI
-                    * settings.filament.diameter
-                    * settings.filament.diameter)
-                    / 4.0;
-                let extrude = extrusion_volume / filament_area;
+                let extrude = filament_length(*start, *end, *thickness, *width, settings);
 
                 writeln!(write_buf, "G1 X{:.5} Y{:.5} E{:.5}", end.x, end.y, extrude)?;
             }
+            Command::MoveAndExtrudeSpiral {
+                start,
+                end,
+                end_z,
+                thickness,
+                width,
+                ..
+            } => {
+                let extrude = filament_length(*start, *end, *thickness, *width, settings);
+
+                current_z = *end_z;
+                writeln!(
+                    write_buf,
+                    "G1 X{:.5} Y{:.5} Z{:.5} E{:.5}",
+                    end.x, end.y, end_z, extrude
+                )?;
+            }
             Command::SetState { new_state } => {
                 match &new_state.retract {
                     RetractionType::NoRetract => {
@@ -273,6 +277,12 @@ pub fn convert(
             Command::ChangeType { print_type } => {
                 writeln!(write_buf, ";TYPE:{}", print_type)?;
             }
+            Command::SetToolAxis { axis } => {
+                //A tilts away from Z, C rotates around Z
+                let a = axis.z.clamp(-1.0, 1.0).acos().to_degrees();
+                let c = axis.y.atan2(axis.x).to_degrees();
+                writeln!(write_buf, "G1 A{:.5} C{:.5}", a, c)?;
+            }
         }
     }
 
@@ -294,6 +304,31 @@ pub fn convert(
     Ok(())
 }
 
+///The length of filament extruded by a straight move
+fn filament_length(
+    start: Coord<f32>,
+    end: Coord<f32>,
+    thickness: f32,
+    width: f32,
+    settings: &Settings,
+) -> f32 {
+    let x_diff = end.x - start.x;
+    let y_diff = end.y - start.y;
+    let length = ((x_diff * x_diff) + (y_diff * y_diff)).sqrt();
+
+    //let extrusion_width = width + (thickness * (1.0 - std::f32::consts::FRAC_PI_4));
+
+    let extrusion_volume = (((width - thickness) * thickness)
+        + (std::f32::consts::PI * (thickness / 2.0) * (thickness / 2.0)))
+        * length;
+    /*let extrusion_volume = width*thickness*length;*/
+
+    let filament_area =
+        (std::f32::consts::PI * settings.filament.diameter * settings.filament.diameter) / 4.0;
+
+    extrusion_volume / filament_area
+}
+
 fn convert_instructions(
     mut instructions: String,
     current_z_height: f32,
@@ -302,7 +337,7 @@ fn convert_instructions(
     current_object: Option<usize>,
     settings: &Settings,
 ) -> String {
-    let layer_settings = settings.get_layer_settings(layer_count, current_z_height);
+    let layer_settings = settings.get_printed_layer_settings(layer_count, current_z_height);
 
     instructions = instructions.replace(
         "[Extruder Temperature]",
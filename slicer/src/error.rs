@@ -102,6 +102,33 @@ pub enum SlicerErrors {
         filepath: String,
     },
 
+    ///The printhead would hit already printed material
+    PrintheadCollision {
+        ///The layer index of the move
+        layer: usize,
+
+        ///The index of the move within the layer
+        move_index: usize,
+
+        ///The part of the printhead that collides
+        part: String,
+    },
+
+    ///The printhead would hit the print bed
+    PrintheadBedCollision {
+        ///The layer index of the move
+        layer: usize,
+
+        ///The index of the move within the layer
+        move_index: usize,
+
+        ///The part of the printhead that collides
+        part: String,
+    },
+
+    ///The slicing process was cancelled
+    Cancelled,
+
     ///Another error, here for plugins to use
     UnspecifiedError(String),
 }
@@ -176,6 +203,15 @@ impl SlicerErrors {
             SlicerErrors::MovesOutsideBuildArea => {
                 (0x1014,"Slicer generated move outside build area.".to_string())
             }
+            SlicerErrors::PrintheadCollision { layer, move_index, part } => {
+                (0x1015,format!("The {} collides with printed material on layer {} at move {}.",part, layer, move_index))
+            }
+            SlicerErrors::PrintheadBedCollision { layer, move_index, part } => {
+                (0x1016,format!("The {} collides with the print bed on layer {} at move {}.",part, layer, move_index))
+            }
+            SlicerErrors::Cancelled => {
+                (0x1017,"Slicing was cancelled.".to_string())
+            }
         }
     }
 }
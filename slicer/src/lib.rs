@@ -1,8 +1,16 @@
 mod settings;
 
-use command_pass::{CommandPass, OptimizePass, SlowDownLayerPass};
+use command_pass::{CommandPass, FanPass, OptimizePass, SlowDownLayerPass};
 use glam::{Vec3, Vec4};
-use plotter::{convert_objects_into_moves, polygon_operations::PolygonOperations};
+use plotter::{
+    convert_objects_into_moves,
+    fuzzy_skin::jitter_outer_walls,
+    polygon_operations::{
+        fixed_point_in_ring, fixed_ring_area, FixedMultiPolygon, PolygonOperations, ToFixed,
+        ToFloat,
+    },
+    support::{block_support, enforce_support},
+};
 use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 pub use settings::*;
 use shared::{process::Process, SliceInput};
@@ -10,7 +18,10 @@ use slice_pass::*;
 use strum_macros::{EnumCount, EnumIter, EnumString};
 use tower::create_towers;
 
+mod adaptive;
+mod cache;
 mod calculation;
+mod collision;
 mod command_pass;
 mod converter;
 mod error;
@@ -23,11 +34,11 @@ mod tower;
 mod utils;
 mod warning;
 
+pub use cache::SliceCache;
 pub use converter::convert;
-
-use error::SlicerErrors;
+pub use error::SlicerErrors;
 use geo::{
-    Contains, Coord, LineString, MultiLineString, MultiPolygon, Polygon, SimplifyVw,
+    BoundingRect, Coord, LineString, MultiLineString, MultiPolygon, Polygon, SimplifyVw,
     SimplifyVwPreserve,
 };
 
@@ -39,35 +50,122 @@ pub struct SliceResult {
     pub moves: Vec<Command>,
     pub calculated_values: CalculatedValues,
     pub settings: Settings,
+    ///Printhead collisions found in the final moves, the slice is still usable for previewing them
+    pub collisions: Vec<SlicerErrors>,
 }
 
+///Slices the input, the towers and slices are taken from the cache if the meshes and their settings didn't change
 pub fn slice(
     input: SliceInput,
     settings: &Settings,
     process: &Process,
+    cache: &mut SliceCache,
 ) -> Result<SliceResult, SlicerErrors> {
     let max = input
         .objects
         .iter()
         .fold(Vec3::NEG_INFINITY, |max, obj| max.max(obj.min_max().1));
 
-    process.set_task("Creating Towers".to_string());
-    process.set_progress(0.1);
+    let towers_key = cache::towers_key(&input);
 
-    let towers = create_towers(&input.objects)?;
-    let towers_masks = create_towers(&input.masks)?;
+    if cache.towers.as_ref().map(|towers| towers.key) != Some(towers_key) {
+        process.start_stage("Creating Towers".to_string(), 0.0, 0.05, 0);
 
-    process.set_task("Slicing".to_string());
-    process.set_progress(0.2);
-    println!("Max: {:?}", max);
-    let mut objects = slicing::slice(&towers, max.z, settings)?;
-    let mut masks = slicing::slice(&towers_masks, max.z, settings)?;
+        cache.towers = Some(cache::CachedTowers {
+            key: towers_key,
+            objects: create_towers(&input.objects)?,
+            masks: create_towers(&input.masks)?,
+            support_enforcers: create_towers(&input.support_enforcers)?,
+            support_blockers: create_towers(&input.support_blockers)?,
+        });
+        check_cancelled(process)?;
+    }
+
+    let towers = cache.towers.as_ref().expect("Towers are cached above");
+    let slices_key = cache::slices_key(towers_key, settings);
+
+    if cache.slices.as_ref().map(|slices| slices.key) != Some(slices_key) {
+        process.start_stage("Calculating Layer Heights".to_string(), 0.05, 0.1, 0);
+        let layer_heights = adaptive::compute_layer_heights(&towers.objects, max.z, settings);
+        check_cancelled(process)?;
+
+        //Every layer is stepped once when walking the tower and once when building the polygons
+        let estimated_layers = layer_heights
+            .as_ref()
+            .map(|heights| heights.len())
+            .unwrap_or_else(|| (max.z / settings.layer_height).ceil() as usize);
+
+        process.start_stage(
+            "Slicing".to_string(),
+            0.1,
+            0.2,
+            2 * estimated_layers * towers.objects.len(),
+        );
+        let objects = slicing::slice(
+            &towers.objects,
+            max.z,
+            layer_heights.as_deref(),
+            settings,
+            process,
+        )?;
+
+        process.start_stage(
+            "Slicing Masks".to_string(),
+            0.2,
+            0.22,
+            2 * estimated_layers
+                * (towers.masks.len()
+                    + towers.support_enforcers.len()
+                    + towers.support_blockers.len()),
+        );
+        let masks = slicing::slice(
+            &towers.masks,
+            max.z,
+            layer_heights.as_deref(),
+            settings,
+            process,
+        )?;
+        let support_enforcers = slicing::slice(
+            &towers.support_enforcers,
+            max.z,
+            layer_heights.as_deref(),
+            settings,
+            process,
+        )?;
+        let support_blockers = slicing::slice(
+            &towers.support_blockers,
+            max.z,
+            layer_heights.as_deref(),
+            settings,
+            process,
+        )?;
+
+        cache.slices = Some(cache::CachedSlices {
+            key: slices_key,
+            objects,
+            masks,
+            support_enforcers,
+            support_blockers,
+        });
+    }
 
-    process.set_task("Cropping Masks".to_string());
-    process.set_progress(0.5);
+    let slices = cache.slices.as_ref().expect("Slices are cached above");
+
+    //The cached slices can be from a slice with different settings of later stages
+    let mut objects = slices.objects.clone();
+    let mut masks = slices.masks.clone();
+
+    objects
+        .iter_mut()
+        .chain(masks.iter_mut())
+        .flat_map(|object| object.layers.iter_mut())
+        .for_each(|slice| slice.update_layer_settings(settings));
+
+    process.start_stage("Cropping Masks".to_string(), 0.22, 0.23, 0);
     mask::crop_masks(&objects, &mut masks, max.z);
     mask::randomize_mask_underlaps(&mut masks);
     handle_masks(&mut masks, settings, process)?;
+    check_cancelled(process)?;
 
     for object in objects.iter_mut() {
         object
@@ -85,147 +183,378 @@ pub fn slice(
             });
     }
 
-    let mut moves = generate_moves(objects, settings, process)?;
+    let mut moves = generate_moves(
+        objects,
+        &masks,
+        &slices.support_enforcers,
+        &slices.support_blockers,
+        settings,
+        process,
+    )?;
+
+    let layer_count = moves
+        .iter()
+        .filter(|cmd| matches!(cmd, Command::LayerChange { .. }))
+        .count();
 
-    process.set_task("Optimizing".to_string());
-    process.set_progress(0.6);
-    OptimizePass::pass(&mut moves, settings);
+    process.start_stage("Optimizing".to_string(), 0.6, 0.68, layer_count);
+    OptimizePass::pass(&mut moves, settings, process);
+    check_cancelled(process)?;
 
-    process.set_task("Slowing Down Layers".to_string());
-    process.set_progress(0.7);
-    SlowDownLayerPass::pass(&mut moves, settings);
+    process.start_stage("Setting Fan Speeds".to_string(), 0.68, 0.69, layer_count);
+    FanPass::pass(&mut moves, settings, process);
+    check_cancelled(process)?;
 
-    process.set_task("Calculating Values".to_string());
-    process.set_progress(0.75);
+    process.start_stage("Slowing Down Layers".to_string(), 0.69, 0.7, layer_count);
+    SlowDownLayerPass::pass(&mut moves, settings, process);
+    check_cancelled(process)?;
+
+    process.start_stage("Checking Collisions".to_string(), 0.7, 0.72, 0);
+    let collisions = collision::check_collisions(&moves, settings);
+    check_cancelled(process)?;
+
+    process.start_stage("Calculating Values".to_string(), 0.72, 0.75, 0);
     let calculated_values = calculation::calculate_values(&moves, settings);
 
     Ok(SliceResult {
         moves,
         calculated_values,
         settings: settings.clone(),
+        collisions,
     })
 }
 
 fn generate_moves(
     mut objects: Vec<Object>,
+    masks: &[Object],
+    support_enforcers: &[Object],
+    support_blockers: &[Object],
     settings: &Settings,
     process: &Process,
 ) -> Result<Vec<Command>, SlicerErrors> {
     //Creates Support Towers
-    process.set_task("Creating Support Towers".to_string());
-    process.set_progress(0.3);
+    process.start_stage("Creating Support Towers".to_string(), 0.25, 0.26, 0);
     SupportTowerPass::pass(&mut objects, settings);
+    check_cancelled(process)?;
+
+    //Adds and removes support inside of the enforcers and blockers
+    if settings.support.is_some() {
+        process.start_stage("Applying Support Modifiers".to_string(), 0.26, 0.27, 0);
+        apply_support_modifiers(&mut objects, support_enforcers, support_blockers);
+        check_cancelled(process)?;
+    }
+
+    //Adds a raft below the objects
+    process.start_stage("Creating Raft".to_string(), 0.27, 0.275, 0);
+    RaftPass::pass(&mut objects, settings);
+    check_cancelled(process)?;
 
     //Adds a skirt
-    process.set_task("Creating Skirt".to_string());
+    process.start_stage("Creating Skirt".to_string(), 0.275, 0.28, 0);
     SkirtPass::pass(&mut objects, settings);
+    check_cancelled(process)?;
 
     //Adds a brim
-    process.set_task("Creating Brim".to_string());
+    process.start_stage("Creating Brim".to_string(), 0.28, 0.3, 0);
     BrimPass::pass(&mut objects, settings);
+    check_cancelled(process)?;
+
+    //Shrink layer
+    run_slice_pass::<ShrinkPass>(
+        &mut objects,
+        settings,
+        process,
+        "Shrinking Layers",
+        0.3,
+        0.32,
+    )?;
+
+    //Handle Perimeters
+    run_slice_pass::<PerimeterPass>(&mut objects, settings, process, "Perimeters", 0.32, 0.37)?;
+
+    //Move the start of the perimeter loops to the seams
+    run_slice_pass::<SeamPass>(&mut objects, settings, process, "Seams", 0.37, 0.373)?;
+
+    //Jitter the outer walls
+    if let Some(fuzzy_skin) = settings.fuzzy_skin.as_ref() {
+        run_fuzzy_skin(
+            &mut objects,
+            masks,
+            fuzzy_skin,
+            settings.raft_layers(),
+            process,
+            0.373,
+            0.376,
+        )?;
+    }
 
-    process.set_task("Generate Moves".to_string());
-    let v: Result<Vec<()>, SlicerErrors> = objects
-        .par_iter_mut()
-        .map(|object| {
-            let slices = &mut object.layers;
+    //Split the perimeters hanging over the layer below into overhang classes
+    run_slice_pass::<OverhangPass>(&mut objects, settings, process, "Overhangs", 0.376, 0.38)?;
+
+    //Fill the gaps left between the perimeters
+    run_slice_pass::<GapFillPass>(&mut objects, settings, process, "Gap Fill", 0.38, 0.39)?;
+
+    //Handle Bridging
+    run_slice_pass::<BridgingPass>(&mut objects, settings, process, "Bridging", 0.39, 0.41)?;
+
+    //Handle Top Layer
+    run_slice_pass::<TopLayerPass>(&mut objects, settings, process, "Top Layers", 0.41, 0.44)?;
+
+    //Handle Top And Bottom Layers
+    run_slice_pass::<TopAndBottomLayersPass>(
+        &mut objects,
+        settings,
+        process,
+        "Top And Bottom Layers",
+        0.44,
+        0.47,
+    )?;
+
+    //Handle Support
+    run_slice_pass::<SupportPass>(&mut objects, settings, process, "Support", 0.47, 0.49)?;
+
+    //Lightning Infill
+    run_slice_pass::<LightningFillPass>(
+        &mut objects,
+        settings,
+        process,
+        "Lightning Infill",
+        0.49,
+        0.5,
+    )?;
+
+    //Adaptive Cubic Infill
+    run_slice_pass::<AdaptiveCubicFillPass>(
+        &mut objects,
+        settings,
+        process,
+        "Adaptive Cubic Infill",
+        0.5,
+        0.51,
+    )?;
+
+    //Fill Remaining areas
+    run_slice_pass::<FillAreaPass>(&mut objects, settings, process, "Infill", 0.51, 0.53)?;
+
+    //Spiral Vase
+    run_slice_pass::<SpiralVasePass>(&mut objects, settings, process, "Spiral Vase", 0.53, 0.54)?;
+
+    //Order the move chains
+    run_slice_pass::<OrderPass>(
+        &mut objects,
+        settings,
+        process,
+        "Ordering Chains",
+        0.54,
+        0.56,
+    )?;
+
+    let layer_count = objects.iter().map(|object| object.layers.len()).sum();
+
+    process.start_stage("Converting Moves".to_string(), 0.56, 0.6, layer_count);
+    let moves = convert_objects_into_moves(objects, settings, process);
+    check_cancelled(process)?;
+
+    Ok(moves)
+}
 
-            //Shrink layer
-            ShrinkPass::pass(slices, settings)?;
+///Runs a slice pass on every object, the progress moves from start to end with the processed layers
+fn run_slice_pass<P: SlicePass>(
+    objects: &mut [Object],
+    settings: &Settings,
+    process: &Process,
+    task: &str,
+    start: f32,
+    end: f32,
+) -> Result<(), SlicerErrors> {
+    let layer_count = objects.iter().map(|object| object.layers.len()).sum();
 
-            //Handle Perimeters
-            PerimeterPass::pass(slices, settings)?;
+    process.start_stage(
+        format!("Generating Moves: {}", task),
+        start,
+        end,
+        layer_count,
+    );
 
-            //Handle Bridging
-            BridgingPass::pass(slices, settings)?;
+    objects
+        .par_iter_mut()
+        .try_for_each(|object| P::pass(&mut object.layers, settings, process))?;
 
-            //Handle Top Layer
-            TopLayerPass::pass(slices, settings)?;
+    check_cancelled(process)
+}
 
-            //Handle Top And Bottom Layers
-            TopAndBottomLayersPass::pass(slices, settings)?;
+///Forces the support inside of the enforcers and removes it inside of the blockers
+fn apply_support_modifiers(
+    objects: &mut [Object],
+    support_enforcers: &[Object],
+    support_blockers: &[Object],
+) {
+    let layer_areas = |modifiers: &[Object], layer_count: usize| -> Vec<FixedMultiPolygon> {
+        (0..layer_count)
+            .map(|index| {
+                modifiers
+                    .iter()
+                    .filter_map(|modifier| modifier.layers.get(index))
+                    .fold(FixedMultiPolygon::new(vec![]), |area, modifier_layer| {
+                        area.union_with(&modifier_layer.main_polygon)
+                    })
+            })
+            .collect()
+    };
 
-            //Handle Support
-            SupportPass::pass(slices, settings)?;
+    objects.par_iter_mut().for_each(|object| {
+        let layer_count = object.layers.len();
 
-            //Lightning Infill
-            LightningFillPass::pass(slices, settings)?;
+        if !support_enforcers.is_empty() {
+            enforce_support(
+                &mut object.layers,
+                &layer_areas(support_enforcers, layer_count),
+            );
+        }
 
-            //Fill Remaining areas
-            FillAreaPass::pass(slices, settings)?;
+        if !support_blockers.is_empty() {
+            block_support(
+                &mut object.layers,
+                &layer_areas(support_blockers, layer_count),
+            );
+        }
+    });
+}
 
-            //Order the move chains
-            OrderPass::pass(slices, settings)
-        })
-        .collect();
+///Jitters the outer walls of every object, only inside of the masks if the fuzzy skin is restricted to them
+///The masks are sliced without the raft, so the raft layers are skipped to keep the indices in line
+fn run_fuzzy_skin(
+    objects: &mut [Object],
+    masks: &[Object],
+    fuzzy_skin: &FuzzySkinSettings,
+    raft_layers: usize,
+    process: &Process,
+    start: f32,
+    end: f32,
+) -> Result<(), SlicerErrors> {
+    let layer_count = objects
+        .iter()
+        .map(|object| object.layers.len().saturating_sub(raft_layers))
+        .sum();
 
-    process.set_progress(0.5);
+    process.start_stage(
+        "Generating Moves: Fuzzy Skin".to_string(),
+        start,
+        end,
+        layer_count,
+    );
 
-    v?;
+    objects.par_iter_mut().try_for_each(|object| {
+        object
+            .layers
+            .iter_mut()
+            .skip(raft_layers)
+            .enumerate()
+            .try_for_each(|(index, slice)| {
+                check_cancelled(process)?;
+
+                let mask_area = fuzzy_skin.mask_only.then(|| {
+                    masks
+                        .iter()
+                        .filter_map(|mask| mask.layers.get(index))
+                        .fold(FixedMultiPolygon::new(vec![]), |area, mask_layer| {
+                            area.union_with(&mask_layer.main_polygon)
+                        })
+                        .to_float()
+                });
+
+                jitter_outer_walls(slice, fuzzy_skin, mask_area.as_ref());
+
+                process.step();
+                Ok(())
+            })
+    })
+}
 
-    Ok(convert_objects_into_moves(objects, settings))
+///Returns an error if the process was closed, checked between stages and layers to stop a running slice
+fn check_cancelled(process: &Process) -> Result<(), SlicerErrors> {
+    if process.is_closed() {
+        Err(SlicerErrors::Cancelled)
+    } else {
+        Ok(())
+    }
 }
 
 fn handle_masks(
-    masks: &mut Vec<Object>,
+    masks: &mut [Object],
     settings: &Settings,
     process: &Process,
 ) -> Result<(), SlicerErrors> {
-    let v: Result<Vec<()>, SlicerErrors> = masks
-        .par_iter_mut()
-        .map(|object| {
-            let slices = &mut object.layers;
-
-            //Shrink layer
-            ShrinkPass::pass(slices, settings)?;
-
-            //Handle Perimeters
-            // PerimeterPass::pass(slices, settings)?;
-
-            //Handle Bridging
-            BridgingPass::pass(slices, settings)?;
-
-            //Handle Top Layer
-            TopLayerPass::pass(slices, settings)?;
-
-            //Handle Top And Bottom Layers
-            TopAndBottomLayersPass::pass(slices, settings)?;
-
-            //Handle Support
-            SupportPass::pass(slices, settings)?;
-
-            //Lightning Infill
-            LightningFillPass::pass(slices, settings)?;
-
-            //Fill Remaining areas
-            FillAreaPass::pass(slices, settings)?;
-
-            //Order the move chains
-            OrderPass::pass(slices, settings)
-        })
-        .collect();
-
-    process.set_progress(0.5);
-
-    v?;
-
-    Ok(())
+    //the masks are sliced without the raft
+    let settings = &Settings {
+        raft: None,
+        ..settings.clone()
+    };
+
+    //Shrink layer
+    run_slice_pass::<ShrinkPass>(masks, settings, process, "Mask Layers", 0.23, 0.235)?;
+
+    //Handle Perimeters
+    // run_slice_pass::<PerimeterPass>(masks, settings, process, "Mask Perimeters", 0.235, 0.235)?;
+
+    //Handle Bridging
+    run_slice_pass::<BridgingPass>(masks, settings, process, "Mask Bridging", 0.235, 0.237)?;
+
+    //Handle Top Layer
+    run_slice_pass::<TopLayerPass>(masks, settings, process, "Mask Top Layers", 0.237, 0.239)?;
+
+    //Handle Top And Bottom Layers
+    run_slice_pass::<TopAndBottomLayersPass>(
+        masks,
+        settings,
+        process,
+        "Mask Top And Bottom Layers",
+        0.239,
+        0.241,
+    )?;
+
+    //Handle Support
+    run_slice_pass::<SupportPass>(masks, settings, process, "Mask Support", 0.241, 0.243)?;
+
+    //Lightning Infill
+    run_slice_pass::<LightningFillPass>(
+        masks,
+        settings,
+        process,
+        "Mask Lightning Infill",
+        0.243,
+        0.245,
+    )?;
+
+    //Fill Remaining areas
+    run_slice_pass::<FillAreaPass>(masks, settings, process, "Mask Infill", 0.245, 0.248)?;
+
+    //Order the move chains
+    run_slice_pass::<OrderPass>(
+        masks,
+        settings,
+        process,
+        "Mask Ordering Chains",
+        0.248,
+        0.25,
+    )
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 ///A single slice of an object containing it's current plotting status.
 pub struct Slice {
     ///The slice's entire polygon. Should not be modified after creation by the slicing process.
-    pub main_polygon: MultiPolygon<f32>,
+    ///Like all areas of the slice it is kept on the fixed point grid, it is converted to float only to generate moves.
+    pub main_polygon: FixedMultiPolygon,
 
     ///The slice's remaining area that needs to be processes. Passes will slowly subtract from this until finally infill will fill the space.
-    pub remaining_area: MultiPolygon<f32>,
+    pub remaining_area: FixedMultiPolygon,
 
     /// The area that will be filled by support interface material.
-    pub support_interface: Option<MultiPolygon<f32>>,
+    pub support_interface: Option<FixedMultiPolygon>,
 
     ///The area that will be filled by support towers
-    pub support_tower: Option<MultiPolygon<f32>>,
+    pub support_tower: Option<FixedMultiPolygon>,
 
     ///Theses moves ares applied in order and the start of the commands for the slice.
     pub fixed_chains: Vec<MoveChain>,
@@ -233,6 +562,12 @@ pub struct Slice {
     ///The move chains generaated by various passses. These chains can be reordered by the optomization process to create faster commands.
     pub chains: Vec<MoveChain>,
 
+    ///Infill chains covering this slice and the slices below it, printed after all other chains with the combined thickness.
+    pub combined_chains: Vec<MoveChain>,
+
+    ///The height of the slices covered by the combined chains
+    pub combined_thickness: f32,
+
     ///The lower height of this slice.
     pub bottom_height: f32,
 
@@ -245,6 +580,31 @@ pub struct Slice {
     pub layer: usize,
 }
 impl Slice {
+    ///Creates a slice without any area, the moves of layers like the raft are added to it directly
+    pub fn empty(
+        bottom_height: f32,
+        top_height: f32,
+        layer: usize,
+        mut layer_settings: LayerSettings,
+    ) -> Self {
+        layer_settings.layer_height = top_height - bottom_height;
+
+        Slice {
+            main_polygon: MultiPolygon(vec![]),
+            remaining_area: MultiPolygon(vec![]),
+            support_interface: None,
+            support_tower: None,
+            fixed_chains: vec![],
+            chains: vec![],
+            combined_chains: vec![],
+            combined_thickness: 0.0,
+            bottom_height,
+            top_height,
+            layer_settings,
+            layer,
+        }
+    }
+
     ///Creates a slice from a spefic iterator of points
     pub fn from_single_point_loop<I>(
         line: I,
@@ -256,17 +616,28 @@ impl Slice {
     where
         I: Iterator<Item = (f32, f32)>,
     {
-        let polygon = Polygon::new(LineString::from_iter(line), vec![]);
+        //Snap the points onto the fixed point grid used by all polygon operations
+        let polygon = Polygon::new(LineString::from_iter(line), vec![]).to_fixed();
+
+        let mut layer_settings =
+            settings.get_layer_settings(layer, (bottom_height + top_height) / 2.0);
 
-        let layer_settings = settings.get_layer_settings(layer, (bottom_height + top_height) / 2.0);
+        //The layer height can differ from the settings when adaptive layer heights are used
+        layer_settings.layer_height = top_height - bottom_height;
 
         Slice {
-            main_polygon: MultiPolygon(vec![polygon.simplify_vw_preserve(&0.01)]),
+            //simplifying only removes points, the remaining ones stay on the grid
+            main_polygon: MultiPolygon(vec![polygon
+                .to_float()
+                .simplify_vw_preserve(&0.01)
+                .to_fixed()]),
             remaining_area: MultiPolygon(vec![polygon]),
             support_interface: None,
             support_tower: None,
             fixed_chains: vec![],
             chains: vec![],
+            combined_chains: vec![],
+            combined_thickness: 0.0,
             bottom_height,
             top_height,
             layer_settings,
@@ -282,50 +653,52 @@ impl Slice {
         layer: usize,
         settings: &Settings,
     ) -> Result<Self, SlicerErrors> {
-        let mut lines_and_area: Vec<(LineString<f32>, f32)> = lines
+        //The loops are sorted into polygons on the fixed point grid, so areas and containment are exact
+        let mut lines_and_area: Vec<(LineString<i64>, i128)> = lines
             .into_iter()
             .map(|line| {
-                let area: f32 = line
-                    .clone()
-                    .into_points()
-                    .iter()
-                    .circular_tuple_windows::<(_, _)>()
-                    .map(|(p1, p2)| (p1.x() + p2.x()) * (p2.y() - p1.y()))
-                    .sum();
+                let line = line.to_fixed();
+                let area = fixed_ring_area(&line);
                 (line, area)
             })
-            .filter(|(_, area)| area.abs() > 0.0001)
+            .filter(|(_, area)| *area != 0)
             .collect();
 
-        lines_and_area
-            .sort_by(|(_l1, a1), (_l2, a2)| a2.partial_cmp(a1).expect("Areas should not be NAN"));
-        let mut polygons = vec![];
+        lines_and_area.sort_by(|(_l1, a1), (_l2, a2)| a2.cmp(a1));
+        let mut polygons: Vec<Polygon<i64>> = vec![];
 
         for (line, area) in lines_and_area {
-            if area > 0.0 {
-                polygons.push(Polygon::new(line.clone(), vec![]));
+            if area > 0 {
+                polygons.push(Polygon::new(line, vec![]));
             } else {
                 //counter clockwise interior polygon
                 let smallest_polygon = polygons
                     .iter_mut()
                     .rev()
-                    .find(|poly| poly.contains(&line.0[0]))
+                    .find(|poly| hole_inside_ring(&line, poly.exterior()))
                     .ok_or(SlicerErrors::SliceGeneration)?;
                 smallest_polygon.interiors_push(line);
             }
         }
 
-        let multi_polygon: MultiPolygon<f32> = MultiPolygon(polygons);
+        //Touching loops and holes are resolved by an exact union
+        let multi_polygon = MultiPolygon(polygons).union_with(&FixedMultiPolygon::new(vec![]));
 
-        let layer_settings = settings.get_layer_settings(layer, (bottom_height + top_height) / 2.0);
+        let mut layer_settings =
+            settings.get_layer_settings(layer, (bottom_height + top_height) / 2.0);
+
+        //The layer height can differ from the settings when adaptive layer heights are used
+        layer_settings.layer_height = top_height - bottom_height;
 
         Ok(Slice {
-            main_polygon: multi_polygon.clone(),
-            remaining_area: multi_polygon.simplify_vw(&0.0001),
+            remaining_area: multi_polygon.to_float().simplify_vw(&0.0001).to_fixed(),
+            main_polygon: multi_polygon,
             support_interface: None,
             support_tower: None,
             chains: vec![],
             fixed_chains: vec![],
+            combined_chains: vec![],
+            combined_thickness: 0.0,
             bottom_height,
             top_height,
             layer_settings,
@@ -337,6 +710,33 @@ impl Slice {
     pub fn get_height(&self) -> f32 {
         (self.bottom_height + self.top_height) / 2.0
     }
+
+    ///Takes the layer settings from the provided settings, keeping the height of the slice
+    pub fn update_layer_settings(&mut self, settings: &Settings) {
+        self.layer_settings = settings.get_layer_settings(self.layer, self.get_height());
+        self.layer_settings.layer_height = self.top_height - self.bottom_height;
+    }
+}
+
+///A hole belongs to a ring if any of its points is strictly inside the ring.
+///Holes touching the ring with all points fall back to their bounding box.
+fn hole_inside_ring(hole: &LineString<i64>, ring: &LineString<i64>) -> bool {
+    for point in hole.0.iter() {
+        if let Some(inside) = fixed_point_in_ring(*point, ring) {
+            return inside;
+        }
+    }
+
+    //every point of the hole lies on the ring
+    match (hole.bounding_rect(), ring.bounding_rect()) {
+        (Some(hole_rect), Some(ring_rect)) => {
+            hole_rect.min().x >= ring_rect.min().x
+                && hole_rect.min().y >= ring_rect.min().y
+                && hole_rect.max().x <= ring_rect.max().x
+                && hole_rect.max().y <= ring_rect.max().y
+        }
+        _ => false,
+    }
 }
 
 ///Types of solid infill
@@ -347,6 +747,12 @@ pub enum SolidInfillTypes {
 
     ///Back and forth lines to fill polygons, rotating custom degrees each layer
     RectilinearCustom(f32),
+
+    ///Lines printed in a single sweep so neighbouring lines always overlap the same way, rotating 120 degree each layer
+    Monotonic,
+
+    ///Linked loops following the outline inwards
+    Concentric,
 }
 
 ///Types of partial infill
@@ -364,11 +770,65 @@ pub enum PartialInfillTypes {
     /// Creates a 3d cube structure.
     Cubic,
 
+    ///Smooth curves following the cross section of a gyroid surface, roughly equally strong in all directions
+    Gyroid,
+
+    ///Hexagonal cells built from continuous zig zag lines
+    Honeycomb,
+
+    ///Cells that change from diamonds to hexagons and back with the height, stacking into a 3d structure
+    Honeycomb3D,
+
     ///Creates lightning shaped infill that retracts into the print walls
     Lightning,
+
+    ///Cubic infill that gets sparser with the distance to the walls and the top and bottom skins
+    AdaptiveCubic,
+
+    ///Spaced loops following the outline inwards
+    Concentric,
 }
 
-#[derive(Debug)]
+///Where closed loops start and end
+#[derive(Clone, Copy, Debug, PartialEq, EnumIter, Serialize, Deserialize)]
+pub enum SeamPlacement {
+    ///Seams follow the seams of the layer below
+    Aligned,
+
+    ///Seams are placed at the back of the loops
+    Rear,
+
+    ///Seams are placed closest to the previous seam
+    Nearest,
+
+    ///Seams are placed randomly, the same seed always gives the same seams
+    Random(u64),
+
+    ///Seams are hidden in the sharpest concave corner
+    SharpestCorner,
+}
+
+///Types of support structures
+#[derive(Clone, Copy, Debug, PartialEq, EnumIter, EnumString, Serialize, Deserialize)]
+pub enum SupportType {
+    ///Straight towers below the overhangs
+    Towers,
+
+    ///Branches growing from the overhangs down to the bed or the part, keeping away from the model
+    Tree,
+}
+
+///Types of fill for the support interface
+#[derive(Clone, Copy, Debug, PartialEq, EnumIter, EnumString, Serialize, Deserialize)]
+pub enum SupportInterfacePattern {
+    ///Back and forth lines, rotating 90 degree each layer
+    Rectilinear,
+
+    ///Spaced loops following the outline inwards
+    Concentric,
+}
+
+#[derive(Debug, Clone)]
 ///A object is the collection of slices for a particular model.
 pub struct Object {
     /// The slices for this model sorted from lowest to highest.
@@ -384,9 +844,11 @@ pub struct Move {
     pub width: f32,
     ///The type of move
     pub move_type: MoveType,
+    ///The overhang class of perimeter moves hanging over the layer below, None for supported moves
+    pub overhang: Option<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// A chain of moves that should happen in order
 pub struct MoveChain {
     ///start point for the chain of moves. Needed as Moves don't contain there own start point.
@@ -425,8 +887,14 @@ pub enum MovePrintType {
     ///A bridge over open air
     Bridging,
 
-    ///Support towers and interface
+    ///Support towers
     Support,
+
+    ///Center line moves filling the gaps between perimeters that are too narrow for infill
+    GapFill,
+
+    ///Dense support layers touching the part
+    SupportInterface,
 }
 
 impl std::fmt::Display for MovePrintType {
@@ -441,6 +909,8 @@ impl std::fmt::Display for MovePrintType {
             MovePrintType::InteriorWallInner => write!(f, "Interior Inner Perimeter"),
             MovePrintType::Bridging => write!(f, "Bridging"),
             MovePrintType::Support => write!(f, "Support"),
+            MovePrintType::GapFill => write!(f, "Gap Fill"),
+            MovePrintType::SupportInterface => write!(f, "Support Interface"),
         }
     }
 }
@@ -457,6 +927,8 @@ impl MovePrintType {
             MovePrintType::InteriorWallInner => Vec4::new(1.0, 1.0, 0.0, 1.0),
             MovePrintType::Bridging => Vec4::new(0.0, 1.0, 1.0, 1.0),
             MovePrintType::Support => Vec4::new(1.0, 1.0, 0.0, 1.0),
+            MovePrintType::GapFill => Vec4::new(1.0, 1.0, 1.0, 1.0),
+            MovePrintType::SupportInterface => Vec4::new(1.0, 0.5, 0.0, 1.0),
         }
     }
 }
@@ -515,6 +987,27 @@ pub enum Command {
         /// The extrusion width
         width: f32,
     },
+    ///Move to a location while extruding plastic and rising linearly from the start to the end height.
+    ///Used by the spiral vase mode, where the layers don't have a fixed height.
+    MoveAndExtrudeSpiral {
+        ///Start point of the move
+        start: Coord<f32>,
+
+        ///End point of the move
+        end: Coord<f32>,
+
+        ///The height of the nozzle at the start point
+        start_z: f32,
+
+        ///The height of the nozzle at the end point
+        end_z: f32,
+
+        ///The height thickness of the move
+        thickness: f32,
+
+        /// The extrusion width
+        width: f32,
+    },
     ///Change the layer height
     LayerChange {
         ///The height the print head should move to
@@ -566,6 +1059,11 @@ pub enum Command {
         ///The new print type to change to
         print_type: MovePrintType,
     },
+    ///Tilt the printhead, all following moves keep this tool axis until it is changed again
+    SetToolAxis {
+        ///Unit vector pointing from the nozzle tip up into the printhead, Z is the untilted head
+        axis: Vec3,
+    },
     ///Used in optimization , should be optimized out
     NoAction,
 }
@@ -575,6 +1073,7 @@ impl Command {
         match self {
             Command::MoveAndExtrude { .. } => true,
             Command::MoveAndExtrudeFiber { .. } => true,
+            Command::MoveAndExtrudeSpiral { .. } => true,
             _ => false,
         }
     }
@@ -723,10 +1222,12 @@ impl MoveChain {
         let mut current_print_type = None;
 
         let mut current_type = None;
+        let mut current_overhang = None;
+        let mut current_fan_override = None;
         let mut current_loc = self.start_point;
 
         for m in self.moves {
-            if Some(m.move_type) != current_type {
+            if Some(m.move_type) != current_type || m.overhang != current_overhang {
                 match m.move_type {
                     MoveType::WithFiber(move_print_type) => {
                         update_state(&move_print_type, settings, &mut cmds)
@@ -748,7 +1249,34 @@ impl MoveChain {
                     }
                 }
 
+                let overhang = m
+                    .overhang
+                    .zip(settings.overhangs.as_ref())
+                    .map(|(class, overhangs)| &overhangs.classes[class]);
+
+                if let Some(overhang) = overhang {
+                    cmds.push(Command::SetState {
+                        new_state: StateChange {
+                            bed_temp: None,
+                            extruder_temp: None,
+                            fan_speed: None,
+                            movement_speed: Some(overhang.speed),
+                            acceleration: None,
+                            retract: RetractionType::NoRetract,
+                        },
+                    });
+                }
+
+                //the moves request no fan speed of their own once the overhang is done,
+                //the fan pass returns to the fan of the layer
+                let fan_override = overhang.and_then(|overhang| overhang.fan_speed);
+                if fan_override != current_fan_override {
+                    cmds.push(fan_state(fan_override.unwrap_or(0.0)));
+                    current_fan_override = fan_override;
+                }
+
                 current_type = Some(m.move_type);
+                current_overhang = m.overhang;
             }
 
             match m.move_type {
@@ -782,6 +1310,10 @@ impl MoveChain {
             }
         }
 
+        if current_fan_override.is_some() {
+            cmds.push(fan_state(0.0));
+        }
+
         cmds
     }
 
@@ -914,6 +1446,43 @@ fn update_state(move_type: &MovePrintType, settings: &LayerSettings, cmds: &mut
                 },
             });
         }
+        MovePrintType::GapFill => {
+            cmds.push(Command::SetState {
+                new_state: StateChange {
+                    bed_temp: None,
+                    extruder_temp: None,
+                    fan_speed: None,
+                    movement_speed: Some(settings.speed.gap_fill),
+                    acceleration: Some(settings.acceleration.gap_fill),
+                    retract: RetractionType::Unretract,
+                },
+            });
+        }
+        MovePrintType::SupportInterface => {
+            cmds.push(Command::SetState {
+                new_state: StateChange {
+                    bed_temp: None,
+                    extruder_temp: None,
+                    fan_speed: None,
+                    movement_speed: Some(settings.speed.support_interface),
+                    acceleration: Some(settings.acceleration.support_interface),
+                    retract: RetractionType::Unretract,
+                },
+            });
+        }
+    }
+}
+
+fn fan_state(fan_speed: f32) -> Command {
+    Command::SetState {
+        new_state: StateChange {
+            bed_temp: None,
+            extruder_temp: None,
+            fan_speed: Some(fan_speed),
+            movement_speed: None,
+            acceleration: None,
+            retract: RetractionType::NoRetract,
+        },
     }
 }
 
@@ -949,3 +1518,29 @@ impl CalculatedValues {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use geo::Area;
+
+    use super::*;
+
+    #[test]
+    fn hole_starting_on_the_outline_is_kept() {
+        let outline = LineString::from(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+
+        //clockwise hole whose first point touches the outline
+        let hole = LineString::from(vec![(0.0, 5.0), (5.0, 8.0), (8.0, 5.0), (5.0, 2.0)]);
+
+        let slice = Slice::from_multiple_point_loop(
+            MultiLineString(vec![outline, hole]),
+            0.0,
+            0.2,
+            0,
+            &Settings::default(),
+        )
+        .expect("Hole should find its parent");
+
+        assert!((slice.main_polygon.to_float().unsigned_area() - (100.0 - 24.0)).abs() < 0.001);
+    }
+}
@@ -1,5 +1,3 @@
-use geo::Area;
-
 use crate::{plotter::polygon_operations::PolygonOperations, Object};
 
 pub fn crop_masks(objects: &[Object], masks: &mut Vec<Object>, max_height: f32) {
@@ -20,9 +18,10 @@ pub fn crop_masks(objects: &[Object], masks: &mut Vec<Object>, max_height: f32)
                 layer.remaining_area = layer.main_polygon.clone();
             });
 
-        mask_object.layers.retain(|layer| {
-            layer.main_polygon.unsigned_area() > f32::EPSILON || layer.top_height <= max_height
-        });
+        //the exact difference leaves no empty polygons behind
+        mask_object
+            .layers
+            .retain(|layer| !layer.main_polygon.0.is_empty() || layer.top_height <= max_height);
     }
 }
 
@@ -2,6 +2,7 @@ use geo::algorithm::euclidean_length::EuclideanLength;
 use geo::euclidean_distance::EuclideanDistance;
 use geo::{Coord, Line};
 use itertools::Itertools;
+use shared::process::Process;
 
 use crate::settings::Settings;
 use crate::{Command, RetractionType, StateChange};
@@ -10,10 +11,12 @@ pub fn unary_optimizer(cmds: &mut Vec<Command>) {
     cmds.retain(|cmd| match cmd {
         Command::MoveTo { .. } => true,
         Command::MoveAndExtrude { start, end, .. }
-        | Command::MoveAndExtrudeFiber { start, end, .. } => start != end,
+        | Command::MoveAndExtrudeFiber { start, end, .. }
+        | Command::MoveAndExtrudeSpiral { start, end, .. } => start != end,
         Command::LayerChange { .. } => true,
         Command::ChangeObject { .. } => true,
         Command::ChangeType { .. } => true,
+        Command::SetToolAxis { .. } => true,
         Command::SetState { new_state } => {
             !(new_state.acceleration.is_none()
                 && new_state.movement_speed.is_none()
@@ -30,12 +33,16 @@ pub fn unary_optimizer(cmds: &mut Vec<Command>) {
     });
 }
 
-pub fn binary_optimizer(cmds: &mut Vec<Command>, settings: &Settings) {
+pub fn binary_optimizer(cmds: &mut Vec<Command>, settings: &Settings, process: &Process) {
     let mut current_pos = Coord::zero();
 
     *cmds = cmds
         .drain(..)
         .coalesce(move |first, second| {
+            if let Command::LayerChange { .. } = second {
+                process.step();
+            }
+
             match (first.clone(), second.clone()) {
                 (
                     Command::MoveAndExtrude {
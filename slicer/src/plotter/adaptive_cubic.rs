@@ -0,0 +1,384 @@
+use std::collections::BTreeMap;
+
+use geo::prelude::*;
+use geo::{Coord, Line, MultiPolygon, Point, Polygon, Rect};
+
+use crate::{PartialInfillTypes, Slice};
+
+use super::infill::cubic_fill_polygon;
+use super::polygon_operations::{PolygonOperations, ToFixed, ToFloat};
+
+///A leaf of the octree, the infill inside doubles its spacing with every level
+#[derive(Debug, Clone, Copy)]
+pub struct Cube {
+    pub center: Coord<f32>,
+    pub z: f32,
+    pub level: u32,
+}
+
+impl Cube {
+    fn size(&self, base_size: f32) -> f32 {
+        base_size * (1 << self.level) as f32
+    }
+
+    fn square(&self, base_size: f32, margin: f32) -> Rect<f32> {
+        let half = self.size(base_size) / 2.0 + margin;
+
+        Rect::new(
+            Coord {
+                x: self.center.x - half,
+                y: self.center.y - half,
+            },
+            Coord {
+                x: self.center.x + half,
+                y: self.center.y + half,
+            },
+        )
+    }
+}
+
+///Cubic infill that gets sparser with the distance to the surfaces of the object.
+///Cubes of an octree over the slices are split as long as they are close to a wall or a top or bottom skin,
+///the infill spacing grows with the size of the cube.
+///The octree is shared by all layers of the object, only the layers that use the adaptive cubic infill are filled with it.
+pub struct AdaptiveCubicInfill {
+    cubes: Vec<Cube>,
+    base_size: f32,
+}
+
+impl AdaptiveCubicInfill {
+    ///Builds the octree over the slices, the smallest cubes are sized for the densest layer using the adaptive cubic infill.
+    ///Returns None if no layer uses it.
+    pub fn new(slices: &[Slice]) -> Option<Self> {
+        if !slices.iter().any(uses_adaptive_cubic) {
+            return None;
+        }
+
+        //the densest cubes are filled like the cubic infill
+        let base_size = slices
+            .iter()
+            .filter(|slice| uses_adaptive_cubic(slice))
+            .map(layer_spacing)
+            .filter(|spacing| spacing.is_finite())
+            .min_by(|a, b| a.total_cmp(b))
+            .map_or(0.0, |spacing| spacing * 2.0);
+
+        let cubes = if base_size > 0.0 {
+            build_octree(slices, base_size)
+        } else {
+            vec![]
+        };
+
+        Some(AdaptiveCubicInfill { cubes, base_size })
+    }
+
+    ///Fills the remaining area of a layer that uses the adaptive cubic infill, other layers are left untouched
+    pub fn fill_layer(&self, slice: &mut Slice) {
+        if !uses_adaptive_cubic(slice) {
+            return;
+        }
+
+        let spacing = layer_spacing(slice);
+        if spacing.is_finite() {
+            fill_layer(slice, &self.cubes, spacing, self.base_size);
+        }
+
+        slice.remaining_area = MultiPolygon(vec![]);
+    }
+}
+
+fn uses_adaptive_cubic(slice: &Slice) -> bool {
+    slice.layer_settings.partial_infill_type == PartialInfillTypes::AdaptiveCubic
+}
+
+///Spacing of the lines in the smallest cubes of a layer, infinite if the layer has no infill
+fn layer_spacing(slice: &Slice) -> f32 {
+    let fill_ratio = slice.layer_settings.infill_percentage;
+
+    if fill_ratio >= f32::EPSILON {
+        3.0 * slice.layer_settings.extrusion_width.infill / fill_ratio
+    } else {
+        f32::INFINITY
+    }
+}
+
+///The outline of a slice prepared for the many square tests of the octree
+struct Outline {
+    bottom_height: f32,
+    top_height: f32,
+    bounds: Rect<f32>,
+    edges: Vec<Line<f32>>,
+    polygon: MultiPolygon<f32>,
+}
+
+impl Outline {
+    fn new(slice: &Slice) -> Option<Self> {
+        let polygon = slice.main_polygon.to_float();
+        let bounds = polygon.bounding_rect()?;
+
+        let edges = polygon
+            .iter()
+            .flat_map(|poly| {
+                poly.exterior()
+                    .lines()
+                    .chain(poly.interiors().iter().flat_map(|ring| ring.lines()))
+            })
+            .collect();
+
+        Some(Outline {
+            bottom_height: slice.bottom_height,
+            top_height: slice.top_height,
+            bounds,
+            edges,
+            polygon,
+        })
+    }
+
+    fn overlaps_heights(&self, bottom: f32, top: f32) -> bool {
+        self.top_height > bottom && self.bottom_height < top
+    }
+
+    fn crosses(&self, square: &Rect<f32>) -> bool {
+        self.edges.iter().any(|edge| edge.intersects(square))
+    }
+
+    ///The square shares any area with the slice
+    fn touches(&self, square: &Rect<f32>) -> bool {
+        self.bounds.intersects(square)
+            && (self.crosses(square) || self.polygon.contains(&Point::from(square.center())))
+    }
+
+    ///The square lies completely inside of the slice, no edge crosses it and its center is inside
+    fn contains(&self, square: &Rect<f32>) -> bool {
+        self.bounds.min().x <= square.min().x
+            && self.bounds.min().y <= square.min().y
+            && self.bounds.max().x >= square.max().x
+            && self.bounds.max().y >= square.max().y
+            && !self.crosses(square)
+            && self.polygon.contains(&Point::from(square.center()))
+    }
+}
+
+///The leaves of the octree covering all slices, cubes of the lowest level surround every surface
+pub fn build_octree(slices: &[Slice], base_size: f32) -> Vec<Cube> {
+    let outlines: Vec<Outline> = slices.iter().filter_map(Outline::new).collect();
+
+    let Some(bounds) = outlines
+        .iter()
+        .map(|outline| outline.bounds)
+        .reduce(|a, b| {
+            Rect::new(
+                Coord {
+                    x: a.min().x.min(b.min().x),
+                    y: a.min().y.min(b.min().y),
+                },
+                Coord {
+                    x: a.max().x.max(b.max().x),
+                    y: a.max().y.max(b.max().y),
+                },
+            )
+        })
+    else {
+        return vec![];
+    };
+
+    let bottom = slices[0].bottom_height;
+    let top = slices[slices.len() - 1].top_height;
+    let extent = bounds.width().max(bounds.height()).max(top - bottom);
+
+    let mut level = 0;
+    while base_size * ((1 << level) as f32) < extent {
+        level += 1;
+    }
+
+    let root = Cube {
+        center: bounds.center(),
+        z: (bottom + top) / 2.0,
+        level,
+    };
+
+    let mut leaves = vec![];
+    refine(&outlines, (bottom, top), root, base_size, &mut leaves);
+
+    leaves
+}
+
+fn refine(
+    outlines: &[Outline],
+    heights: (f32, f32),
+    cube: Cube,
+    base_size: f32,
+    leaves: &mut Vec<Cube>,
+) {
+    let half = cube.size(base_size) / 2.0;
+    let square = cube.square(base_size, 0.0);
+
+    let touches_object = outlines
+        .iter()
+        .filter(|outline| outline.overlaps_heights(cube.z - half, cube.z + half))
+        .any(|outline| outline.touches(&square));
+
+    if !touches_object {
+        return;
+    }
+
+    if cube.level == 0 || is_interior(outlines, heights, &cube, base_size) {
+        leaves.push(cube);
+        return;
+    }
+
+    let offset = half / 2.0;
+    for (dx, dy, dz) in itertools::iproduct!([-1.0, 1.0], [-1.0, 1.0], [-1.0, 1.0]) {
+        refine(
+            outlines,
+            heights,
+            Cube {
+                center: Coord {
+                    x: cube.center.x + dx * offset,
+                    y: cube.center.y + dy * offset,
+                },
+                z: cube.z + dz * offset,
+                level: cube.level - 1,
+            },
+            base_size,
+            leaves,
+        );
+    }
+}
+
+///A cube is interior if it and a margin of the smallest cube size around it lie inside the object
+fn is_interior(outlines: &[Outline], heights: (f32, f32), cube: &Cube, base_size: f32) -> bool {
+    let reach = cube.size(base_size) / 2.0 + base_size;
+    let square = cube.square(base_size, base_size);
+
+    //the margin must not stick out of the top or the bottom of the object
+    if cube.z - reach < heights.0 || cube.z + reach > heights.1 {
+        return false;
+    }
+
+    outlines
+        .iter()
+        .filter(|outline| outline.overlaps_heights(cube.z - reach, cube.z + reach))
+        .all(|outline| outline.contains(&square))
+}
+
+fn fill_layer(slice: &mut Slice, cubes: &[Cube], spacing: f32, base_size: f32) {
+    let height = slice.get_height();
+
+    let mut levels: BTreeMap<u32, Vec<Polygon<f32>>> = BTreeMap::new();
+
+    //the layers on the border of two cubes belong to the upper one
+    for cube in cubes.iter().filter(|cube| {
+        let half = cube.size(base_size) / 2.0;
+        cube.z - half <= height && height < cube.z + half
+    }) {
+        levels
+            .entry(cube.level)
+            .or_default()
+            .push(cube.square(base_size, 0.0).to_polygon());
+    }
+
+    for (level, squares) in levels {
+        let area = slice
+            .remaining_area
+            .intersection_with(&MultiPolygon(squares).to_fixed());
+
+        for poly in area.to_float().iter() {
+            slice.chains.append(&mut cubic_fill_polygon(
+                poly,
+                &slice.layer_settings,
+                spacing * (1 << level) as f32,
+                height,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+
+    #[test]
+    fn octree_is_coarse_only_far_from_the_surfaces() {
+        let settings = Settings::default();
+
+        let slices: Vec<Slice> = (0..60)
+            .map(|layer| {
+                Slice::from_single_point_loop(
+                    [(0.0, 0.0), (30.0, 0.0), (30.0, 30.0), (0.0, 30.0)].into_iter(),
+                    layer as f32 * 0.5,
+                    (layer + 1) as f32 * 0.5,
+                    layer,
+                    &settings,
+                )
+            })
+            .collect();
+
+        let base_size = 2.4;
+        let cubes = build_octree(&slices, base_size);
+
+        assert!(cubes.iter().any(|cube| cube.level == 0));
+        assert!(cubes.iter().any(|cube| cube.level > 0));
+
+        for cube in cubes.iter().filter(|cube| cube.level > 0) {
+            let square = cube.square(base_size, 0.0);
+            let half = cube.size(base_size) / 2.0;
+
+            assert!(square.min().x >= base_size && square.max().x <= 30.0 - base_size);
+            assert!(square.min().y >= base_size && square.max().y <= 30.0 - base_size);
+            assert!(cube.z - half >= base_size && cube.z + half <= 30.0 - base_size);
+        }
+    }
+
+    #[test]
+    fn only_layers_choosing_adaptive_cubic_are_filled() {
+        let settings = Settings::default();
+
+        let mut slices: Vec<Slice> = (0..20)
+            .map(|layer| {
+                let mut slice = Slice::from_single_point_loop(
+                    [(0.0, 0.0), (30.0, 0.0), (30.0, 30.0), (0.0, 30.0)].into_iter(),
+                    layer as f32 * 0.5,
+                    (layer + 1) as f32 * 0.5,
+                    layer,
+                    &settings,
+                );
+                if layer >= 10 {
+                    slice.layer_settings.partial_infill_type = PartialInfillTypes::AdaptiveCubic;
+                }
+                slice
+            })
+            .collect();
+
+        let infill = AdaptiveCubicInfill::new(&slices).expect("Layers use adaptive cubic infill");
+
+        for slice in slices.iter_mut() {
+            infill.fill_layer(slice);
+        }
+
+        for slice in &slices[..10] {
+            assert!(slice.chains.is_empty());
+            assert!(!slice.remaining_area.0.is_empty());
+        }
+        for slice in &slices[10..] {
+            assert!(!slice.chains.is_empty());
+            assert!(slice.remaining_area.0.is_empty());
+        }
+    }
+
+    #[test]
+    fn no_octree_without_adaptive_cubic_layers() {
+        let settings = Settings::default();
+
+        let slices = vec![Slice::from_single_point_loop(
+            [(0.0, 0.0), (30.0, 0.0), (30.0, 30.0), (0.0, 30.0)].into_iter(),
+            0.0,
+            0.2,
+            0,
+            &settings,
+        )];
+
+        assert!(AdaptiveCubicInfill::new(&slices).is_none());
+    }
+}
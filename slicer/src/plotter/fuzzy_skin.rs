@@ -0,0 +1,150 @@
+use geo::prelude::*;
+use geo::{Coord, MultiPolygon, Point};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::settings::FuzzySkinSettings;
+use crate::{Move, MoveChain, MovePrintType, MoveType, Slice};
+
+///Splits the outer wall loops of the slice into short moves and jitters the new points along the wall normal.
+///The corners of the loops stay in place, so the seams and the connections to other moves are unchanged.
+///If a mask area is given only points inside of it are moved.
+pub fn jitter_outer_walls(
+    slice: &mut Slice,
+    settings: &FuzzySkinSettings,
+    mask_area: Option<&MultiPolygon<f32>>,
+) {
+    let layer = slice.layer;
+
+    for (index, chain) in slice
+        .fixed_chains
+        .iter_mut()
+        .filter(|chain| chain.is_loop)
+        .enumerate()
+    {
+        let mut rng = StdRng::seed_from_u64(settings.seed ^ ((layer as u64) << 32) ^ index as u64);
+
+        jitter_chain(chain, settings, mask_area, &mut rng);
+    }
+}
+
+fn jitter_chain(
+    chain: &mut MoveChain,
+    settings: &FuzzySkinSettings,
+    mask_area: Option<&MultiPolygon<f32>>,
+    rng: &mut StdRng,
+) {
+    let mut current = chain.start_point;
+    let mut moves = Vec::with_capacity(chain.moves.len());
+
+    for m in chain.moves.drain(..) {
+        if m.move_type == MoveType::WithoutFiber(MovePrintType::WallOuter) {
+            let direction = m.end - current;
+            let length = (direction.x * direction.x + direction.y * direction.y).sqrt();
+            let segments = (length / settings.point_distance).ceil() as usize;
+
+            if segments > 1 {
+                let normal = Coord {
+                    x: direction.y / length,
+                    y: -direction.x / length,
+                };
+
+                for segment in 1..segments {
+                    let point = current + direction * (segment as f32 / segments as f32);
+
+                    let inside_mask = match mask_area {
+                        Some(mask) => mask.intersects(&Point::from(point)),
+                        None => true,
+                    };
+
+                    let end = if inside_mask {
+                        point + normal * rng.gen_range(-settings.thickness..=settings.thickness)
+                    } else {
+                        point
+                    };
+
+                    moves.push(Move { end, ..m });
+                }
+            }
+        }
+
+        current = m.end;
+        moves.push(m);
+    }
+
+    chain.moves = moves;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_loop() -> MoveChain {
+        let points = [(10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (0.0, 0.0)];
+        let move_type = MoveType::WithoutFiber(MovePrintType::WallOuter);
+
+        MoveChain {
+            start_point: Coord { x: 0.0, y: 0.0 },
+            moves: points
+                .iter()
+                .map(|(x, y)| Move {
+                    end: Coord { x: *x, y: *y },
+                    move_type,
+                    width: 0.4,
+                    overhang: None,
+                })
+                .collect(),
+            is_loop: true,
+        }
+    }
+
+    #[test]
+    fn jitter_keeps_the_corners_and_stays_close_to_the_wall() {
+        let settings = FuzzySkinSettings::default();
+        let mut chain = square_loop();
+
+        jitter_chain(
+            &mut chain,
+            &settings,
+            None,
+            &mut StdRng::seed_from_u64(settings.seed),
+        );
+
+        //every side is split into 13 moves
+        assert_eq!(chain.moves.len(), 4 * 13);
+        assert_eq!(chain.moves[12].end, Coord { x: 10.0, y: 0.0 });
+        assert_eq!(chain.moves[51].end, Coord { x: 0.0, y: 0.0 });
+
+        for m in chain.moves[..12].iter() {
+            assert!(m.end.y.abs() <= settings.thickness + f32::EPSILON);
+        }
+
+        assert!(chain.moves[..12].iter().any(|m| m.end.y != 0.0));
+    }
+
+    #[test]
+    fn jitter_only_moves_points_inside_of_the_mask() {
+        let settings = FuzzySkinSettings::default();
+        let mut chain = square_loop();
+
+        let mask = MultiPolygon(vec![geo::Rect::new(
+            Coord { x: 5.0, y: -1.0 },
+            Coord { x: 11.0, y: 0.5 },
+        )
+        .to_polygon()]);
+
+        jitter_chain(
+            &mut chain,
+            &settings,
+            Some(&mask),
+            &mut StdRng::seed_from_u64(settings.seed),
+        );
+
+        for m in chain.moves[..12].iter().filter(|m| m.end.x < 5.0) {
+            assert_eq!(m.end.y, 0.0);
+        }
+
+        for m in chain.moves[13..].iter() {
+            assert!(m.end.x == 0.0 || m.end.x == 10.0 || m.end.y == 10.0);
+        }
+    }
+}
@@ -2,8 +2,9 @@ use crate::settings::LayerSettings;
 use crate::utils::point_y_lerp;
 use crate::{Move, MoveChain, MovePrintType, MoveType, PartialInfillTypes, SolidInfillTypes};
 
-use super::monotone::get_monotone_sections;
-use super::polygon_operations::PolygonOperations;
+use super::monotone::{get_monotone_sections, MonotoneSection};
+use super::polygon_operations::{clip_lines, PolygonOperations};
+use super::seam::loop_seam;
 use geo::prelude::*;
 use geo::*;
 
@@ -21,33 +22,224 @@ pub fn linear_fill_polygon(
     fill_type: MoveType,
     angle: f32,
 ) -> Vec<MoveChain> {
-    let rotate_poly = poly.rotate_around_point(angle, Point(Coord::zero()));
+    rotated_infill(poly, settings, angle, |fill_area| {
+        fill_area
+            .iter()
+            .flat_map(|polygon| {
+                spaced_fill_polygon(
+                    polygon,
+                    settings,
+                    fill_type,
+                    settings
+                        .extrusion_width
+                        .get_value_for_movement_type(&fill_type),
+                    0.0,
+                )
+            })
+            .collect()
+    })
+}
 
-    let mut new_moves: Vec<MoveChain> = rotate_poly
-        .offset_from(
-            ((-settings.extrusion_width.interior_inner_perimeter / 2.0)
-                * (1.0 - settings.infill_perimeter_overlap_percentage))
-                + (settings.extrusion_width.interior_inner_perimeter / 2.0),
-        )
-        .iter()
-        .flat_map(|polygon| {
-            spaced_fill_polygon(
-                polygon,
-                settings,
-                fill_type,
-                settings
-                    .extrusion_width
-                    .get_value_for_movement_type(&fill_type),
-                0.0,
-            )
+///Solid lines printed in a single sweep across the polygon.
+///A line is only printed once every line above it that it touches is printed, so neighbouring lines always overlap the same way.
+pub fn monotonic_fill_polygon(
+    poly: &Polygon<f32>,
+    settings: &LayerSettings,
+    fill_type: MoveType,
+    angle: f32,
+) -> Vec<MoveChain> {
+    let width = settings
+        .extrusion_width
+        .get_value_for_movement_type(&fill_type);
+
+    rotated_infill(poly, settings, angle, |fill_area| {
+        fill_area
+            .iter()
+            .filter_map(|polygon| {
+                let sections: Vec<Vec<FillLine>> = get_monotone_sections(polygon)
+                    .iter()
+                    .map(|section| section_lines(section, width))
+                    .collect();
+
+                let mut runs = monotonic_runs(&sections).into_iter();
+                let first_run = runs.next()?;
+                let mut moves = vec![];
+
+                //the first run starts at the start point, the others are reached with a travel
+                let start_point = run_moves(&sections, &first_run, width, fill_type, &mut moves);
+
+                for run in runs {
+                    let (section, line) = run[0];
+                    moves.push(Move {
+                        end: sections[section][line].left,
+                        move_type: MoveType::Travel,
+                        width: 0.0,
+                        overhang: None,
+                    });
+                    run_moves(&sections, &run, width, fill_type, &mut moves);
+                }
+
+                Some(MoveChain {
+                    start_point,
+                    moves,
+                    is_loop: false,
+                })
+            })
+            .collect()
+    })
+}
+
+///A single fill line, rows are counted in multiples of the spacing
+#[derive(Debug)]
+struct FillLine {
+    row: i64,
+    left: Coord<f32>,
+    right: Coord<f32>,
+}
+
+///The fill lines of a monotone section from top to bottom
+fn section_lines(section: &MonotoneSection, spacing: f32) -> Vec<FillLine> {
+    let top = section.left_chain[0].y;
+    let bottom = section
+        .left_chain
+        .last()
+        .expect("Chain must have entries")
+        .y
+        .max(
+            section
+                .right_chain
+                .last()
+                .expect("Chain must have entries")
+                .y,
+        );
+
+    let mut row = (top / spacing).floor() as i64;
+    if row as f32 * spacing >= top {
+        row -= 1;
+    }
+
+    let mut lines = vec![];
+
+    while row as f32 * spacing > bottom {
+        let y = row as f32 * spacing;
+
+        match (
+            chain_point_at(&section.left_chain, y),
+            chain_point_at(&section.right_chain, y),
+        ) {
+            (Some(left), Some(right)) => lines.push(FillLine { row, left, right }),
+            _ => break,
+        }
+
+        row -= 1;
+    }
+
+    lines
+}
+
+///The point of a chain descending in y at the height y
+fn chain_point_at(chain: &[Coord<f32>], y: f32) -> Option<Coord<f32>> {
+    chain
+        .windows(2)
+        .find(|edge| edge[0].y >= y && edge[1].y <= y && edge[0].y > edge[1].y)
+        .map(|edge| point_y_lerp(&edge[0], &edge[1], y))
+}
+
+///Orders the lines of all sections into runs of consecutive lines of a single section.
+///Lines are only taken once all touching lines of the row above are taken.
+fn monotonic_runs(sections: &[Vec<FillLine>]) -> Vec<Vec<(usize, usize)>> {
+    let mut next_line = vec![0; sections.len()];
+
+    let is_ready = |next_line: &[usize], section: usize| -> bool {
+        let Some(line) = sections[section].get(next_line[section]) else {
+            return false;
+        };
+
+        sections.iter().enumerate().all(|(other, lines)| {
+            lines[next_line[other]..].iter().all(|above| {
+                above.row != line.row + 1
+                    || above.right.x <= line.left.x
+                    || above.left.x >= line.right.x
+            })
         })
-        .collect();
+    };
 
-    for chain in new_moves.iter_mut() {
-        chain.rotate(-angle.to_radians());
+    let mut runs = vec![];
+    let mut current: Option<usize> = None;
+
+    loop {
+        let section = match current.filter(|section| is_ready(&next_line, *section)) {
+            Some(section) => section,
+            None => {
+                //the topmost ready line starts the next run
+                let Some(section) = (0..sections.len())
+                    .filter(|section| is_ready(&next_line, *section))
+                    .max_by_key(|section| sections[*section][next_line[*section]].row)
+                else {
+                    break;
+                };
+
+                runs.push(vec![]);
+                current = Some(section);
+                section
+            }
+        };
+
+        runs.last_mut()
+            .expect("A run was started")
+            .push((section, next_line[section]));
+        next_line[section] += 1;
     }
 
-    new_moves
+    runs
+}
+
+///Appends the zig zag moves of a run and returns its start point
+fn run_moves(
+    sections: &[Vec<FillLine>],
+    run: &[(usize, usize)],
+    width: f32,
+    fill_type: MoveType,
+    moves: &mut Vec<Move>,
+) -> Coord<f32> {
+    let mut start_point = None;
+    let mut position = Coord::zero();
+
+    for (index, (section, line)) in run.iter().enumerate() {
+        let line = &sections[*section][*line];
+        let (start, end) = if index % 2 == 0 {
+            (line.left, line.right)
+        } else {
+            (line.right, line.left)
+        };
+
+        match start_point {
+            None => start_point = Some(start),
+            //long connections would leave the section
+            Some(_) if (start.x - position.x).abs() > width * 2.0 => moves.push(Move {
+                end: start,
+                move_type: MoveType::Travel,
+                width: 0.0,
+                overhang: None,
+            }),
+            Some(_) => moves.push(Move {
+                end: start,
+                move_type: fill_type,
+                width,
+                overhang: None,
+            }),
+        }
+
+        moves.push(Move {
+            end,
+            move_type: fill_type,
+            width,
+            overhang: None,
+        });
+        position = end;
+    }
+
+    start_point.expect("Runs contain lines")
 }
 
 pub fn partial_linear_fill_polygon(
@@ -58,17 +250,30 @@ pub fn partial_linear_fill_polygon(
     angle: f32,
     offset: f32,
 ) -> Vec<MoveChain> {
+    rotated_infill(poly, settings, angle, |fill_area| {
+        fill_area
+            .iter()
+            .flat_map(|polygon| spaced_fill_polygon(polygon, settings, fill_type, spacing, offset))
+            .collect()
+    })
+}
+
+///Rotates the polygon by the angle and shrinks it to the infill overlap with the perimeters.
+///The pattern fills the rotated area along its axes, its chains are rotated back.
+fn rotated_infill<F>(
+    poly: &Polygon<f32>,
+    settings: &LayerSettings,
+    angle: f32,
+    fill: F,
+) -> Vec<MoveChain>
+where
+    F: FnOnce(&MultiPolygon<f32>) -> Vec<MoveChain>,
+{
     let rotate_poly = poly.rotate_around_point(angle, Point(Coord::zero()));
 
-    let mut new_moves: Vec<MoveChain> = rotate_poly
-        .offset_from(
-            ((-settings.extrusion_width.interior_inner_perimeter / 2.0)
-                * (1.0 - settings.infill_perimeter_overlap_percentage))
-                + (settings.extrusion_width.interior_inner_perimeter / 2.0),
-        )
-        .iter()
-        .flat_map(|polygon| spaced_fill_polygon(polygon, settings, fill_type, spacing, offset))
-        .collect();
+    let fill_area = rotate_poly.offset_from(infill_overlap(settings));
+
+    let mut new_moves = fill(&fill_area);
 
     for chain in new_moves.iter_mut() {
         chain.rotate(-angle.to_radians());
@@ -77,6 +282,13 @@ pub fn partial_linear_fill_polygon(
     new_moves
 }
 
+///Distance the infill area grows from the polygon into the inner perimeter by the infill overlap
+fn infill_overlap(settings: &LayerSettings) -> f32 {
+    ((-settings.extrusion_width.interior_inner_perimeter / 2.0)
+        * (1.0 - settings.infill_perimeter_overlap_percentage))
+        + (settings.extrusion_width.interior_inner_perimeter / 2.0)
+}
+
 pub fn support_linear_fill_polygon(
     poly: &Polygon<f32>,
     settings: &LayerSettings,
@@ -120,6 +332,22 @@ pub fn solid_infill_polygon(
 
             linear_fill_polygon(poly, settings, fill_type, angle)
         }
+
+        SolidInfillTypes::Monotonic => {
+            let angle = 45.0 + (120_f32) * layer_count as f32;
+
+            monotonic_fill_polygon(poly, settings, fill_type, angle)
+        }
+
+        SolidInfillTypes::Concentric => concentric_fill_polygon(
+            poly,
+            settings,
+            fill_type,
+            settings
+                .extrusion_width
+                .get_value_for_movement_type(&fill_type),
+            layer_count,
+        ),
     }
 }
 
@@ -127,7 +355,7 @@ pub fn partial_infill_polygon(
     poly: &Polygon<f32>,
     settings: &LayerSettings,
     fill_ratio: f32,
-    _layer_count: usize,
+    layer_count: usize,
     layer_height: f32,
 ) -> Vec<MoveChain> {
     if fill_ratio < f32::EPSILON {
@@ -188,39 +416,562 @@ pub fn partial_infill_polygon(
             ));
             fill
         }
-        PartialInfillTypes::Cubic => {
-            let mut fill = partial_linear_fill_polygon(
-                poly,
-                settings,
-                MoveType::WithoutFiber(MovePrintType::Infill),
-                3.0 * settings.extrusion_width.infill / fill_ratio,
-                45.0,
-                layer_height / std::f32::consts::SQRT_2,
-            );
-            fill.append(&mut partial_linear_fill_polygon(
+        PartialInfillTypes::Cubic | PartialInfillTypes::AdaptiveCubic => cubic_fill_polygon(
+            poly,
+            settings,
+            3.0 * settings.extrusion_width.infill / fill_ratio,
+            layer_height,
+        ),
+        PartialInfillTypes::Honeycomb => {
+            //every wall is printed once, the line length per area is 1 / rise
+            let rise = settings.extrusion_width.infill / fill_ratio;
+            let side = rise * 2.0 / 3.0_f32.sqrt();
+
+            wave_fill_polygon(
                 poly,
                 settings,
                 MoveType::WithoutFiber(MovePrintType::Infill),
-                3.0 * settings.extrusion_width.infill / fill_ratio,
-                45.0 + 120.0,
-                layer_height / std::f32::consts::SQRT_2,
-            ));
-            fill.append(&mut partial_linear_fill_polygon(
+                side,
+                side / 2.0,
+                rise,
+                0.0,
+            )
+        }
+        PartialInfillTypes::Honeycomb3D => {
+            let cell = std::f32::consts::SQRT_2 * settings.extrusion_width.infill / fill_ratio;
+
+            //the flats grow and shrink with the height and the waves turn by 90 degree every cell,
+            //stacking the cells into truncated octahedra
+            let phase = layer_height / cell;
+            let growth = 1.0 - (2.0 * phase.fract() - 1.0).abs();
+            let flat = cell * 0.5 * growth;
+            let angle = if (phase.floor() as i64).rem_euclid(2) == 0 {
+                45.0
+            } else {
+                135.0
+            };
+
+            wave_fill_polygon(
                 poly,
                 settings,
                 MoveType::WithoutFiber(MovePrintType::Infill),
-                3.0 * settings.extrusion_width.infill / fill_ratio,
-                45.0 + 240.0,
-                layer_height / std::f32::consts::SQRT_2,
-            ));
-            fill
+                flat,
+                cell - flat,
+                cell,
+                angle,
+            )
         }
+        PartialInfillTypes::Concentric => concentric_fill_polygon(
+            poly,
+            settings,
+            MoveType::WithoutFiber(MovePrintType::Infill),
+            settings.extrusion_width.infill / fill_ratio,
+            layer_count,
+        ),
+        PartialInfillTypes::Gyroid => gyroid_fill_polygon(
+            poly,
+            settings,
+            MoveType::WithoutFiber(MovePrintType::Infill),
+            settings.extrusion_width.infill / fill_ratio,
+            layer_height,
+        ),
         PartialInfillTypes::Lightning => {
             unreachable!()
         }
     }
 }
 
+///Lines in 3 directions that shift with the height, stacking into cubes standing on a corner
+pub fn cubic_fill_polygon(
+    poly: &Polygon<f32>,
+    settings: &LayerSettings,
+    spacing: f32,
+    layer_height: f32,
+) -> Vec<MoveChain> {
+    let mut fill = partial_linear_fill_polygon(
+        poly,
+        settings,
+        MoveType::WithoutFiber(MovePrintType::Infill),
+        spacing,
+        45.0,
+        layer_height / std::f32::consts::SQRT_2,
+    );
+    fill.append(&mut partial_linear_fill_polygon(
+        poly,
+        settings,
+        MoveType::WithoutFiber(MovePrintType::Infill),
+        spacing,
+        45.0 + 120.0,
+        layer_height / std::f32::consts::SQRT_2,
+    ));
+    fill.append(&mut partial_linear_fill_polygon(
+        poly,
+        settings,
+        MoveType::WithoutFiber(MovePrintType::Infill),
+        spacing,
+        45.0 + 240.0,
+        layer_height / std::f32::consts::SQRT_2,
+    ));
+    fill
+}
+
+///Loops following the outline of the polygon inwards, spaced by the spacing.
+///The loops of a region are linked into one chain, the first loop starts at the seam of the layer
+///and every other loop starts next to the end of the loop before it.
+pub fn concentric_fill_polygon(
+    poly: &Polygon<f32>,
+    settings: &LayerSettings,
+    fill_type: MoveType,
+    spacing: f32,
+    layer: usize,
+) -> Vec<MoveChain> {
+    let width = settings
+        .extrusion_width
+        .get_value_for_movement_type(&fill_type);
+
+    //the outermost loop lies half a width inside the area the linear fills cover
+    poly.offset_from(infill_overlap(settings) - width / 2.0)
+        .iter()
+        .filter_map(|polygon| {
+            let mut rings = vec![];
+            concentric_rings(polygon, spacing, &mut rings);
+
+            let mut rings = rings.into_iter().filter(|ring| ring.0.len() >= 4);
+
+            let first_ring = rings.next()?;
+            let first_points = &first_ring.0[..first_ring.0.len() - 1];
+            let start_point = first_points[loop_seam(first_points, settings.seam_placement, layer)];
+
+            let mut moves = vec![];
+            let mut position = start_point;
+
+            for ring in std::iter::once(first_ring).chain(rings) {
+                let points = &ring.0[..ring.0.len() - 1];
+
+                let seam = points
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| {
+                        a.euclidean_distance(&position)
+                            .total_cmp(&b.euclidean_distance(&position))
+                    })
+                    .map(|(index, _)| index)
+                    .expect("Rings contain points");
+
+                //neighbouring loops are linked with an extrusion, jumps to other regions travel
+                if points[seam] != position {
+                    let linked = points[seam].euclidean_distance(&position) <= spacing * 2.0;
+
+                    moves.push(Move {
+                        end: points[seam],
+                        move_type: if linked { fill_type } else { MoveType::Travel },
+                        width: if linked { width } else { 0.0 },
+                        overhang: None,
+                    });
+                }
+
+                for offset in 1..=points.len() {
+                    moves.push(Move {
+                        end: points[(seam + offset) % points.len()],
+                        move_type: fill_type,
+                        width,
+                        overhang: None,
+                    });
+                }
+
+                position = points[seam];
+            }
+
+            Some(MoveChain {
+                start_point,
+                moves,
+                is_loop: false,
+            })
+        })
+        .collect()
+}
+
+///The rings of the polygon followed by the rings of its insets, from the outside in
+fn concentric_rings(polygon: &Polygon<f32>, spacing: f32, rings: &mut Vec<LineString<f32>>) {
+    let polygon = polygon.simplify(&0.01);
+
+    rings.push(polygon.exterior().clone());
+    rings.extend(polygon.interiors().iter().cloned());
+
+    for inset in polygon.offset_from(-spacing).iter() {
+        concentric_rings(inset, spacing, rings);
+    }
+}
+
+///Samples per period of the gyroid curves
+const GYROID_RESOLUTION: usize = 32;
+
+///Fills the polygon with the cross section of a gyroid surface at the height.
+///The spacing is the average distance between neighbouring curves.
+pub fn gyroid_fill_polygon(
+    poly: &Polygon<f32>,
+    settings: &LayerSettings,
+    fill_type: MoveType,
+    spacing: f32,
+    height: f32,
+) -> Vec<MoveChain> {
+    let fill_area = poly.offset_from(infill_overlap(settings));
+
+    let Some(bounds) = fill_area.bounding_rect() else {
+        return vec![];
+    };
+
+    //two curves per period
+    let scale = spacing / std::f32::consts::PI;
+    let curves = MultiLineString(
+        gyroid_curves(
+            bounds.min().x / scale,
+            bounds.max().x / scale,
+            bounds.min().y / scale,
+            bounds.max().y / scale,
+            height / scale,
+        )
+        .into_iter()
+        .map(|curve| curve.map_coords(|coord| coord * scale))
+        .collect(),
+    );
+
+    clip_curves_into_chains(&fill_area, &curves, settings, fill_type)
+}
+
+///Clips the curves to the area, every remaining piece becomes a chain
+fn clip_curves_into_chains(
+    fill_area: &MultiPolygon<f32>,
+    curves: &MultiLineString<f32>,
+    settings: &LayerSettings,
+    fill_type: MoveType,
+) -> Vec<MoveChain> {
+    let width = settings
+        .extrusion_width
+        .get_value_for_movement_type(&fill_type);
+
+    clip_lines(curves, fill_area)
+        .into_iter()
+        .filter(|curve| curve.0.len() >= 2)
+        .map(|curve| MoveChain {
+            start_point: curve.0[0],
+            moves: curve.0[1..]
+                .iter()
+                .map(|point| Move {
+                    end: *point,
+                    move_type: fill_type,
+                    width,
+                    overhang: None,
+                })
+                .collect(),
+            is_loop: false,
+        })
+        .collect()
+}
+
+///Fills the polygon with zig zag waves along the x axis of the rotated polygon.
+///Neighbouring waves are mirrored and share their flat parts, closing the cells between them.
+///Every wave only prints its low flats with the diagonals next to them and travels over its high flats,
+///which the next wave prints, so each shared flat is printed once.
+///The waves are walked back and forth, so a region is filled by a few long chains.
+///Flats of the length of the diagonals with a rise of sqrt(3)/2 of it form regular hexagons.
+fn wave_fill_polygon(
+    poly: &Polygon<f32>,
+    settings: &LayerSettings,
+    fill_type: MoveType,
+    flat: f32,
+    run: f32,
+    rise: f32,
+    angle: f32,
+) -> Vec<MoveChain> {
+    let width = settings
+        .extrusion_width
+        .get_value_for_movement_type(&fill_type);
+
+    rotated_infill(poly, settings, angle, |fill_area| {
+        let Some(bounds) = fill_area.bounding_rect() else {
+            return vec![];
+        };
+
+        //distance between the centers of a low and a high flat
+        let half_period = flat + run;
+
+        let first_flat = (bounds.min().x / half_period).floor() as i64 - 1;
+        let last_flat = (bounds.max().x / half_period).ceil() as i64 + 1;
+        let first_wave = (bounds.min().y / rise).floor() as i64 - 1;
+        let last_wave = (bounds.max().y / rise).ceil() as i64;
+
+        //travels inside a chain stay over the fill area and its overlap with the perimeters
+        let link_area = fill_area.offset_from(width / 2.0);
+        let max_link = 2.0 * half_period.max(rise);
+
+        //clipped points are rounded to the fixed point grid
+        let tolerance = 0.001;
+
+        let mut chains: Vec<MoveChain> = vec![];
+        let mut current: Option<MoveChain> = None;
+
+        for (row, wave) in (first_wave..=last_wave).enumerate() {
+            let low = wave as f32 * rise;
+            let high = low + rise;
+
+            //odd waves are shifted by half a period, their low flats are the high flats of the wave below
+            let points: Vec<Coord<f32>> = (first_flat..=last_flat)
+                .filter(|index| (index - wave).rem_euclid(2) == 0)
+                .flat_map(|index| {
+                    let center = index as f32 * half_period;
+
+                    [
+                        Coord {
+                            x: center - flat / 2.0,
+                            y: low,
+                        },
+                        Coord {
+                            x: center + flat / 2.0,
+                            y: low,
+                        },
+                        Coord {
+                            x: center + flat / 2.0 + run,
+                            y: high,
+                        },
+                        Coord {
+                            x: center + flat / 2.0 + run + flat,
+                            y: high,
+                        },
+                    ]
+                })
+                .collect();
+
+            //a wave is monotone in x, so its pieces are ordered by their left end
+            let mut pieces: Vec<Vec<Coord<f32>>> =
+                clip_lines(&MultiLineString(vec![LineString(points)]), fill_area)
+                    .into_iter()
+                    .filter(|piece| piece.0.len() >= 2)
+                    .map(|piece| {
+                        let mut piece = piece.0;
+                        if piece[0].x > piece[piece.len() - 1].x {
+                            piece.reverse();
+                        }
+                        piece
+                    })
+                    .collect();
+            pieces.sort_by(|a, b| a[0].x.total_cmp(&b[0].x));
+
+            //every other wave is walked backwards
+            if row % 2 == 1 {
+                pieces.reverse();
+                pieces.iter_mut().for_each(|piece| piece.reverse());
+            }
+
+            for piece in pieces {
+                let printed: Vec<bool> = piece
+                    .windows(2)
+                    .map(|segment| {
+                        (segment[0].y - high).abs() > tolerance
+                            || (segment[1].y - high).abs() > tolerance
+                    })
+                    .collect();
+
+                //pieces don't start or end with a travel
+                let (Some(first), Some(last)) = (
+                    printed.iter().position(|printed| *printed),
+                    printed.iter().rposition(|printed| *printed),
+                ) else {
+                    continue;
+                };
+
+                let start = piece[first];
+                let moves = (first..=last).map(|index| Move {
+                    end: piece[index + 1],
+                    move_type: if printed[index] {
+                        fill_type
+                    } else {
+                        MoveType::Travel
+                    },
+                    width: if printed[index] { width } else { 0.0 },
+                    overhang: None,
+                });
+
+                match current.as_mut() {
+                    Some(chain)
+                        if chain.moves.last().is_some_and(|m| {
+                            let link = Line::new(m.end, start);
+
+                            link.euclidean_length() <= max_link
+                                && link_area.iter().any(|polygon| polygon.contains(&link))
+                        }) =>
+                    {
+                        chain.moves.push(Move {
+                            end: start,
+                            move_type: MoveType::Travel,
+                            width: 0.0,
+                            overhang: None,
+                        });
+                        chain.moves.extend(moves);
+                    }
+                    _ => {
+                        chains.extend(current.replace(MoveChain {
+                            start_point: start,
+                            moves: moves.collect(),
+                            is_loop: false,
+                        }));
+                    }
+                }
+            }
+        }
+
+        chains.extend(current);
+        chains
+    })
+}
+
+///The curves of sin(x)cos(y) + sin(y)cos(z) + sin(z)cos(x) = 0 covering the rectangle, in units of the gyroid.
+///The curves are traced through a grid of samples, so steep parts and turning points are followed like any other part.
+fn gyroid_curves(min_x: f32, max_x: f32, min_y: f32, max_y: f32, z: f32) -> Vec<LineString<f32>> {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    //column, row and whether the edge of the grid is horizontal
+    type Edge = (usize, usize, bool);
+
+    let step = std::f32::consts::TAU / GYROID_RESOLUTION as f32;
+    let (sin_z, cos_z) = z.sin_cos();
+    let value = |point: Coord<f32>| {
+        point.x.sin() * point.y.cos() + point.y.sin() * cos_z + sin_z * point.x.cos()
+    };
+
+    //one sample outside of the rectangle on every side
+    let columns = ((max_x - min_x) / step).ceil() as usize + 3;
+    let rows = ((max_y - min_y) / step).ceil() as usize + 3;
+    let corner = |column: usize, row: usize| Coord {
+        x: min_x + (column as f32 - 1.0) * step,
+        y: min_y + (row as f32 - 1.0) * step,
+    };
+
+    let values: Vec<Vec<f32>> = (0..rows)
+        .map(|row| {
+            (0..columns)
+                .map(|column| value(corner(column, row)))
+                .collect()
+        })
+        .collect();
+
+    let ends = |(column, row, horizontal): Edge| {
+        if horizontal {
+            ((column, row), (column + 1, row))
+        } else {
+            ((column, row), (column, row + 1))
+        }
+    };
+    let inside = |(column, row): (usize, usize)| values[row][column] >= 0.0;
+    let crosses = |edge: Edge| {
+        let (a, b) = ends(edge);
+        inside(a) != inside(b)
+    };
+
+    //marching squares, every edge crossing the surface is linked to the crossings in the cells next to it
+    let mut neighbours: BTreeMap<Edge, Vec<Edge>> = BTreeMap::new();
+    let mut link = |a: Edge, b: Edge| {
+        neighbours.entry(a).or_default().push(b);
+        neighbours.entry(b).or_default().push(a);
+    };
+
+    for row in 0..rows - 1 {
+        for column in 0..columns - 1 {
+            let bottom = (column, row, true);
+            let right = (column + 1, row, false);
+            let top = (column, row + 1, true);
+            let left = (column, row, false);
+
+            let crossing: Vec<Edge> = [bottom, right, top, left]
+                .into_iter()
+                .filter(|edge| crosses(*edge))
+                .collect();
+
+            match crossing[..] {
+                [a, b] => link(a, b),
+                //a saddle, the center decides which corners are connected
+                [_, _, _, _] => {
+                    let center = value(corner(column, row) + Coord::from((step, step)) / 2.0);
+
+                    if (center >= 0.0) == inside((column, row)) {
+                        link(bottom, right);
+                        link(top, left);
+                    } else {
+                        link(left, bottom);
+                        link(right, top);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    //interpolate along the edge and move the point onto the surface with a few newton steps
+    let point = |edge: Edge| {
+        let ((column_a, row_a), (column_b, row_b)) = ends(edge);
+        let (a, b) = (corner(column_a, row_a), corner(column_b, row_b));
+        let (value_a, value_b) = (values[row_a][column_a], values[row_b][column_b]);
+
+        let mut point = a + (b - a) * (value_a / (value_a - value_b));
+
+        for _ in 0..4 {
+            let gradient = Coord {
+                x: point.x.cos() * point.y.cos() - sin_z * point.x.sin(),
+                y: point.y.cos() * cos_z - point.x.sin() * point.y.sin(),
+            };
+            let length_squared = gradient.x * gradient.x + gradient.y * gradient.y;
+
+            if length_squared < f32::EPSILON {
+                break;
+            }
+
+            point = point - gradient * (value(point) / length_squared);
+        }
+
+        point
+    };
+
+    //open curves start at the border of the grid, the remaining edges form loops
+    let starts: Vec<Edge> = neighbours
+        .iter()
+        .filter(|(_, linked)| linked.len() == 1)
+        .chain(neighbours.iter())
+        .map(|(edge, _)| *edge)
+        .collect();
+
+    let mut visited: BTreeSet<Edge> = BTreeSet::new();
+    let mut curves = vec![];
+
+    for start in starts {
+        if visited.contains(&start) {
+            continue;
+        }
+
+        let mut curve = vec![];
+        let mut current = Some(start);
+
+        while let Some(edge) = current {
+            visited.insert(edge);
+            curve.push(point(edge));
+
+            current = neighbours[&edge]
+                .iter()
+                .find(|neighbour| !visited.contains(neighbour))
+                .copied();
+        }
+
+        if curve.len() > 2 && neighbours[&start].len() == 2 {
+            curve.push(curve[0]);
+        }
+
+        if curve.len() >= 2 {
+            curves.push(LineString::from(curve));
+        }
+    }
+
+    curves
+}
+
 pub fn spaced_fill_polygon(
     poly: &Polygon<f32>,
     settings: &LayerSettings,
@@ -297,6 +1048,7 @@ pub fn spaced_fill_polygon(
                             width: settings
                                 .extrusion_width
                                 .get_value_for_movement_type(&fill_type),
+                            overhang: None,
                         });
 
                         y = Some(point.y);
@@ -318,6 +1070,7 @@ pub fn spaced_fill_polygon(
                         width: settings
                             .extrusion_width
                             .get_value_for_movement_type(&fill_type),
+                        overhang: None,
                     });
 
                     moves.push(Move {
@@ -329,6 +1082,7 @@ pub fn spaced_fill_polygon(
                         width: settings
                             .extrusion_width
                             .get_value_for_movement_type(&fill_type),
+                        overhang: None,
                     });
                 } else {
                     moves.push(Move {
@@ -340,6 +1094,7 @@ pub fn spaced_fill_polygon(
                         width: settings
                             .extrusion_width
                             .get_value_for_movement_type(&fill_type),
+                        overhang: None,
                     });
 
                     moves.push(Move {
@@ -351,6 +1106,7 @@ pub fn spaced_fill_polygon(
                         width: settings
                             .extrusion_width
                             .get_value_for_movement_type(&fill_type),
+                        overhang: None,
                     });
                 }
 
@@ -368,3 +1124,282 @@ pub fn spaced_fill_polygon(
         .into_iter()
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+    use crate::SeamPlacement;
+
+    #[test]
+    fn concentric_fill_starts_at_the_seam() {
+        let mut settings = Settings::default().get_layer_settings(0, 0.2);
+        settings.seam_placement = SeamPlacement::Rear;
+
+        let square = Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]),
+            vec![],
+        );
+
+        let chains = concentric_fill_polygon(
+            &square,
+            &settings,
+            MoveType::WithoutFiber(MovePrintType::SolidInfill),
+            0.4,
+            0,
+        );
+
+        let start = chains[0].start_point;
+        let max_y = chains[0]
+            .moves
+            .iter()
+            .map(|m| m.end.y)
+            .fold(f32::MIN, f32::max);
+        let min_x = chains[0]
+            .moves
+            .iter()
+            .map(|m| m.end.x)
+            .fold(f32::MAX, f32::min);
+
+        assert_eq!(start, Coord { x: min_x, y: max_y });
+    }
+
+    #[test]
+    fn concentric_loops_are_linked() {
+        let settings = Settings::default().get_layer_settings(0, 0.2);
+        let square = Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]),
+            vec![],
+        );
+
+        let chains = concentric_fill_polygon(
+            &square,
+            &settings,
+            MoveType::WithoutFiber(MovePrintType::SolidInfill),
+            0.4,
+            0,
+        );
+
+        assert_eq!(chains.len(), 1);
+        assert!(chains[0]
+            .moves
+            .iter()
+            .all(|m| m.move_type != MoveType::Travel));
+
+        //a square loop ends where it starts, so four corners per loop
+        let corners = chains[0]
+            .moves
+            .iter()
+            .filter(|m| m.move_type == MoveType::WithoutFiber(MovePrintType::SolidInfill))
+            .count();
+        assert!(corners >= 4 * 11);
+    }
+
+    #[test]
+    fn honeycomb_waves_share_their_flats() {
+        let mut settings = Settings::default().get_layer_settings(0, 0.2);
+        settings.infill_perimeter_overlap_percentage = 0.0;
+
+        let rect = Polygon::new(
+            LineString::from(vec![(0.1, 0.1), (9.1, 0.1), (9.1, 3.5), (0.1, 3.5)]),
+            vec![],
+        );
+
+        let chains = wave_fill_polygon(
+            &rect,
+            &settings,
+            MoveType::WithoutFiber(MovePrintType::Infill),
+            1.0,
+            0.5,
+            3.0_f32.sqrt() / 2.0,
+            0.0,
+        );
+
+        let moves: Vec<(Coord<f32>, &Move)> = chains
+            .iter()
+            .flat_map(|chain| {
+                std::iter::once(chain.start_point)
+                    .chain(chain.moves.iter().map(|m| m.end))
+                    .zip(chain.moves.iter())
+            })
+            .collect();
+        let segments: Vec<(Coord<f32>, Coord<f32>)> = moves
+            .iter()
+            .filter(|(_, m)| m.move_type != MoveType::Travel)
+            .map(|(start, m)| (*start, m.end))
+            .collect();
+        let length =
+            |(start, end): &(Coord<f32>, Coord<f32>)| (*end - *start).x.hypot((*end - *start).y);
+
+        //the waves are walked back and forth in one chain, travelling over the flats of the next wave
+        assert_eq!(chains.len(), 1);
+        assert!(moves
+            .iter()
+            .filter(|(_, m)| m.move_type == MoveType::Travel)
+            .all(|(start, m)| length(&(*start, m.end)) < 2.0
+                && (-0.2..9.4).contains(&m.end.x)
+                && (-0.2..3.8).contains(&m.end.y)));
+        assert_eq!(segments.len(), 44);
+        assert!(segments.iter().all(|segment| length(segment) < 1.001));
+        assert_eq!(
+            segments
+                .iter()
+                .filter(|segment| (length(segment) - 1.0).abs() < 0.001)
+                .count(),
+            28
+        );
+        assert!((segments.iter().map(length).sum::<f32>() - 35.556).abs() < 0.01);
+
+        //the flats shared by two waves are printed once
+        let flats: Vec<_> = segments
+            .iter()
+            .filter(|(start, end)| (start.y - end.y).abs() < 0.001)
+            .map(|(start, end)| (start.y, start.x.min(end.x), start.x.max(end.x)))
+            .collect();
+        assert_eq!(
+            flats
+                .iter()
+                .filter(|(_, min, max)| (max - min - 1.0).abs() < 0.001)
+                .count(),
+            10
+        );
+        for (index, (y, min, max)) in flats.iter().enumerate() {
+            assert!(flats[index + 1..]
+                .iter()
+                .all(
+                    |(other_y, other_min, other_max)| (y - other_y).abs() > 0.001
+                        || other_min >= max
+                        || other_max <= min
+                ));
+        }
+    }
+
+    #[test]
+    fn gyroid_curves_lie_on_the_surface() {
+        for z in [0.0, 0.7, 1.6, 3.0] {
+            let curves = gyroid_curves(0.0, 10.0, 0.0, 10.0, z);
+            assert!(!curves.is_empty());
+
+            for point in curves.iter().flat_map(|curve| curve.0.iter()) {
+                let value = point.x.sin() * point.y.cos()
+                    + point.y.sin() * z.cos()
+                    + z.sin() * point.x.cos();
+
+                assert!(
+                    value.abs() < 0.001,
+                    "{:?} at {} is off by {}",
+                    point,
+                    z,
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gyroid_fill_follows_steep_parts() {
+        let mut settings = Settings::default().get_layer_settings(0, 0.2);
+        settings.infill_perimeter_overlap_percentage = 0.0;
+
+        let square = Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]),
+            vec![],
+        );
+
+        let spacing = 2.0;
+        let scale = spacing / std::f32::consts::PI;
+
+        for z in [0.0, 0.7, std::f32::consts::FRAC_PI_2, 1.6, 3.0] {
+            let chains = gyroid_fill_polygon(
+                &square,
+                &settings,
+                MoveType::WithoutFiber(MovePrintType::Infill),
+                spacing,
+                z * scale,
+            );
+
+            let segments: Vec<Line<f32>> = chains
+                .iter()
+                .flat_map(|chain| {
+                    std::iter::once(chain.start_point)
+                        .chain(chain.moves.iter().map(|m| m.end))
+                        .zip(chain.moves.iter())
+                        .map(|(start, m)| Line::new(start, m.end))
+                })
+                .collect();
+
+            let value = |point: Coord<f32>| {
+                let (x, y) = (point.x / scale, point.y / scale);
+                x.sin() * y.cos() + y.sin() * z.cos() + z.sin() * x.cos()
+            };
+
+            //every crossing of the surface with a fine grid inside the square lies on a printed curve
+            let step = 0.1;
+            for row in 5..95 {
+                for column in 5..95 {
+                    let start = Coord {
+                        x: column as f32 * step,
+                        y: row as f32 * step,
+                    };
+
+                    for end in [
+                        start + Coord { x: step, y: 0.0 },
+                        start + Coord { x: 0.0, y: step },
+                    ] {
+                        let (a, b) = (value(start), value(end));
+
+                        if (a >= 0.0) != (b >= 0.0) {
+                            let crossing = Point::from(start + (end - start) * (a / (a - b)));
+                            let distance = segments
+                                .iter()
+                                .map(|segment| segment.euclidean_distance(&crossing))
+                                .fold(f32::MAX, f32::min);
+
+                            assert!(
+                                distance < 0.02,
+                                "{:?} at {} is {} away from the fill",
+                                crossing,
+                                z,
+                                distance
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn line(row: i64, left: f32, right: f32) -> FillLine {
+        FillLine {
+            row,
+            left: Coord {
+                x: left,
+                y: row as f32,
+            },
+            right: Coord {
+                x: right,
+                y: row as f32,
+            },
+        }
+    }
+
+    #[test]
+    fn monotonic_runs_wait_for_the_lines_above() {
+        //an upside down U, the legs are split below row 2
+        let sections = vec![
+            vec![line(4, 0.0, 10.0), line(3, 0.0, 10.0), line(2, 0.0, 3.0)],
+            vec![line(2, 7.0, 10.0), line(1, 7.0, 10.0)],
+            vec![line(1, 0.0, 3.0)],
+        ];
+
+        let runs = monotonic_runs(&sections);
+        let order: Vec<(usize, usize)> = runs.iter().flatten().copied().collect();
+
+        assert_eq!(order.len(), 6);
+        assert_eq!(&order[..3], &[(0, 0), (0, 1), (0, 2)]);
+
+        let position = |entry| order.iter().position(|other| *other == entry);
+        assert!(position((2, 0)) > position((0, 2)));
+        assert!(position((1, 1)) > position((1, 0)));
+    }
+}
@@ -11,7 +11,7 @@ use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
 use crate::settings::LayerSettings;
 use crate::{Move, MoveChain, MovePrintType, MoveType, Slice};
 
-use super::polygon_operations::PolygonOperations;
+use super::polygon_operations::{PolygonOperations, ToFloat};
 
 pub fn lightning_infill(slices: &mut Vec<Slice>) {
     let mut lt = LightningForest { trees: vec![] };
@@ -65,9 +65,10 @@ pub fn lightning_layer(
             .offset_from(-(inset_amount))
     } else {
         slice.remaining_area.offset_from(-(inset_amount))
-    };
+    }
+    .to_float();
 
-    let infill_area = slice.remaining_area.clone().offset_from(-overlap);
+    let infill_area = slice.remaining_area.offset_from(-overlap).to_float();
 
     let (min_x, max_x, min_y, max_y) = unsupported_area
         .iter()
@@ -293,6 +294,7 @@ impl LightningNode {
                         end: self.location,
                         width,
                         move_type: MoveType::WithoutFiber(MovePrintType::Infill),
+                        overhang: None,
                     });
                 } else {
                     chains.push(MoveChain {
@@ -300,6 +302,7 @@ impl LightningNode {
                             end: self.location,
                             width,
                             move_type: MoveType::WithoutFiber(MovePrintType::Infill),
+                            overhang: None,
                         }],
                         start_point: child.location,
                         is_loop: false,
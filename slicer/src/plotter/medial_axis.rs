@@ -0,0 +1,602 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+use geo::prelude::*;
+use geo::{Coord, LineString, Point, Polygon};
+use itertools::Itertools;
+
+///A polyline along the medial axis, every point carries the radius of the largest circle around it inside the region
+pub type AxisLine = Vec<(Coord<f32>, f32)>;
+
+///A part of the axis is a branch into a corner if the boundary between its two closest points is shorter than this times the radius.
+///That is the case for convex corners wider than about 67 degree, the center line of a region always exceeds it.
+const BRANCH_RATIO: f64 = 3.0;
+
+///A point sampled on the boundary of the polygon
+struct Sample {
+    point: Coord<f64>,
+    ring: usize,
+
+    ///Distance along the ring from its first point
+    arc: f64,
+}
+
+struct Triangle {
+    vertices: [usize; 3],
+    center: Coord<f64>,
+    radius_squared: f64,
+}
+
+impl Triangle {
+    fn new(vertices: [usize; 3], points: &[Coord<f64>]) -> Self {
+        let [a, b, c] = vertices.map(|index| points[index]);
+        let (b, c) = (b - a, c - a);
+        let d = 2.0 * (b.x * c.y - b.y * c.x);
+
+        //collinear points, every later point lies inside of the circle and replaces the triangle
+        if d == 0.0 {
+            return Triangle {
+                vertices,
+                center: a,
+                radius_squared: f64::INFINITY,
+            };
+        }
+
+        let b_squared = b.x * b.x + b.y * b.y;
+        let c_squared = c.x * c.x + c.y * c.y;
+        let offset = Coord {
+            x: (c.y * b_squared - b.y * c_squared) / d,
+            y: (b.x * c_squared - c.x * b_squared) / d,
+        };
+
+        Triangle {
+            vertices,
+            center: a + offset,
+            radius_squared: offset.x * offset.x + offset.y * offset.y,
+        }
+    }
+
+    fn circumcircle_contains(&self, point: Coord<f64>) -> bool {
+        let offset = point - self.center;
+        offset.x * offset.x + offset.y * offset.y < self.radius_squared
+    }
+
+    ///The sides from every vertex to the next, counter clockwise
+    fn sides(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.vertices;
+        [(a, b), (b, c), (c, a)]
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        self.sides().map(|(a, b)| edge(a, b))
+    }
+
+    ///The side that has the point strictly on its outer side
+    fn side_facing(&self, point: Coord<f64>, points: &[Coord<f64>]) -> Option<usize> {
+        self.sides().iter().position(|(a, b)| {
+            let (a, b) = (points[*a], points[*b]);
+            (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x) < 0.0
+        })
+    }
+}
+
+///A vertex of the Voronoi diagram inside of the polygon
+struct Node {
+    center: Coord<f32>,
+
+    ///Distance to the boundary of the polygon
+    radius: f32,
+
+    ///Distance to the samples of its triangle
+    circle_radius: f64,
+}
+
+fn edge(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+///The medial axis of the polygon as polylines, closed polylines end at their first point.
+///It is approximated by the Voronoi diagram of points sampled along the boundary with the resolution.
+///Branches running into convex corners and parts closer than the min radius to the boundary are pruned.
+pub fn medial_axis(polygon: &Polygon<f32>, resolution: f32, min_radius: f32) -> Vec<AxisLine> {
+    let rings: Vec<&LineString<f32>> = std::iter::once(polygon.exterior())
+        .chain(polygon.interiors())
+        .collect();
+
+    let mut samples = vec![];
+    let mut ring_lengths = vec![];
+
+    for (ring, line_string) in rings.iter().enumerate() {
+        let mut arc = 0.0;
+
+        for line in line_string.lines() {
+            let start = Coord {
+                x: line.start.x as f64,
+                y: line.start.y as f64,
+            };
+            let direction = Coord {
+                x: line.end.x as f64,
+                y: line.end.y as f64,
+            } - start;
+            let length = direction.x.hypot(direction.y);
+
+            if length <= f64::EPSILON {
+                continue;
+            }
+
+            let pieces = (length / resolution as f64).ceil().max(1.0) as usize;
+            for piece in 0..pieces {
+                let t = piece as f64 / pieces as f64;
+
+                samples.push(Sample {
+                    point: start + direction * t,
+                    ring,
+                    arc: arc + length * t,
+                });
+            }
+
+            arc += length;
+        }
+
+        ring_lengths.push(arc);
+    }
+
+    if samples.len() < 3 {
+        return vec![];
+    }
+
+    //samples along parallel walls are cocircular, a tiny offset makes the triangulation unique
+    let jitter = resolution as f64 * 1e-4;
+    let points: Vec<Coord<f64>> = samples
+        .iter()
+        .enumerate()
+        .map(|(index, sample)| sample.point + tie_breaker(index) * jitter)
+        .collect();
+
+    let triangles = delaunay(&points);
+
+    let nodes: Vec<Option<Node>> = triangles
+        .iter()
+        .map(|triangle| {
+            let center = Coord {
+                x: triangle.center.x as f32,
+                y: triangle.center.y as f32,
+            };
+
+            if !polygon.contains(&Point::from(center)) {
+                return None;
+            }
+
+            let radius = rings
+                .iter()
+                .map(|ring| Point::from(center).euclidean_distance(*ring))
+                .fold(f32::MAX, f32::min);
+
+            if radius < min_radius {
+                return None;
+            }
+
+            Some(Node {
+                center,
+                radius,
+                circle_radius: triangle.radius_squared.sqrt(),
+            })
+        })
+        .collect();
+
+    let boundary_distance = |a: usize, b: usize| {
+        if samples[a].ring != samples[b].ring {
+            f64::INFINITY
+        } else {
+            let distance = (samples[a].arc - samples[b].arc).abs();
+            distance.min(ring_lengths[samples[a].ring] - distance)
+        }
+    };
+
+    let mut shared_edges: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+    for (index, triangle) in triangles.iter().enumerate() {
+        for triangle_edge in triangle.edges() {
+            shared_edges.entry(triangle_edge).or_default().push(index);
+        }
+    }
+
+    //the Voronoi edge between two triangles is dual to their shared edge
+    let mut adjacency: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    for ((a, b), shared) in shared_edges.iter() {
+        if let &[first, second] = shared.as_slice() {
+            if let (Some(first_node), Some(second_node)) = (&nodes[first], &nodes[second]) {
+                let radius = first_node.circle_radius.min(second_node.circle_radius);
+
+                if boundary_distance(*a, *b) > BRANCH_RATIO * radius {
+                    adjacency.entry(first).or_default().insert(second);
+                    adjacency.entry(second).or_default().insert(first);
+                }
+            }
+        }
+    }
+
+    let node = |index: usize| nodes[index].as_ref().expect("Axis nodes are kept");
+
+    //short spurs left by the sampling at the junctions are removed until none are left
+    let lines = loop {
+        let lines = walk(&adjacency);
+
+        let spurs: Vec<&Vec<usize>> = lines
+            .iter()
+            .filter(|line| {
+                let degree = |index: &usize| adjacency[index].len();
+                let (first, last) = (&line[0], &line[line.len() - 1]);
+
+                let junction = if degree(first) == 1 && degree(last) >= 3 {
+                    last
+                } else if degree(last) == 1 && degree(first) >= 3 {
+                    first
+                } else {
+                    return false;
+                };
+
+                let length: f32 = line
+                    .iter()
+                    .tuple_windows::<(_, _)>()
+                    .map(|(a, b)| node(*a).center.euclidean_distance(&node(*b).center))
+                    .sum();
+
+                length < 2.0 * node(*junction).radius
+            })
+            .collect();
+
+        if spurs.is_empty() {
+            break lines;
+        }
+
+        for line in spurs {
+            for (a, b) in line.iter().tuple_windows::<(_, _)>() {
+                if let Some(neighbours) = adjacency.get_mut(a) {
+                    neighbours.remove(b);
+                }
+                if let Some(neighbours) = adjacency.get_mut(b) {
+                    neighbours.remove(a);
+                }
+            }
+        }
+
+        adjacency.retain(|_, neighbours| !neighbours.is_empty());
+    };
+
+    //neighbouring Voronoi vertices of cocircular samples nearly coincide
+    let tolerance = resolution / 10.0;
+
+    lines
+        .into_iter()
+        .map(|line| {
+            let mut points: AxisLine = vec![];
+
+            for index in line.iter() {
+                let point = (node(*index).center, node(*index).radius);
+
+                match points.last() {
+                    Some(last) if last.0.euclidean_distance(&point.0) < tolerance => {}
+                    _ => points.push(point),
+                }
+            }
+
+            //the line keeps its end, closed lines end where they start
+            let end = node(line[line.len() - 1]);
+            if points.len() > 1 && points[points.len() - 1].0 != end.center {
+                let last = points.len() - 1;
+                points[last] = (end.center, end.radius);
+            }
+
+            points
+        })
+        .filter(|points| points.len() >= 2)
+        .collect()
+}
+
+///A deterministic offset in the unit square centered at zero
+fn tie_breaker(index: usize) -> Coord<f64> {
+    let hash = (index as u64)
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        .wrapping_add(0x7F4A_7C15);
+    let second_hash = hash.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let unit = |value: u64| (value >> 11) as f64 / (1u64 << 53) as f64 - 0.5;
+
+    Coord {
+        x: unit(hash),
+        y: unit(second_hash),
+    }
+}
+
+///Delaunay triangulation of the points by inserting them one after another into a triangle enclosing all of them.
+///Every point walks to the triangle containing it and only replaces the triangles around it whose circles contain it.
+fn delaunay(points: &[Coord<f64>]) -> Vec<Triangle> {
+    let count = points.len();
+
+    let (min, max) = points.iter().fold(
+        (
+            Coord {
+                x: f64::MAX,
+                y: f64::MAX,
+            },
+            Coord {
+                x: f64::MIN,
+                y: f64::MIN,
+            },
+        ),
+        |(min, max), point| {
+            (
+                Coord {
+                    x: min.x.min(point.x),
+                    y: min.y.min(point.y),
+                },
+                Coord {
+                    x: max.x.max(point.x),
+                    y: max.y.max(point.y),
+                },
+            )
+        },
+    );
+
+    let size = (max.x - min.x).max(max.y - min.y).max(f64::EPSILON);
+    let mid = (min + max) / 2.0;
+
+    let order = insertion_order(points, min, max);
+
+    let mut points = points.to_vec();
+    points.extend([
+        Coord {
+            x: mid.x - 20.0 * size,
+            y: mid.y - size,
+        },
+        Coord {
+            x: mid.x + 20.0 * size,
+            y: mid.y - size,
+        },
+        Coord {
+            x: mid.x,
+            y: mid.y + 20.0 * size,
+        },
+    ]);
+
+    let mut triangles = vec![Triangle::new([count, count + 1, count + 2], &points)];
+    //the triangles on the other side of the sides, None outside of the enclosing triangle
+    let mut neighbours: Vec<[Option<usize>; 3]> = vec![[None; 3]];
+    let mut removed = vec![false];
+    //the last point whose cavity contains the triangle
+    let mut cavity_of: Vec<Option<usize>> = vec![None];
+    let mut last = 0;
+
+    for index in order {
+        let point = points[index];
+
+        //a walk through a Delaunay triangulation always arrives at the triangle containing the point
+        let mut current = last;
+        let mut steps = 0;
+        while let Some(side) = triangles[current].side_facing(point, &points) {
+            match neighbours[current][side] {
+                Some(next) if steps < triangles.len() => current = next,
+                //only reached through rounding, any triangle whose circle contains the point starts the cavity
+                _ => {
+                    current = (0..triangles.len())
+                        .find(|triangle| {
+                            !removed[*triangle] && triangles[*triangle].circumcircle_contains(point)
+                        })
+                        .unwrap_or(current);
+                    break;
+                }
+            }
+
+            steps += 1;
+        }
+
+        let mut cavity = vec![current];
+        let mut open = vec![current];
+        cavity_of[current] = Some(index);
+
+        while let Some(triangle) = open.pop() {
+            for neighbour in neighbours[triangle].into_iter().flatten() {
+                if cavity_of[neighbour] != Some(index)
+                    && triangles[neighbour].circumcircle_contains(point)
+                {
+                    cavity_of[neighbour] = Some(index);
+                    cavity.push(neighbour);
+                    open.push(neighbour);
+                }
+            }
+        }
+
+        //the sides of the cavity with the triangles outside of it
+        let mut boundary = vec![];
+        for triangle in cavity {
+            removed[triangle] = true;
+
+            for (side, (a, b)) in triangles[triangle].sides().into_iter().enumerate() {
+                let outside = neighbours[triangle][side];
+
+                if outside.is_none_or(|outside| cavity_of[outside] != Some(index)) {
+                    boundary.push((a, b, outside));
+                }
+            }
+        }
+
+        //every side of the cavity forms a new triangle with the point
+        let first = triangles.len();
+        for (offset, &(a, b, outside)) in boundary.iter().enumerate() {
+            let next = boundary.iter().position(|(next_a, _, _)| *next_a == b);
+            let previous = boundary
+                .iter()
+                .position(|(_, previous_b, _)| *previous_b == a);
+
+            triangles.push(Triangle::new([a, b, index], &points));
+            neighbours.push([
+                outside,
+                next.map(|next| first + next),
+                previous.map(|previous| first + previous),
+            ]);
+            removed.push(false);
+            cavity_of.push(None);
+
+            if let Some(outside) = outside {
+                if let Some(side) = triangles[outside]
+                    .sides()
+                    .iter()
+                    .position(|side| *side == (b, a))
+                {
+                    neighbours[outside][side] = Some(first + offset);
+                }
+            }
+        }
+
+        last = first;
+    }
+
+    triangles
+        .into_iter()
+        .zip(removed)
+        .filter(|(triangle, removed)| {
+            !removed && triangle.vertices.iter().all(|vertex| *vertex < count)
+        })
+        .map(|(triangle, _)| triangle)
+        .collect()
+}
+
+///Rounds of growing size, about half of the points are in the last round, a quarter in the one before and so on.
+///Mixing the rounds keeps the cavities small on regularly sampled walls,
+///the snake order through a grid inside of a round keeps the walks between the points short.
+fn insertion_order(points: &[Coord<f64>], min: Coord<f64>, max: Coord<f64>) -> Vec<usize> {
+    let (width, height) = (max.x - min.x, max.y - min.y);
+    let count = points.len() as f64;
+    let cell = (width * height / count)
+        .sqrt()
+        .max(width.max(height) / count)
+        .max(f64::EPSILON);
+
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by_key(|index| {
+        let column = ((points[*index].x - min.x) / cell) as i64;
+        let row = ((points[*index].y - min.y) / cell) as i64;
+        let round = (*index as u64 + 1)
+            .wrapping_mul(0xBF58_476D_1CE4_E5B9)
+            .leading_zeros();
+
+        (
+            Reverse(round),
+            row,
+            if row % 2 == 0 { column } else { -column },
+        )
+    });
+
+    order
+}
+
+///Splits the graph into lines between ends and junctions, the remaining cycles become closed lines
+fn walk(adjacency: &BTreeMap<usize, BTreeSet<usize>>) -> Vec<Vec<usize>> {
+    let mut visited: BTreeSet<(usize, usize)> = BTreeSet::new();
+    let mut lines = vec![];
+
+    let starts = adjacency
+        .iter()
+        .filter(|(_, neighbours)| neighbours.len() != 2)
+        .chain(
+            adjacency
+                .iter()
+                .filter(|(_, neighbours)| neighbours.len() == 2),
+        );
+
+    for (&start, neighbours) in starts {
+        for &next in neighbours {
+            if !visited.insert(edge(start, next)) {
+                continue;
+            }
+
+            let mut line = vec![start];
+            let mut current = next;
+
+            loop {
+                line.push(current);
+
+                if adjacency[&current].len() != 2 {
+                    break;
+                }
+
+                match adjacency[&current]
+                    .iter()
+                    .find(|neighbour| !visited.contains(&edge(current, **neighbour)))
+                {
+                    Some(&neighbour) => {
+                        visited.insert(edge(current, neighbour));
+                        current = neighbour;
+                    }
+                    None => break,
+                }
+            }
+
+            lines.push(line);
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delaunay_triangles_have_empty_circumcircles() {
+        let points: Vec<Coord<f64>> = (0..40)
+            .map(|index| {
+                let angle = index as f64 * 2.4;
+                let radius = (index as f64).sqrt();
+                Coord {
+                    x: radius * angle.cos(),
+                    y: radius * angle.sin(),
+                }
+            })
+            .collect();
+
+        let triangles = delaunay(&points);
+
+        assert!(!triangles.is_empty());
+        for triangle in triangles.iter() {
+            for (index, point) in points.iter().enumerate() {
+                if !triangle.vertices.contains(&index) {
+                    let offset = *point - triangle.center;
+                    assert!(
+                        offset.x * offset.x + offset.y * offset.y
+                            >= triangle.radius_squared * (1.0 - 1e-9)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn long_thin_walls_are_triangulated_quickly() {
+        //20000 samples, testing every triangle for every point would take minutes
+        let wall = Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (500.0, 0.0), (500.0, 0.3), (0.0, 0.3)]),
+            vec![],
+        );
+
+        let start = std::time::Instant::now();
+        let lines = medial_axis(&wall, 0.05, 0.025);
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+
+        assert_eq!(lines.len(), 1);
+        let (min_x, max_x) = lines[0]
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), (point, _)| {
+                (min.min(point.x), max.max(point.x))
+            });
+        assert!(min_x < 1.0 && max_x > 499.0);
+
+        //the radius shrinks towards the closed ends
+        for (point, radius) in lines[0].iter() {
+            assert!((point.y - 0.15).abs() < 0.01);
+            if point.x > 1.0 && point.x < 499.0 {
+                assert!((radius - 0.15).abs() < 0.01);
+            }
+        }
+    }
+}
@@ -1,13 +1,20 @@
+pub(crate) mod adaptive_cubic;
+pub(crate) mod fuzzy_skin;
 mod infill;
 pub(crate) mod lightning_infill;
+mod medial_axis;
 mod monotone;
+mod overhang;
 mod perimeter;
 pub mod polygon_operations;
+pub(crate) mod raft;
+mod seam;
 pub(crate) mod support;
+pub(crate) mod tree_support;
 
 use crate::{Move, MoveChain, MovePrintType};
 
-use crate::settings::SkirtSettings;
+use crate::settings::{SkirtSettings, ThinWallSettings};
 use crate::utils::point_lerp;
 use crate::{Command, MoveType, Object, RetractionType, Settings, Slice, StateChange};
 use geo::coordinate_position::CoordPos;
@@ -18,28 +25,47 @@ pub use infill::*;
 use itertools::Itertools;
 use ordered_float::OrderedFloat;
 use perimeter::*;
-use polygon_operations::PolygonOperations;
+use polygon_operations::{FixedMultiPolygon, PolygonOperations, ToFloat};
+use shared::process::Process;
 
 pub trait Plotter {
-    fn slice_perimeters_into_chains(&mut self, number_of_perimeters: usize);
+    fn slice_perimeters_into_chains(
+        &mut self,
+        number_of_perimeters: usize,
+        thin_walls: Option<&ThinWallSettings>,
+    );
     fn shrink_layer(&mut self);
+    fn fill_gaps(&mut self);
     fn fill_remaining_area(&mut self, solid: bool, layer_count: usize);
-    fn fill_solid_subtracted_area(&mut self, other: &MultiPolygon<f32>, layer_count: usize);
-    fn fill_solid_bridge_area(&mut self, layer_below: &MultiPolygon<f32>);
-    fn fill_solid_top_layer(&mut self, layer_above: &MultiPolygon<f32>, layer_count: usize);
+    fn fill_solid_subtracted_area(&mut self, other: &FixedMultiPolygon, layer_count: usize);
+    fn fill_solid_bridge_area(&mut self, layer_below: &FixedMultiPolygon);
+    fn fill_solid_top_layer(&mut self, layer_above: &FixedMultiPolygon, layer_count: usize);
     fn generate_skirt(
         &mut self,
         convex_polygon: &Polygon<f32>,
         skirt_settings: &SkirtSettings,
         settings: &Settings,
     );
-    fn generate_brim(&mut self, entire_first_layer: MultiPolygon<f32>, brim_width: f32);
+    fn generate_brim(&mut self, entire_first_layer: FixedMultiPolygon, brim_width: f32);
     fn order_chains(&mut self);
+    fn place_seams(&mut self, previous_seams: &[Coord<f32>]) -> Vec<Coord<f32>>;
+    fn classify_overhangs(&mut self, layer_below: &FixedMultiPolygon);
+    fn spiralize(&mut self, start_near: Option<Coord<f32>>) -> Option<Coord<f32>>;
     fn slice_into_commands(&mut self, commands: &mut Vec<Command>, layer_thickness: f32);
+    fn slice_into_spiral_commands(
+        &mut self,
+        commands: &mut Vec<Command>,
+        start_z: f32,
+        continues_spiral: bool,
+    );
 }
 
 impl Plotter for Slice {
-    fn slice_perimeters_into_chains(&mut self, number_of_perimeters: usize) {
+    fn slice_perimeters_into_chains(
+        &mut self,
+        number_of_perimeters: usize,
+        thin_walls: Option<&ThinWallSettings>,
+    ) {
         let mut new_chains = self
             .remaining_area
             .iter()
@@ -56,6 +82,15 @@ impl Plotter for Slice {
 
         self.fixed_chains.append(&mut new_chains);
 
+        //regions too thin for the perimeters would otherwise vanish
+        if let Some(thin_walls) = thin_walls.filter(|_| number_of_perimeters > 0) {
+            self.fixed_chains.append(&mut thin_wall_chains(
+                &self.remaining_area,
+                &self.layer_settings,
+                thin_walls,
+            ));
+        }
+
         let perimeter_inset = if number_of_perimeters == 0 {
             0.0
         } else if number_of_perimeters == 1 {
@@ -87,9 +122,25 @@ impl Plotter for Slice {
         }
     }
 
+    fn fill_gaps(&mut self) {
+        let width = self.layer_settings.extrusion_width.gap_fill;
+        let gaps = narrow_regions(&self.remaining_area, width);
+
+        for polygon in gaps.iter() {
+            self.chains.append(&mut medial_axis_chains(
+                &polygon.to_float(),
+                width / 2.0,
+                width,
+                MoveType::WithoutFiber(MovePrintType::GapFill),
+            ));
+        }
+
+        self.remaining_area = self.remaining_area.difference_with(&gaps);
+    }
+
     fn fill_remaining_area(&mut self, solid: bool, layer_count: usize) {
         //For each region still available fill wih infill
-        for poly in &self.remaining_area {
+        for poly in &self.remaining_area.to_float() {
             if solid {
                 let new_moves = solid_infill_polygon(
                     poly,
@@ -120,7 +171,7 @@ impl Plotter for Slice {
         self.remaining_area = MultiPolygon(vec![])
     }
 
-    fn fill_solid_subtracted_area(&mut self, other: &MultiPolygon<f32>, layer_count: usize) {
+    fn fill_solid_subtracted_area(&mut self, other: &FixedMultiPolygon, layer_count: usize) {
         //For each area not in this slice that is in the other polygon, fill solid
 
         let solid_area = self
@@ -133,7 +184,7 @@ impl Plotter for Slice {
 
         let layer_settings = &self.layer_settings;
         self.chains
-            .extend(&mut solid_area.0.iter().flat_map(|poly| {
+            .extend(&mut solid_area.to_float().0.iter().flat_map(|poly| {
                 linear_fill_polygon(
                     poly,
                     layer_settings,
@@ -146,7 +197,7 @@ impl Plotter for Slice {
         self.remaining_area = self.remaining_area.difference_with(&solid_area)
     }
 
-    fn fill_solid_bridge_area(&mut self, layer_below: &MultiPolygon<f32>) {
+    fn fill_solid_bridge_area(&mut self, layer_below: &FixedMultiPolygon) {
         //For each area not in this slice that is in the other polygon, fill solid
 
         let solid_area = self
@@ -158,15 +209,18 @@ impl Plotter for Slice {
         let layer_settings = &self.layer_settings;
         self.chains
             .extend(&mut solid_area.0.iter().flat_map(|poly| {
-                let unsupported_area: MultiPolygon<f32> = poly.difference_with(layer_below);
-                let mut angle = get_optimal_bridge_angle(poly, &unsupported_area);
+                let unsupported_area = MultiPolygon(vec![poly.clone()])
+                    .difference_with(layer_below)
+                    .to_float();
+                let poly = poly.to_float();
+                let mut angle = get_optimal_bridge_angle(&poly, &unsupported_area);
 
                 if angle < 0.0 {
                     angle += 180.0;
                 }
 
                 linear_fill_polygon(
-                    poly,
+                    &poly,
                     layer_settings,
                     MoveType::WithoutFiber(MovePrintType::Bridging),
                     angle,
@@ -177,7 +231,7 @@ impl Plotter for Slice {
         self.remaining_area = self.remaining_area.difference_with(&solid_area)
     }
 
-    fn fill_solid_top_layer(&mut self, layer_above: &MultiPolygon<f32>, layer_count: usize) {
+    fn fill_solid_top_layer(&mut self, layer_above: &FixedMultiPolygon, layer_count: usize) {
         //For each area not in this slice that is in the other polygon, fill solid
 
         let solid_area = self
@@ -186,7 +240,7 @@ impl Plotter for Slice {
             .offset_from(self.layer_settings.extrusion_width.solid_top_infill * 4.0)
             .intersection_with(&self.remaining_area);
 
-        for poly in &solid_area {
+        for poly in &solid_area.to_float() {
             let angle = 45.0 + (120_f32) * layer_count as f32;
 
             let new_moves = linear_fill_polygon(
@@ -232,6 +286,7 @@ impl Plotter for Slice {
                         .layer_settings
                         .extrusion_width
                         .exterior_surface_perimeter,
+                    overhang: None,
                 }
             })
             .collect();
@@ -254,7 +309,7 @@ impl Plotter for Slice {
         });
     }
 
-    fn generate_brim(&mut self, entire_first_layer: MultiPolygon<f32>, brim_width: f32) {
+    fn generate_brim(&mut self, entire_first_layer: FixedMultiPolygon, brim_width: f32) {
         let layer_settings = &self.layer_settings;
         self.fixed_chains.extend(
             (0..((brim_width
@@ -268,7 +323,7 @@ impl Plotter for Slice {
                     (i as f32 * layer_settings.extrusion_width.exterior_surface_perimeter)
                         + (layer_settings.extrusion_width.exterior_surface_perimeter / 2.0)
                 })
-                .map(|distance| entire_first_layer.offset_from(distance))
+                .map(|distance| entire_first_layer.offset_from(distance).to_float())
                 .flat_map(|multi| {
                     multi.into_iter().map(|poly| {
                         let moves = poly
@@ -280,6 +335,7 @@ impl Plotter for Slice {
                                 end,
                                 move_type: MoveType::WithoutFiber(MovePrintType::WallOuter),
                                 width: layer_settings.extrusion_width.exterior_surface_perimeter,
+                                overhang: None,
                             })
                             .collect();
 
@@ -327,6 +383,28 @@ impl Plotter for Slice {
         self.chains = ordered_chains;
     }
 
+    fn place_seams(&mut self, previous_seams: &[Coord<f32>]) -> Vec<Coord<f32>> {
+        seam::place_seams(self, previous_seams)
+    }
+
+    fn classify_overhangs(&mut self, layer_below: &FixedMultiPolygon) {
+        overhang::classify_overhangs(self, layer_below)
+    }
+
+    ///Replaces everything generated for this slice with a single outer wall.
+    ///Returns the start of the wall, the wall of the next layer should start close to it.
+    fn spiralize(&mut self, start_near: Option<Coord<f32>>) -> Option<Coord<f32>> {
+        self.remaining_area = MultiPolygon(vec![]);
+        self.chains.clear();
+        self.combined_chains.clear();
+
+        self.fixed_chains = spiral_outline(&self.main_polygon, &self.layer_settings, start_near)
+            .into_iter()
+            .collect();
+
+        self.fixed_chains.first().map(|chain| chain.start_point)
+    }
+
     fn slice_into_commands(&mut self, commands: &mut Vec<Command>, layer_thickness: f32) {
         if !self.fixed_chains.is_empty() {
             commands.push(Command::SetState {
@@ -340,7 +418,19 @@ impl Plotter for Slice {
                 },
             });
 
-            for chain in self.fixed_chains.drain(..).chain(self.chains.drain(..)) {
+            let combined_thickness = self.combined_thickness;
+
+            for (chain, thickness) in self
+                .fixed_chains
+                .drain(..)
+                .chain(self.chains.drain(..))
+                .map(|chain| (chain, layer_thickness))
+                .chain(
+                    self.combined_chains
+                        .drain(..)
+                        .map(|chain| (chain, combined_thickness)),
+                )
+            {
                 let retraction_length = self.layer_settings.retraction_length;
                 let retract_command =
                     if let Some(retraction_wipe) = self.layer_settings.retraction_wipe.as_ref() {
@@ -446,12 +536,139 @@ impl Plotter for Slice {
                 commands.push(Command::MoveTo {
                     end: chain.start_point,
                 });
-                commands.append(&mut chain.create_commands(&self.layer_settings, layer_thickness));
+                commands.append(&mut chain.create_commands(&self.layer_settings, thickness));
 
                 commands.push(retract_command);
             }
         }
     }
+
+    ///The outer wall rises from the start height to the top of the slice along its length.
+    ///The wall continues the spiral of the layer below without a retraction if continues_spiral is set.
+    fn slice_into_spiral_commands(
+        &mut self,
+        commands: &mut Vec<Command>,
+        start_z: f32,
+        continues_spiral: bool,
+    ) {
+        let layer_thickness = self.top_height - start_z;
+
+        for chain in self.fixed_chains.drain(..) {
+            if !continues_spiral {
+                commands.push(Command::SetState {
+                    new_state: StateChange {
+                        bed_temp: None,
+                        extruder_temp: None,
+                        fan_speed: None,
+                        movement_speed: Some(self.layer_settings.speed.travel),
+                        acceleration: Some(self.layer_settings.acceleration.travel),
+                        retract: RetractionType::Retract,
+                    },
+                });
+            }
+
+            commands.push(Command::MoveTo {
+                end: chain.start_point,
+            });
+
+            let chain_commands = chain.create_commands(&self.layer_settings, layer_thickness);
+
+            let length: f32 = chain_commands
+                .iter()
+                .map(|cmd| match cmd {
+                    Command::MoveAndExtrude { start, end, .. } => start.euclidean_distance(end),
+                    _ => 0.0,
+                })
+                .sum();
+
+            let mut travelled = 0.0;
+
+            commands.extend(chain_commands.into_iter().map(|cmd| match cmd {
+                Command::MoveAndExtrude {
+                    start,
+                    end,
+                    thickness,
+                    width,
+                } => {
+                    let move_start_z = start_z + layer_thickness * travelled / length;
+                    travelled += start.euclidean_distance(&end);
+
+                    Command::MoveAndExtrudeSpiral {
+                        start,
+                        end,
+                        start_z: move_start_z,
+                        end_z: start_z + layer_thickness * travelled / length,
+                        thickness,
+                        width,
+                    }
+                }
+                //the extruder stays primed between the layers of the spiral
+                Command::SetState { mut new_state } if continues_spiral => {
+                    new_state.retract = RetractionType::NoRetract;
+                    Command::SetState { new_state }
+                }
+                cmd => cmd,
+            }));
+        }
+    }
+}
+
+///Moves the partial infill shared by groups of slices onto the top slice of each group.
+///There it is printed once with the combined height of the group, the rest of the infill stays on its slice.
+///A group ends after the given number of layers or before it gets thicker than the max thickness.
+pub fn combine_infill_layers(slices: &mut [Slice], layers: usize, max_thickness: f32) {
+    let mut start = 0;
+
+    while start < slices.len() {
+        let mut end = start + 1;
+
+        while end < slices.len()
+            && end - start < layers
+            && slices[end].top_height - slices[start].bottom_height <= max_thickness
+        {
+            end += 1;
+        }
+
+        combine_group(&mut slices[start..end]);
+        start = end;
+    }
+}
+
+fn combine_group(group: &mut [Slice]) {
+    if group.len() < 2 {
+        return;
+    }
+
+    let combined = group[1..]
+        .iter()
+        .fold(group[0].remaining_area.clone(), |area, slice| {
+            area.intersection_with(&slice.remaining_area)
+        });
+
+    if combined.0.is_empty() {
+        return;
+    }
+
+    let bottom_height = group[0].bottom_height;
+
+    for slice in group.iter_mut() {
+        slice.remaining_area = slice.remaining_area.difference_with(&combined);
+    }
+
+    let combined = combined.to_float();
+
+    let top = group.last_mut().expect("Groups contain slices");
+    top.combined_thickness = top.top_height - bottom_height;
+
+    for poly in combined.iter() {
+        top.combined_chains.append(&mut partial_infill_polygon(
+            poly,
+            &top.layer_settings,
+            top.layer_settings.infill_percentage,
+            top.layer,
+            top.get_height(),
+        ));
+    }
 }
 
 fn get_optimal_bridge_angle(fill_area: &Polygon<f32>, unsupported_area: &MultiPolygon<f32>) -> f32 {
@@ -514,7 +731,11 @@ fn get_optimal_bridge_angle(fill_area: &Polygon<f32>, unsupported_area: &MultiPo
         .unwrap_or(0.0)
 }
 
-pub fn convert_objects_into_moves(objects: Vec<Object>, settings: &Settings) -> Vec<Command> {
+pub fn convert_objects_into_moves(
+    objects: Vec<Object>,
+    settings: &Settings,
+    process: &Process,
+) -> Vec<Command> {
     // info!("Convert into Commnds");
     let mut layer_moves: Vec<(f32, Vec<Command>)> = objects
         .into_iter()
@@ -527,30 +748,44 @@ pub fn convert_objects_into_moves(objects: Vec<Object>, settings: &Settings) ->
                 .into_iter()
                 .enumerate()
                 .map(|(layer_num, mut slice)| {
-                    let layer_settings = settings.get_layer_settings(layer_num, slice.top_height);
+                    //the layer settings of the slice are resolved before the raft shifted its index
+                    let layer_settings = slice.layer_settings.clone();
                     let mut moves = vec![];
                     moves.push(Command::ChangeObject { object: object_num });
+                    //the spiral rises from the top of the layer below
+                    let first_spiral_layer = settings.raft_layers() + settings.bottom_layers;
+                    let spiral = settings.spiral_vase && layer_num >= first_spiral_layer;
+
                     moves.push(Command::LayerChange {
-                        z: slice.top_height,
+                        z: if spiral { last_layer } else { slice.top_height },
                         index: layer_num,
                     });
                     moves.push(Command::SetState {
                         new_state: StateChange {
                             extruder_temp: Some(layer_settings.extruder_temp),
                             bed_temp: Some(layer_settings.bed_temp),
-                            fan_speed: Some(if layer_num < settings.fan.disable_fan_for_layers {
-                                0.0
-                            } else {
-                                settings.fan.fan_speed
-                            }),
+                            fan_speed: None,
                             movement_speed: None,
                             acceleration: None,
                             retract: RetractionType::NoRetract,
                         },
                     });
-                    slice.slice_into_commands(&mut moves, slice.top_height - last_layer);
+                    if spiral {
+                        slice.slice_into_spiral_commands(
+                            &mut moves,
+                            last_layer,
+                            layer_num > first_spiral_layer,
+                        );
+                    } else {
+                        //the first layer above the air gap of the raft is only as thick as itself
+                        slice.slice_into_commands(
+                            &mut moves,
+                            slice.top_height - last_layer.max(slice.bottom_height),
+                        );
+                    }
 
                     last_layer = slice.top_height;
+                    process.step();
                     (slice.top_height, moves)
                 })
                 .collect::<Vec<(f32, Vec<Command>)>>()
@@ -566,3 +801,86 @@ pub fn convert_objects_into_moves(objects: Vec<Object>, settings: &Settings) ->
         .flat_map(|(_, layer_moves)| layer_moves)
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_slices(layer_height: f32, count: usize, settings: &Settings) -> Vec<Slice> {
+        (0..count)
+            .map(|layer| {
+                Slice::from_single_point_loop(
+                    [(0.0, 0.0), (20.0, 0.0), (20.0, 20.0), (0.0, 20.0)].into_iter(),
+                    layer as f32 * layer_height,
+                    (layer + 1) as f32 * layer_height,
+                    layer,
+                    settings,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn infill_is_combined_onto_the_top_slice_of_a_group() {
+        let settings = Settings::default();
+        let mut slices = square_slices(0.1, 4, &settings);
+
+        combine_infill_layers(&mut slices, 3, settings.nozzle_diameter * 0.8);
+
+        for slice in slices[..3].iter() {
+            assert!(slice.remaining_area.0.is_empty());
+        }
+        assert!(slices[0].combined_chains.is_empty());
+        assert!(slices[1].combined_chains.is_empty());
+        assert!(!slices[2].combined_chains.is_empty());
+        assert!((slices[2].combined_thickness - 0.3).abs() < 0.0001);
+
+        //the last slice is a group of its own and keeps its infill area
+        assert!(slices[3].combined_chains.is_empty());
+        assert!(!slices[3].remaining_area.0.is_empty());
+
+        let top = &mut slices[2];
+        let start = Coord { x: 0.0, y: 0.0 };
+        top.fixed_chains.push(MoveChain {
+            start_point: start,
+            moves: vec![Move {
+                end: Coord { x: 20.0, y: 0.0 },
+                move_type: MoveType::WithoutFiber(MovePrintType::WallOuter),
+                width: 0.4,
+                overhang: None,
+            }],
+            is_loop: false,
+        });
+
+        let mut commands = vec![];
+        top.slice_into_commands(&mut commands, 0.1);
+
+        let thicknesses: Vec<f32> = commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                Command::MoveAndExtrude { thickness, .. } => Some(*thickness),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(thicknesses[0], 0.1);
+        assert!(thicknesses[1..]
+            .iter()
+            .all(|thickness| (thickness - 0.3).abs() < 0.0001));
+        assert!(thicknesses.len() > 1);
+    }
+
+    #[test]
+    fn groups_are_not_thicker_than_the_nozzle_allows() {
+        let settings = Settings::default();
+        let mut slices = square_slices(0.2, 3, &settings);
+
+        //two layers of 0.2 mm are already thicker than 80% of the 0.4 mm nozzle
+        combine_infill_layers(&mut slices, 3, settings.nozzle_diameter * 0.8);
+
+        for slice in slices.iter() {
+            assert!(slice.combined_chains.is_empty());
+            assert!(!slice.remaining_area.0.is_empty());
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use geo::prelude::*;
+use geo::{Closest, Coord, Line, MultiLineString, MultiPolygon, Point};
+
+use super::polygon_operations::{FixedMultiPolygon, ToFloat};
+use crate::{Move, MoveChain, MovePrintType, MoveType, Slice};
+
+///Number of overhang classes, every class covers a quarter of the extrusion width
+const OVERHANG_CLASSES: usize = 4;
+
+///Moves with at most this part of their width over air count as supported
+const SUPPORTED_FRACTION: f32 = 0.05;
+
+///Splits the perimeters of the slice where they leave the layer below and sorts the parts into overhang classes.
+///The moves are cut into pieces of a quarter of their width, neighbouring pieces of the same class are joined again.
+pub fn classify_overhangs(slice: &mut Slice, layer_below: &FixedMultiPolygon) {
+    //the first layer rests on the bed or the raft
+    if layer_below.0.is_empty() {
+        return;
+    }
+
+    //the moves are measured against the layer below in float coordinates
+    let layer_below = layer_below.to_float();
+
+    let boundary = MultiLineString(
+        layer_below
+            .iter()
+            .flat_map(|poly| std::iter::once(poly.exterior()).chain(poly.interiors()))
+            .cloned()
+            .collect(),
+    );
+
+    for chain in slice.fixed_chains.iter_mut() {
+        classify_chain(chain, &layer_below, &boundary);
+    }
+}
+
+fn classify_chain(
+    chain: &mut MoveChain,
+    layer_below: &MultiPolygon<f32>,
+    boundary: &MultiLineString<f32>,
+) {
+    let mut current = chain.start_point;
+    let mut moves: Vec<Move> = Vec::with_capacity(chain.moves.len());
+
+    for m in chain.moves.drain(..) {
+        if is_perimeter(&m.move_type)
+            && m.width > 0.0
+            && !is_supported(Line::new(current, m.end), m.width, layer_below, boundary)
+        {
+            let direction = m.end - current;
+            let length = (direction.x * direction.x + direction.y * direction.y).sqrt();
+            let pieces = ((length / (m.width / OVERHANG_CLASSES as f32)).ceil() as usize).max(1);
+
+            for piece in 1..=pieces {
+                let start = current + direction * ((piece - 1) as f32 / pieces as f32);
+                let end = if piece == pieces {
+                    m.end
+                } else {
+                    current + direction * (piece as f32 / pieces as f32)
+                };
+
+                let overhang = overhang_class((start + end) / 2.0, m.width, layer_below, boundary);
+
+                match moves.last_mut() {
+                    //the pieces of one move are on a line, so they can be joined
+                    Some(last) if piece > 1 && last.overhang == overhang => last.end = end,
+                    _ => moves.push(Move { end, overhang, ..m }),
+                }
+            }
+        } else {
+            moves.push(m);
+        }
+
+        current = m.end;
+    }
+
+    chain.moves = moves;
+}
+
+fn is_perimeter(move_type: &MoveType) -> bool {
+    matches!(
+        move_type,
+        MoveType::WithoutFiber(
+            MovePrintType::WallOuter
+                | MovePrintType::WallInner
+                | MovePrintType::InteriorWallOuter
+                | MovePrintType::InteriorWallInner
+        )
+    )
+}
+
+///Moves far enough inside of the layer below don't need to be split
+fn is_supported(
+    line: Line<f32>,
+    width: f32,
+    layer_below: &MultiPolygon<f32>,
+    boundary: &MultiLineString<f32>,
+) -> bool {
+    layer_below.intersects(&Point::from(line.start))
+        && layer_below.intersects(&Point::from(line.end))
+        && boundary
+            .iter()
+            .all(|edge| line.euclidean_distance(edge) >= width / 2.0)
+}
+
+///The class of the part of the extrusion width at the point that hangs over air, None if it is supported
+fn overhang_class(
+    point: Coord<f32>,
+    width: f32,
+    layer_below: &MultiPolygon<f32>,
+    boundary: &MultiLineString<f32>,
+) -> Option<usize> {
+    let distance = match boundary.closest_point(&Point::from(point)) {
+        Closest::SinglePoint(closest) | Closest::Intersection(closest) => {
+            closest.0.euclidean_distance(&point)
+        }
+        Closest::Indeterminate => return None,
+    };
+
+    //distance to the edge of the layer below, negative above the layer
+    let distance = if layer_below.intersects(&Point::from(point)) {
+        -distance
+    } else {
+        distance
+    };
+
+    let fraction = ((distance + width / 2.0) / width).clamp(0.0, 1.0);
+
+    if fraction <= SUPPORTED_FRACTION {
+        None
+    } else {
+        Some(((fraction * OVERHANG_CLASSES as f32).ceil() as usize).clamp(1, OVERHANG_CLASSES) - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wall_leaving_the_layer_below_gets_rising_classes() {
+        let layer_below = MultiPolygon(vec![geo::Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord { x: 10.0, y: 10.0 },
+        )
+        .to_polygon()]);
+
+        let boundary = MultiLineString(vec![layer_below.0[0].exterior().clone()]);
+
+        let mut chain = MoveChain {
+            start_point: Coord { x: 5.0, y: 5.0 },
+            moves: vec![Move {
+                end: Coord { x: 15.0, y: 5.0 },
+                move_type: MoveType::WithoutFiber(MovePrintType::WallOuter),
+                width: 0.4,
+                overhang: None,
+            }],
+            is_loop: false,
+        };
+
+        classify_chain(&mut chain, &layer_below, &boundary);
+
+        let classes: Vec<Option<usize>> = chain.moves.iter().map(|m| m.overhang).collect();
+        assert_eq!(classes, vec![None, Some(0), Some(1), Some(2), Some(3)]);
+
+        assert!((chain.moves[0].end.x - 9.8).abs() < 0.001);
+        assert!((chain.moves[3].end.x - 10.1).abs() < 0.001);
+        assert_eq!(chain.moves[4].end, Coord { x: 15.0, y: 5.0 });
+    }
+}
@@ -2,14 +2,16 @@ use geo::prelude::*;
 use geo::*;
 
 use itertools::Itertools;
+use ordered_float::OrderedFloat;
 
-use crate::settings::LayerSettings;
+use crate::settings::{LayerSettings, ThinWallSettings};
 use crate::{Move, MoveChain, MovePrintType, MoveType};
 
-use super::polygon_operations::PolygonOperations;
+use super::medial_axis::medial_axis;
+use super::polygon_operations::{FixedMultiPolygon, PolygonOperations, ToFloat};
 
 pub fn inset_polygon_recursive(
-    poly: &MultiPolygon<f32>,
+    poly: &FixedMultiPolygon,
     settings: &LayerSettings,
     outer_perimeter: bool,
     layer_left: usize,
@@ -24,7 +26,7 @@ pub fn inset_polygon_recursive(
     );
 
     for raw_polygon in inset_poly.0.iter() {
-        let polygon = raw_polygon.simplify(&0.01);
+        let polygon = raw_polygon.to_float().simplify(&0.01);
         let mut outer_chains = vec![];
         let moves: Vec<Move> = polygon
             .exterior()
@@ -43,6 +45,7 @@ pub fn inset_polygon_recursive(
                     width: settings
                         .extrusion_width
                         .get_value_for_movement_type(&move_type),
+                    overhang: None,
                 }
             })
             .collect();
@@ -68,6 +71,7 @@ pub fn inset_polygon_recursive(
                     width: settings
                         .extrusion_width
                         .get_value_for_movement_type(&move_type),
+                    overhang: None,
                 });
             }
 
@@ -80,7 +84,7 @@ pub fn inset_polygon_recursive(
 
         let mut inner_chains = vec![];
         if layer_left != 0 {
-            let rec_inset_poly = polygon.offset_from(
+            let rec_inset_poly = MultiPolygon(vec![raw_polygon.clone()]).offset_from(
                 if outer_perimeter {
                     settings.extrusion_width.interior_surface_perimeter
                 } else {
@@ -90,7 +94,7 @@ pub fn inset_polygon_recursive(
 
             for polygon_rec in rec_inset_poly {
                 if let Some(mc) = inset_polygon_recursive(
-                    &MultiPolygon::from(polygon_rec),
+                    &MultiPolygon(vec![polygon_rec]),
                     settings,
                     false,
                     layer_left - 1,
@@ -119,6 +123,7 @@ pub fn inset_polygon_recursive(
                     end: chain.start_point,
                     move_type: MoveType::Travel,
                     width: 0.0,
+                    overhang: None,
                 });
                 full_moves.append(&mut chain.moves)
             }
@@ -130,3 +135,297 @@ pub fn inset_polygon_recursive(
             }
         })
 }
+
+///The outer wall of the largest polygon of the area as a single loop for the spiral vase mode.
+///The loop starts at the corner closest to the given point, so consecutive layers connect without a travel.
+pub fn spiral_outline(
+    area: &FixedMultiPolygon,
+    settings: &LayerSettings,
+    start_near: Option<Coord<f32>>,
+) -> Option<MoveChain> {
+    let move_type = MoveType::WithoutFiber(MovePrintType::WallOuter);
+    let width = settings
+        .extrusion_width
+        .get_value_for_movement_type(&move_type);
+
+    let inset = area.offset_from(width / -2.0).to_float();
+    let polygon = inset
+        .iter()
+        .max_by(|a, b| {
+            a.unsigned_area()
+                .partial_cmp(&b.unsigned_area())
+                .expect("Areas should not be NAN")
+        })?
+        .simplify(&0.01);
+
+    //the closing point repeats the first one
+    let mut points = polygon.exterior().0.clone();
+    points.pop();
+
+    if points.len() < 3 {
+        return None;
+    }
+
+    if let Some(start_near) = start_near {
+        let start = points
+            .iter()
+            .position_min_by_key(|point| OrderedFloat(point.euclidean_distance(&start_near)))
+            .expect("Loops contain points");
+
+        points.rotate_left(start);
+    }
+
+    let start_point = points[0];
+
+    Some(MoveChain {
+        start_point,
+        moves: points
+            .into_iter()
+            .skip(1)
+            .chain(std::iter::once(start_point))
+            .map(|end| Move {
+                end,
+                move_type,
+                width,
+                overhang: None,
+            })
+            .collect(),
+        is_loop: true,
+    })
+}
+
+///Variable width walls for the parts of the area that are too thin to hold a perimeter.
+///Each chain follows the medial axis of a thin region, the width of its moves follows the local thickness of the region.
+pub fn thin_wall_chains(
+    area: &FixedMultiPolygon,
+    settings: &LayerSettings,
+    thin_walls: &ThinWallSettings,
+) -> Vec<MoveChain> {
+    //the same regions the outer perimeter inset drops
+    narrow_regions(area, settings.extrusion_width.interior_surface_perimeter)
+        .iter()
+        .flat_map(|polygon| {
+            medial_axis_chains(
+                &polygon.to_float(),
+                thin_walls.min_width,
+                thin_walls.max_width,
+                MoveType::WithoutFiber(MovePrintType::WallOuter),
+            )
+        })
+        .collect()
+}
+
+///The parts of the area narrower than the width, these are removed by an opening with half the width
+pub fn narrow_regions(area: &FixedMultiPolygon, width: f32) -> FixedMultiPolygon {
+    let opened = area.offset_from(-width / 2.0).offset_from(width / 2.0);
+
+    MultiPolygon(
+        area.difference_with(&opened)
+            .into_iter()
+            //slivers left at the corners of thick regions by the opening,
+            //smaller than a square of half the width they can't hold a bead of the narrowest width
+            .filter(|polygon| polygon.to_float().unsigned_area() >= width * width / 4.0)
+            .collect(),
+    )
+}
+
+///Beads along the medial axis of a narrow region, the width of the beads is the diameter of the largest circle inside the region.
+///Parts of the region thinner than half the min width are left out, the widths are clamped to the limits.
+pub fn medial_axis_chains(
+    polygon: &Polygon<f32>,
+    min_width: f32,
+    max_width: f32,
+    move_type: MoveType,
+) -> Vec<MoveChain> {
+    medial_axis(polygon, min_width / 2.0, min_width / 4.0)
+        .into_iter()
+        .map(|line| MoveChain {
+            start_point: line[0].0,
+            moves: line
+                .iter()
+                .tuple_windows::<(_, _)>()
+                .map(|((_, start_radius), (end, end_radius))| Move {
+                    end: *end,
+                    move_type,
+                    width: (start_radius + end_radius).clamp(min_width, max_width),
+                    overhang: None,
+                })
+                .collect(),
+            is_loop: line[0].0 == line[line.len() - 1].0,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plotter::polygon_operations::ToFixed;
+    use crate::settings::Settings;
+
+    #[test]
+    fn thin_rib_becomes_a_single_bead() {
+        let settings = Settings::default().get_layer_settings(0, 0.2);
+        let thin_walls = ThinWallSettings::default();
+
+        let rib = MultiPolygon(vec![Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 0.3), (0.0, 0.3)]),
+            vec![],
+        )]);
+
+        assert!(rib.offset_from(-0.2).0.is_empty());
+
+        let chains = thin_wall_chains(&rib.to_fixed(), &settings, &thin_walls);
+        assert_eq!(chains.len(), 1);
+
+        let chain = &chains[0];
+        let start = chain.start_point;
+        let end = chain.moves.last().expect("The bead has moves").end;
+
+        assert!((start.x.min(end.x)) < 0.5 && (start.x.max(end.x)) > 9.5);
+
+        for m in chain.moves.iter() {
+            assert!((m.end.y - 0.15).abs() < 0.001);
+            assert!((m.width - 0.3).abs() < 0.001);
+        }
+    }
+
+    fn chain_points(chain: &MoveChain) -> Vec<Coord<f32>> {
+        std::iter::once(chain.start_point)
+            .chain(chain.moves.iter().map(|m| m.end))
+            .collect()
+    }
+
+    #[test]
+    fn l_shaped_rib_follows_both_legs() {
+        let settings = Settings::default().get_layer_settings(0, 0.2);
+        let thin_walls = ThinWallSettings::default();
+
+        let rib = MultiPolygon(vec![Polygon::new(
+            LineString::from(vec![
+                (0.0, 0.0),
+                (10.0, 0.0),
+                (10.0, 0.3),
+                (0.3, 0.3),
+                (0.3, 10.0),
+                (0.0, 10.0),
+            ]),
+            vec![],
+        )]);
+
+        let chains = thin_wall_chains(&rib.to_fixed(), &settings, &thin_walls);
+        assert_eq!(chains.len(), 1);
+
+        let chain = &chains[0];
+        let points = chain_points(chain);
+        let ends = [points[0], points[points.len() - 1]];
+
+        assert!(ends
+            .iter()
+            .any(|end| (end.x - 9.85).abs() < 0.01 && (end.y - 0.15).abs() < 0.01));
+        assert!(ends
+            .iter()
+            .any(|end| (end.x - 0.15).abs() < 0.01 && (end.y - 9.85).abs() < 0.01));
+
+        //at the bend the largest circle touches both outer walls and the inner corner
+        for point in points.iter() {
+            assert!((point.x - 0.15).abs().min((point.y - 0.15).abs()) < 0.03);
+        }
+
+        for m in chain.moves.iter() {
+            if m.end.x.max(m.end.y) > 1.0 {
+                assert!((m.width - 0.3).abs() < 0.001);
+            } else {
+                assert!(m.width > 0.299 && m.width < 0.36);
+            }
+        }
+    }
+
+    #[test]
+    fn thin_ring_becomes_a_closed_bead() {
+        let settings = Settings::default().get_layer_settings(0, 0.2);
+        let thin_walls = ThinWallSettings::default();
+
+        let ring = MultiPolygon(vec![Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]),
+            vec![LineString::from(vec![
+                (0.3, 0.3),
+                (0.3, 9.7),
+                (9.7, 9.7),
+                (9.7, 0.3),
+            ])],
+        )]);
+
+        let chains = thin_wall_chains(&ring.to_fixed(), &settings, &thin_walls);
+        assert_eq!(chains.len(), 1);
+
+        let chain = &chains[0];
+        assert!(chain.is_loop);
+
+        let points = chain_points(chain);
+        assert_eq!(points[0], points[points.len() - 1]);
+        assert!(points.len() > 100);
+
+        for point in points.iter() {
+            let wall = point.x.min(point.y).min(10.0 - point.x).min(10.0 - point.y);
+            assert!(wall > 0.149 && wall < 0.18);
+        }
+
+        for m in chain.moves.iter() {
+            assert!(m.width > 0.299 && m.width < 0.36);
+        }
+    }
+
+    #[test]
+    fn gap_is_filled_with_a_bead_as_wide_as_the_gap() {
+        let gap = Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (8.0, 0.0), (8.0, 0.25), (0.0, 0.25)]),
+            vec![],
+        );
+
+        let chains = medial_axis_chains(
+            &gap,
+            0.2,
+            0.4,
+            MoveType::WithoutFiber(MovePrintType::GapFill),
+        );
+        assert_eq!(chains.len(), 1);
+
+        let points = chain_points(&chains[0]);
+        assert!(points[0].x.min(points[points.len() - 1].x) < 0.5);
+        assert!(points[0].x.max(points[points.len() - 1].x) > 7.5);
+
+        for point in points.iter() {
+            assert!((point.y - 0.125).abs() < 0.001);
+        }
+        //the inscribed circle shrinks towards the closed ends of the gap
+        for m in chains[0].moves.iter() {
+            if m.end.x > 0.5 && m.end.x < 7.5 {
+                assert!((m.width - 0.25).abs() < 0.001);
+            } else {
+                assert!(m.width >= 0.2 && m.width < 0.251);
+            }
+        }
+    }
+
+    #[test]
+    fn narrow_regions_keep_only_the_gap() {
+        let area = MultiPolygon(vec![Polygon::new(
+            LineString::from(vec![
+                (0.0, 0.0),
+                (5.0, 0.0),
+                (5.0, 5.0),
+                (15.0, 5.0),
+                (15.0, 5.25),
+                (5.0, 5.25),
+                (5.0, 10.0),
+                (0.0, 10.0),
+            ]),
+            vec![],
+        )]);
+
+        let gaps = narrow_regions(&area.to_fixed(), 0.4);
+
+        assert_eq!(gaps.0.len(), 1);
+        assert!((gaps.to_float().unsigned_area() - 2.5).abs() < 0.1);
+    }
+}
@@ -2,70 +2,392 @@ use geo::*;
 
 //todo remove dependency on geo clipper and by extension bindgen
 
+///Units of the fixed point grid per mm.
+///Grid points below 512mm survive a round trip through f32, so stored geometry never drifts between operations.
+pub const FIXED_POINT_SCALE: f64 = 10_000.0;
+
+///Geometry on the fixed point grid, one unit is 1/FIXED_POINT_SCALE mm
+pub type FixedMultiPolygon = MultiPolygon<i64>;
+
 pub trait PolygonOperations {
-    fn offset_from(&self, delta: f32) -> MultiPolygon<f32>;
+    type Output;
 
-    fn difference_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32>;
+    fn offset_from(&self, delta: f32) -> Self::Output;
 
-    fn intersection_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32>;
+    fn difference_with(&self, other: &Self::Output) -> Self::Output;
 
-    fn union_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32>;
+    fn intersection_with(&self, other: &Self::Output) -> Self::Output;
 
-    fn xor_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32>;
+    fn union_with(&self, other: &Self::Output) -> Self::Output;
+
+    fn xor_with(&self, other: &Self::Output) -> Self::Output;
 }
 
-impl PolygonOperations for MultiPolygon<f32> {
-    fn offset_from(&self, delta: f32) -> MultiPolygon<f32> {
-        geo_clipper::Clipper::offset(
-            self,
-            delta,
+///Conversion of float geometry onto the fixed point grid
+pub trait ToFixed {
+    type Output;
+
+    fn to_fixed(&self) -> Self::Output;
+}
+
+///Conversion of fixed point geometry back to float geometry
+pub trait ToFloat {
+    type Output;
+
+    fn to_float(&self) -> Self::Output;
+}
+
+pub fn coord_to_fixed(coord: Coord<f32>) -> Coord<i64> {
+    Coord {
+        x: (coord.x as f64 * FIXED_POINT_SCALE).round() as i64,
+        y: (coord.y as f64 * FIXED_POINT_SCALE).round() as i64,
+    }
+}
+
+pub fn coord_to_float(coord: Coord<i64>) -> Coord<f32> {
+    Coord {
+        x: (coord.x as f64 / FIXED_POINT_SCALE) as f32,
+        y: (coord.y as f64 / FIXED_POINT_SCALE) as f32,
+    }
+}
+
+impl ToFixed for LineString<f32> {
+    type Output = LineString<i64>;
+
+    fn to_fixed(&self) -> LineString<i64> {
+        self.map_coords(coord_to_fixed)
+    }
+}
+
+impl ToFixed for MultiLineString<f32> {
+    type Output = MultiLineString<i64>;
+
+    fn to_fixed(&self) -> MultiLineString<i64> {
+        self.map_coords(coord_to_fixed)
+    }
+}
+
+impl ToFixed for Polygon<f32> {
+    type Output = Polygon<i64>;
+
+    fn to_fixed(&self) -> Polygon<i64> {
+        self.map_coords(coord_to_fixed)
+    }
+}
+
+impl ToFixed for MultiPolygon<f32> {
+    type Output = FixedMultiPolygon;
+
+    fn to_fixed(&self) -> FixedMultiPolygon {
+        self.map_coords(coord_to_fixed)
+    }
+}
+
+impl ToFloat for MultiLineString<i64> {
+    type Output = MultiLineString<f32>;
+
+    fn to_float(&self) -> MultiLineString<f32> {
+        self.map_coords(coord_to_float)
+    }
+}
+
+impl ToFloat for Polygon<i64> {
+    type Output = Polygon<f32>;
+
+    fn to_float(&self) -> Polygon<f32> {
+        self.map_coords(coord_to_float)
+    }
+}
+
+impl ToFloat for FixedMultiPolygon {
+    type Output = MultiPolygon<f32>;
+
+    fn to_float(&self) -> MultiPolygon<f32> {
+        self.map_coords(coord_to_float)
+    }
+}
+
+//Clipper works on integers internally, integer valued f64 with a factor of 1 are converted exactly
+fn to_clipper<G: MapCoords<i64, f64>>(geometry: &G) -> G::Output {
+    geometry.map_coords(|coord| Coord {
+        x: coord.x as f64,
+        y: coord.y as f64,
+    })
+}
+
+fn from_clipper<G: MapCoords<f64, i64>>(geometry: G) -> G::Output {
+    geometry.map_coords(|coord| Coord {
+        x: coord.x.round() as i64,
+        y: coord.y.round() as i64,
+    })
+}
+
+impl PolygonOperations for FixedMultiPolygon {
+    type Output = FixedMultiPolygon;
+
+    ///The delta is in mm
+    fn offset_from(&self, delta: f32) -> FixedMultiPolygon {
+        from_clipper(geo_clipper::Clipper::offset(
+            &to_clipper(self),
+            delta as f64 * FIXED_POINT_SCALE,
             geo_clipper::JoinType::Square,
             geo_clipper::EndType::ClosedPolygon,
-            1000000.0,
-        )
+            1.0,
+        ))
+    }
+
+    fn difference_with(&self, other: &FixedMultiPolygon) -> FixedMultiPolygon {
+        from_clipper(geo_clipper::Clipper::difference(
+            &to_clipper(self),
+            &to_clipper(other),
+            1.0,
+        ))
+    }
+
+    fn intersection_with(&self, other: &FixedMultiPolygon) -> FixedMultiPolygon {
+        from_clipper(geo_clipper::Clipper::intersection(
+            &to_clipper(self),
+            &to_clipper(other),
+            1.0,
+        ))
+    }
+
+    fn union_with(&self, other: &FixedMultiPolygon) -> FixedMultiPolygon {
+        from_clipper(geo_clipper::Clipper::union(
+            &to_clipper(self),
+            &to_clipper(other),
+            1.0,
+        ))
+    }
+
+    fn xor_with(&self, other: &FixedMultiPolygon) -> FixedMultiPolygon {
+        from_clipper(geo_clipper::Clipper::xor(
+            &to_clipper(self),
+            &to_clipper(other),
+            1.0,
+        ))
+    }
+}
+
+///The areas of the slices are stored as FixedMultiPolygon, float operations are left for the polygons moves are generated from.
+///Float geometry is kept on the fixed point grid, so converting it for every operation doesn't accumulate any error.
+impl PolygonOperations for MultiPolygon<f32> {
+    type Output = MultiPolygon<f32>;
+
+    fn offset_from(&self, delta: f32) -> MultiPolygon<f32> {
+        self.to_fixed().offset_from(delta).to_float()
     }
 
     fn difference_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32> {
-        geo_clipper::Clipper::difference(self, other, 1000000.0)
+        self.to_fixed()
+            .difference_with(&other.to_fixed())
+            .to_float()
     }
 
     fn intersection_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32> {
-        geo_clipper::Clipper::intersection(self, other, 1000000.0)
+        self.to_fixed()
+            .intersection_with(&other.to_fixed())
+            .to_float()
     }
 
     fn union_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32> {
-        geo_clipper::Clipper::union(self, other, 1000000.0)
+        self.to_fixed().union_with(&other.to_fixed()).to_float()
     }
 
     fn xor_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32> {
-        geo_clipper::Clipper::xor(self, other, 1000000.0)
+        self.to_fixed().xor_with(&other.to_fixed()).to_float()
     }
 }
 
 impl PolygonOperations for Polygon<f32> {
+    type Output = MultiPolygon<f32>;
+
     fn offset_from(&self, delta: f32) -> MultiPolygon<f32> {
-        geo_clipper::Clipper::offset(
-            self,
-            delta,
-            geo_clipper::JoinType::Square,
-            geo_clipper::EndType::ClosedPolygon,
-            1000000.0,
-        )
+        MultiPolygon(vec![self.clone()]).offset_from(delta)
     }
 
     fn difference_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32> {
-        geo_clipper::Clipper::difference(self, other, 1000000.0)
+        MultiPolygon(vec![self.clone()]).difference_with(other)
     }
 
     fn intersection_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32> {
-        geo_clipper::Clipper::intersection(self, other, 1000000.0)
+        MultiPolygon(vec![self.clone()]).intersection_with(other)
     }
 
     fn union_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32> {
-        geo_clipper::Clipper::union(self, other, 1000000.0)
+        MultiPolygon(vec![self.clone()]).union_with(other)
     }
 
     fn xor_with(&self, other: &MultiPolygon<f32>) -> MultiPolygon<f32> {
-        geo_clipper::Clipper::xor(self, other, 1000000.0)
+        MultiPolygon(vec![self.clone()]).xor_with(other)
+    }
+}
+
+///Cuts open lines to the parts inside of the area on the fixed point grid
+pub fn clip_lines(lines: &MultiLineString<f32>, area: &MultiPolygon<f32>) -> MultiLineString<f32> {
+    let clipped: MultiLineString<i64> = from_clipper(geo_clipper::ClipperOpen::intersection(
+        &to_clipper(&lines.to_fixed()),
+        &to_clipper(&area.to_fixed()),
+        1.0,
+    ));
+
+    clipped.to_float()
+}
+
+///Twice the signed area of a ring, positive for counter clockwise rings. Exact on the fixed point grid.
+pub fn fixed_ring_area(ring: &LineString<i64>) -> i128 {
+    ring.0
+        .iter()
+        .zip(ring.0.iter().cycle().skip(1))
+        .map(|(p1, p2)| (p1.x as i128 + p2.x as i128) * (p2.y as i128 - p1.y as i128))
+        .sum()
+}
+
+///Exact point in ring test on the fixed point grid.
+///Returns None if the point lies on the ring.
+pub fn fixed_point_in_ring(point: Coord<i64>, ring: &LineString<i64>) -> Option<bool> {
+    let mut inside = false;
+
+    for (a, b) in ring.0.iter().zip(ring.0.iter().cycle().skip(1)) {
+        let cross = (b.x as i128 - a.x as i128) * (point.y as i128 - a.y as i128)
+            - (b.y as i128 - a.y as i128) * (point.x as i128 - a.x as i128);
+
+        if cross == 0
+            && point.x >= a.x.min(b.x)
+            && point.x <= a.x.max(b.x)
+            && point.y >= a.y.min(b.y)
+            && point.y <= a.y.max(b.y)
+        {
+            return None;
+        }
+
+        if (a.y > point.y) != (b.y > point.y) {
+            //the ray to the right of the point crosses the edge
+            let crosses = if b.y > a.y { cross > 0 } else { cross < 0 };
+
+            if crosses {
+                inside = !inside;
+            }
+        }
+    }
+
+    Some(inside)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(min: f32, max: f32) -> LineString<i64> {
+        LineString::from(vec![(min, min), (max, min), (max, max), (min, max)]).to_fixed()
+    }
+
+    #[test]
+    fn fixed_point_round_trip_is_lossless() {
+        let coord = coord_to_fixed(Coord {
+            x: 311.123_46,
+            y: -0.000_05,
+        });
+
+        assert_eq!(coord_to_fixed(coord_to_float(coord)), coord);
+    }
+
+    #[test]
+    fn grid_points_survive_the_float_round_trip_across_the_bed() {
+        for x in (-5_119_999..5_120_000).step_by(997) {
+            let coord = Coord { x, y: -x / 3 };
+
+            assert_eq!(coord_to_fixed(coord_to_float(coord)), coord);
+        }
+    }
+
+    #[test]
+    fn repeated_operations_do_not_drift() {
+        let area = MultiPolygon(vec![Polygon::new(
+            LineString::from(vec![
+                (311.123_46, -0.000_05),
+                (411.7, 0.3),
+                (400.0, 120.123_4),
+                (310.0, 100.0),
+            ]),
+            vec![LineString::from(vec![
+                (350.000_1, 50.0),
+                (360.0, 60.000_3),
+                (370.0, 50.0),
+            ])],
+        )]);
+
+        let sorted_coords = |area: &MultiPolygon<f32>| {
+            let mut coords: Vec<(i64, i64)> = area
+                .to_fixed()
+                .coords_iter()
+                .map(|coord| (coord.x, coord.y))
+                .collect();
+            coords.sort();
+            coords.dedup();
+            coords
+        };
+
+        let once = area.union_with(&MultiPolygon(vec![]));
+        let mut repeated = once.clone();
+        for _ in 0..50 {
+            repeated = repeated
+                .union_with(&MultiPolygon(vec![]))
+                .intersection_with(&once);
+        }
+
+        assert_eq!(sorted_coords(&repeated), sorted_coords(&once));
+    }
+
+    #[test]
+    fn ring_area_and_point_in_ring_are_exact() {
+        let ring = square(0.0, 1.0);
+
+        assert_eq!(
+            fixed_ring_area(&ring),
+            2 * (FIXED_POINT_SCALE as i128) * (FIXED_POINT_SCALE as i128)
+        );
+
+        assert_eq!(
+            fixed_point_in_ring(coord_to_fixed(Coord { x: 0.5, y: 0.5 }), &ring),
+            Some(true)
+        );
+        assert_eq!(
+            fixed_point_in_ring(coord_to_fixed(Coord { x: 1.5, y: 0.5 }), &ring),
+            Some(false)
+        );
+        assert_eq!(
+            fixed_point_in_ring(coord_to_fixed(Coord { x: 1.0, y: 0.5 }), &ring),
+            None
+        );
+    }
+
+    #[test]
+    fn lines_are_cut_at_the_borders_and_holes_of_the_area() {
+        let area = MultiPolygon(vec![Polygon::new(
+            LineString::from(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]),
+            vec![LineString::from(vec![
+                (4.0, 4.0),
+                (6.0, 4.0),
+                (6.0, 6.0),
+                (4.0, 6.0),
+            ])],
+        )]);
+        let lines = MultiLineString(vec![
+            LineString::from(vec![(-5.0, 5.0), (15.0, 5.0)]),
+            LineString::from(vec![(20.0, 0.0), (20.0, 10.0)]),
+        ]);
+
+        let mut pieces: Vec<(f32, f32)> = clip_lines(&lines, &area)
+            .into_iter()
+            .map(|line| {
+                assert_eq!(line.0.len(), 2);
+                assert!(line.0.iter().all(|point| point.y == 5.0));
+                (line.0[0].x.min(line.0[1].x), line.0[0].x.max(line.0[1].x))
+            })
+            .collect();
+        pieces.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        assert_eq!(pieces, vec![(0.0, 4.0), (6.0, 10.0)]);
     }
 }
@@ -0,0 +1,254 @@
+use geo::MultiPolygon;
+
+use crate::settings::{RaftSettings, Settings};
+use crate::{MovePrintType, MoveType, Object, Slice};
+
+use super::polygon_operations::{FixedMultiPolygon, PolygonOperations, ToFloat};
+use super::support::Supporter;
+use super::support_linear_fill_polygon;
+
+///Inserts the raft layers below the objects and shifts the layers of the objects up onto the raft.
+///The raft is printed with the first object, the other objects get empty layers so the layer indices of all objects line up.
+///The raft is always inserted, so the number of layers below the objects is `Settings::raft_layers`.
+///The layers of the objects keep the settings of their own layer index, the raft layers get the raft layer settings.
+pub fn insert_raft(objects: &mut [Object], raft: &RaftSettings, settings: &Settings) {
+    let layer_count = raft.layer_count();
+
+    if layer_count == 0 {
+        return;
+    }
+
+    let area = raft_area(objects, 0, raft);
+
+    let mut heights = Vec::with_capacity(layer_count);
+    let mut top = 0.0;
+    for layer in 0..layer_count {
+        let bottom = top;
+        top += settings.get_raft_layer_settings(layer).layer_height;
+        heights.push((bottom, top));
+    }
+
+    let shift = top + raft.air_gap;
+
+    for (object_num, object) in objects.iter_mut().enumerate() {
+        for slice in object.layers.iter_mut() {
+            slice.bottom_height += shift;
+            slice.top_height += shift;
+            slice.layer += layer_count;
+        }
+
+        let raft_layers = heights.iter().enumerate().map(|(layer, (bottom, top))| {
+            let mut slice = Slice::empty(
+                *bottom,
+                *top,
+                layer,
+                settings.get_raft_layer_settings(layer),
+            );
+
+            if object_num == 0 {
+                fill_raft_layer(&mut slice, &area, raft, layer < raft.base_layers);
+            }
+
+            slice
+        });
+
+        object.layers.splice(0..0, raft_layers);
+    }
+}
+
+///The area covered by the raft, the first layer of the objects and their support grown by the margin.
+///Objects starting above empty layers rest on the raft with their lowest layer that has an area.
+pub fn raft_area(objects: &[Object], first_layer: usize, raft: &RaftSettings) -> FixedMultiPolygon {
+    let layer_count = objects
+        .iter()
+        .map(|object| object.layers.len())
+        .max()
+        .unwrap_or(0);
+
+    (first_layer..layer_count)
+        .map(|layer| {
+            objects
+                .iter()
+                .filter_map(|object| object.layers.get(layer))
+                .fold(FixedMultiPolygon::new(vec![]), |area, slice| {
+                    area.union_with(&slice.main_polygon)
+                        .union_with(&slice.get_support_polygon())
+                })
+        })
+        .find(|area| !area.0.is_empty())
+        .unwrap_or(MultiPolygon(vec![]))
+        .offset_from(raft.margin)
+}
+
+///The base layers are sparse lines, the interface layers on top are dense like the support interface
+fn fill_raft_layer(slice: &mut Slice, area: &FixedMultiPolygon, raft: &RaftSettings, base: bool) {
+    let (move_type, spacing) = if base {
+        (MoveType::WithoutFiber(MovePrintType::Support), raft.spacing)
+    } else {
+        (
+            MoveType::WithoutFiber(MovePrintType::SupportInterface),
+            slice.layer_settings.extrusion_width.support_interface,
+        )
+    };
+
+    let angle = if slice.layer % 2 == 0 { 0.0 } else { 90.0 };
+
+    slice
+        .fixed_chains
+        .extend(area.to_float().iter().flat_map(|poly| {
+            support_linear_fill_polygon(poly, &slice.layer_settings, move_type, spacing, angle, 0.0)
+        }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slice_pass::{BridgingPass, SlicePass};
+    use shared::process::Process;
+
+    #[test]
+    fn objects_are_shifted_onto_the_raft() {
+        let raft = RaftSettings::default();
+        let settings = Settings {
+            raft: Some(raft.clone()),
+            ..Settings::default()
+        };
+
+        let object = || Object {
+            layers: (0..5)
+                .map(|layer| {
+                    Slice::from_single_point_loop(
+                        [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)].into_iter(),
+                        layer as f32 * 0.2,
+                        (layer + 1) as f32 * 0.2,
+                        layer,
+                        &settings,
+                    )
+                })
+                .collect(),
+        };
+
+        let mut objects = vec![object(), object()];
+        let first_layer_speed = objects[0].layers[0].layer_settings.speed.infill;
+        let layer_speed = objects[0].layers[1].layer_settings.speed.infill;
+        assert_ne!(first_layer_speed, layer_speed);
+
+        insert_raft(&mut objects, &raft, &settings);
+
+        let raft_layers = raft.layer_count();
+        let raft_top = objects[0].layers[raft_layers - 1].top_height;
+
+        for object in objects.iter() {
+            assert_eq!(object.layers.len(), 5 + raft_layers);
+
+            for (index, slice) in object.layers.iter().enumerate() {
+                assert_eq!(slice.layer, index);
+            }
+
+            let first = &object.layers[raft_layers];
+            assert!((first.bottom_height - (raft_top + raft.air_gap)).abs() < 0.0001);
+            assert!((first.top_height - first.bottom_height - 0.2).abs() < 0.0001);
+            assert!((first.bottom_height - settings.raft_height()).abs() < 0.0001);
+
+            //the first layer settings stay on the first layer of the object
+            assert_eq!(first.layer_settings.speed.infill, first_layer_speed);
+            assert_eq!(
+                object.layers[raft_layers + 1].layer_settings.speed.infill,
+                layer_speed
+            );
+
+            //only the bottom raft layer is printed like a first layer
+            assert_eq!(
+                object.layers[0].layer_settings.speed.infill,
+                first_layer_speed
+            );
+            assert_eq!(object.layers[1].layer_settings.speed.infill, layer_speed);
+        }
+
+        assert!(objects[0].layers[..raft_layers]
+            .iter()
+            .all(|slice| !slice.fixed_chains.is_empty()));
+        assert!(objects[1].layers[..raft_layers]
+            .iter()
+            .all(|slice| slice.fixed_chains.is_empty()));
+    }
+
+    #[test]
+    fn raft_is_inserted_below_empty_first_layers() {
+        let raft = RaftSettings::default();
+        let settings = Settings {
+            raft: Some(raft.clone()),
+            ..Settings::default()
+        };
+
+        let mut layers: Vec<Slice> = (0..3)
+            .map(|layer| {
+                Slice::from_single_point_loop(
+                    [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)].into_iter(),
+                    layer as f32 * 0.2,
+                    (layer + 1) as f32 * 0.2,
+                    layer,
+                    &settings,
+                )
+            })
+            .collect();
+        layers[0] = Slice::empty(0.0, 0.2, 0, settings.get_layer_settings(0, 0.1));
+
+        let mut objects = vec![Object { layers }];
+
+        insert_raft(&mut objects, &raft, &settings);
+
+        assert_eq!(objects[0].layers.len(), 3 + settings.raft_layers());
+        assert!(objects[0].layers[..settings.raft_layers()]
+            .iter()
+            .all(|slice| !slice.fixed_chains.is_empty()));
+    }
+
+    #[test]
+    fn first_layer_above_the_raft_is_not_bridged() {
+        let raft = RaftSettings::default();
+        let settings = Settings {
+            raft: Some(raft.clone()),
+            ..Settings::default()
+        };
+
+        //the top layer is wider than the ones below
+        let object = || Object {
+            layers: (0..3)
+                .map(|layer| {
+                    let size = if layer == 2 { 20.0 } else { 10.0 };
+
+                    Slice::from_single_point_loop(
+                        [(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)].into_iter(),
+                        layer as f32 * 0.2,
+                        (layer + 1) as f32 * 0.2,
+                        layer,
+                        &settings,
+                    )
+                })
+                .collect(),
+        };
+
+        let mut objects = vec![object(), object()];
+        insert_raft(&mut objects, &raft, &settings);
+
+        let bridges = |slice: &Slice| {
+            slice
+                .chains
+                .iter()
+                .flat_map(|chain| chain.moves.iter())
+                .filter(|m| m.move_type == MoveType::WithoutFiber(MovePrintType::Bridging))
+                .count()
+        };
+
+        let raft_layers = settings.raft_layers();
+        for object in objects.iter_mut() {
+            BridgingPass::pass(&mut object.layers, &settings, &Process::new())
+                .expect("The process is not cancelled");
+
+            assert_eq!(bridges(&object.layers[raft_layers]), 0);
+            assert_eq!(bridges(&object.layers[raft_layers + 1]), 0);
+            assert!(bridges(&object.layers[raft_layers + 2]) > 0);
+        }
+    }
+}
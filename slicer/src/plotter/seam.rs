@@ -0,0 +1,234 @@
+use std::f32::consts::PI;
+
+use geo::{Coord, EuclideanDistance};
+use ordered_float::OrderedFloat;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{MoveChain, MovePrintType, MoveType, SeamPlacement, Slice};
+
+///Corners turning less than this angle in radians are treated as straight
+const MIN_CORNER_ANGLE: f32 = 0.001;
+
+///Rotates the closed loops of the fixed chains so they start at the seam position of the layer.
+///Returns the seam positions, aligned seams of the next layer follow them.
+pub fn place_seams(slice: &mut Slice, previous_seams: &[Coord<f32>]) -> Vec<Coord<f32>> {
+    let placement = slice.layer_settings.seam_placement;
+    let layer = slice.layer;
+
+    let mut seams: Vec<Coord<f32>> = vec![];
+
+    for chain in slice.fixed_chains.iter_mut().filter(|chain| chain.is_loop) {
+        for (start, end) in closed_loops(chain) {
+            let points: Vec<Coord<f32>> = chain.moves[start..end].iter().map(|m| m.end).collect();
+
+            //holes are walked in the opposite direction, the material is on the left side of both
+            let is_hole = matches!(
+                chain.moves[start].move_type,
+                MoveType::WithoutFiber(MovePrintType::WallInner | MovePrintType::InteriorWallInner)
+                    | MoveType::WithFiber(
+                        MovePrintType::WallInner | MovePrintType::InteriorWallInner
+                    )
+            );
+
+            let seam = match placement {
+                SeamPlacement::Aligned if previous_seams.is_empty() => {
+                    sharpest_corner(&points, is_hole)
+                }
+                SeamPlacement::Aligned => nearest_point(&points, previous_seams),
+                SeamPlacement::Rear => rear_point(&points),
+                SeamPlacement::Nearest => match seams.last().or(previous_seams.last()) {
+                    Some(last_seam) => nearest_point(&points, &[*last_seam]),
+                    None => points.len() - 1,
+                },
+                SeamPlacement::Random(seed) => {
+                    let mut rng =
+                        StdRng::seed_from_u64(seed ^ ((layer as u64) << 32) ^ seams.len() as u64);
+                    rng.gen_range(0..points.len())
+                }
+                SeamPlacement::SharpestCorner => sharpest_corner(&points, is_hole),
+            };
+
+            rotate_loop(chain, start, end, seam);
+            seams.push(points[seam]);
+        }
+    }
+
+    seams
+}
+
+///The index of the seam point of a closed loop around material, for loops that are not part of the perimeters.
+///Loops don't know the seams of other layers, so aligned seams fall back to the sharpest corner.
+pub fn loop_seam(points: &[Coord<f32>], placement: SeamPlacement, layer: usize) -> usize {
+    match placement {
+        SeamPlacement::Aligned | SeamPlacement::SharpestCorner => sharpest_corner(points, false),
+        SeamPlacement::Rear => rear_point(points),
+        SeamPlacement::Nearest => points.len() - 1,
+        SeamPlacement::Random(seed) => {
+            StdRng::seed_from_u64(seed ^ ((layer as u64) << 32)).gen_range(0..points.len())
+        }
+    }
+}
+
+///Ranges of the moves forming closed loops, a loop is a run of extrusions that ends at its start position
+fn closed_loops(chain: &MoveChain) -> Vec<(usize, usize)> {
+    let mut loops = vec![];
+    let mut index = 0;
+
+    while index < chain.moves.len() {
+        if chain.moves[index].move_type == MoveType::Travel {
+            index += 1;
+            continue;
+        }
+
+        let start = index;
+        while index < chain.moves.len() && chain.moves[index].move_type != MoveType::Travel {
+            index += 1;
+        }
+
+        let start_position = if start == 0 {
+            chain.start_point
+        } else {
+            chain.moves[start - 1].end
+        };
+
+        if index - start >= 3 && chain.moves[index - 1].end == start_position {
+            loops.push((start, index));
+        }
+    }
+
+    loops
+}
+
+///Makes the loop start and end at the end point of the move at the seam index
+fn rotate_loop(chain: &mut MoveChain, start: usize, end: usize, seam: usize) {
+    chain.moves[start..end].rotate_left(seam + 1);
+
+    let seam_point = chain.moves[end - 1].end;
+
+    //the travels leading to the loop have to end at the new start
+    let mut index = start;
+    while index > 0 && chain.moves[index - 1].move_type == MoveType::Travel {
+        chain.moves[index - 1].end = seam_point;
+        index -= 1;
+    }
+
+    if index == 0 {
+        chain.start_point = seam_point;
+    }
+}
+
+fn nearest_point(points: &[Coord<f32>], references: &[Coord<f32>]) -> usize {
+    points
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, point)| {
+            references
+                .iter()
+                .map(|reference| OrderedFloat(point.euclidean_distance(reference)))
+                .min()
+                .unwrap_or(OrderedFloat(0.0))
+        })
+        .map(|(index, _)| index)
+        .expect("Loops contain points")
+}
+
+fn rear_point(points: &[Coord<f32>]) -> usize {
+    points
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, point)| (OrderedFloat(point.y), OrderedFloat(-point.x)))
+        .map(|(index, _)| index)
+        .expect("Loops contain points")
+}
+
+///Prefers the sharpest concave corner, where the seam is hidden best, followed by the sharpest convex corner
+fn sharpest_corner(points: &[Coord<f32>], is_hole: bool) -> usize {
+    let area: f32 = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(p1, p2)| (p1.x + p2.x) * (p2.y - p1.y))
+        .sum();
+
+    let material_side = if (area > 0.0) != is_hole { 1.0 } else { -1.0 };
+
+    (0..points.len())
+        .max_by_key(|index| {
+            let current = points[*index];
+
+            //neighbours at the same position are skipped
+            let previous = (1..points.len())
+                .map(|offset| points[(index + points.len() - offset) % points.len()])
+                .find(|point| *point != current);
+            let next = (1..points.len())
+                .map(|offset| points[(index + offset) % points.len()])
+                .find(|point| *point != current);
+
+            let (Some(previous), Some(next)) = (previous, next) else {
+                return OrderedFloat(-PI);
+            };
+
+            let incoming = current - previous;
+            let outgoing = next - current;
+
+            let cross = incoming.x * outgoing.y - incoming.y * outgoing.x;
+            let dot = incoming.x * outgoing.x + incoming.y * outgoing.y;
+            let turn = cross.atan2(dot) * material_side;
+
+            if -turn > MIN_CORNER_ANGLE {
+                OrderedFloat(-turn)
+            } else {
+                OrderedFloat(turn - PI)
+            }
+        })
+        .expect("Loops contain points")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Move;
+
+    fn square_loop() -> MoveChain {
+        let points = [(10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (0.0, 0.0)];
+        let move_type = MoveType::WithoutFiber(MovePrintType::WallOuter);
+
+        let mut moves = vec![Move {
+            end: Coord { x: 0.0, y: 0.0 },
+            move_type: MoveType::Travel,
+            width: 0.0,
+            overhang: None,
+        }];
+
+        moves.extend(points.iter().map(|(x, y)| Move {
+            end: Coord { x: *x, y: *y },
+            move_type,
+            width: 0.4,
+            overhang: None,
+        }));
+
+        MoveChain {
+            start_point: Coord { x: 0.0, y: 0.0 },
+            moves,
+            is_loop: true,
+        }
+    }
+
+    #[test]
+    fn rotated_loop_starts_and_ends_at_seam() {
+        let mut chain = square_loop();
+
+        let loops = closed_loops(&chain);
+        assert_eq!(loops, vec![(1, 5)]);
+
+        let points: Vec<Coord<f32>> = chain.moves[1..5].iter().map(|m| m.end).collect();
+        let seam = rear_point(&points);
+        assert_eq!(points[seam], Coord { x: 0.0, y: 10.0 });
+
+        rotate_loop(&mut chain, 1, 5, seam);
+
+        assert_eq!(chain.start_point, Coord { x: 0.0, y: 10.0 });
+        assert_eq!(chain.moves[0].end, Coord { x: 0.0, y: 10.0 });
+        assert_eq!(chain.moves[4].end, Coord { x: 0.0, y: 10.0 });
+        assert_eq!(chain.moves[1].end, Coord { x: 0.0, y: 0.0 });
+    }
+}
@@ -1,13 +1,22 @@
+use std::ops::Range;
+
 use geo::MultiPolygon;
 
-use crate::{settings::SupportSettings, MovePrintType, MoveType, Slice};
+use crate::{
+    settings::SupportSettings, Move, MoveChain, MovePrintType, MoveType, Slice,
+    SupportInterfacePattern, SupportType,
+};
 
-use super::{polygon_operations::PolygonOperations, support_linear_fill_polygon};
+use super::{
+    concentric_fill_polygon,
+    polygon_operations::{FixedMultiPolygon, PolygonOperations, ToFloat},
+    support_linear_fill_polygon,
+};
 
 pub trait Supporter {
     fn add_support_polygons(&mut self, slice_above: &Slice, support_settings: &SupportSettings);
     fn fill_support_polygons(&mut self, support_settings: &SupportSettings);
-    fn get_support_polygon(&self) -> MultiPolygon<f32>;
+    fn get_support_polygon(&self) -> FixedMultiPolygon;
 }
 
 impl Supporter for Slice {
@@ -42,21 +51,51 @@ impl Supporter for Slice {
 
     fn fill_support_polygons(&mut self, support_settings: &SupportSettings) {
         let layer_settings = &self.layer_settings;
-        /* if let Some(tower_polygon) = &self.support_interface{
-
-            self.fixed_chains.extend(
-                tower_polygon
-                    .iter()
-                    .map(|poly| {
-                        linear_fill_polygon(poly,layer_settings,MoveType::Support,0.0).into_iter()
-                    })
-                    .flatten()
-            );
-        }*/
+
+        if let Some(interface_polygon) = &self.support_interface {
+            let move_type = MoveType::WithoutFiber(MovePrintType::SupportInterface);
+            let angle = if self.layer % 2 == 0 { 0.0 } else { 90.0 };
+
+            self.fixed_chains
+                .extend(interface_polygon.to_float().iter().flat_map(|poly| {
+                    match support_settings.interface_pattern {
+                        SupportInterfacePattern::Rectilinear => support_linear_fill_polygon(
+                            poly,
+                            layer_settings,
+                            move_type,
+                            support_settings.interface_spacing,
+                            angle,
+                            0.0,
+                        ),
+                        SupportInterfacePattern::Concentric => concentric_fill_polygon(
+                            poly,
+                            layer_settings,
+                            move_type,
+                            support_settings.interface_spacing,
+                            self.layer,
+                        ),
+                    }
+                }));
+        }
 
         if let Some(tower_polygon) = &self.support_tower {
+            let fill_area = match support_settings.support_type {
+                SupportType::Towers => tower_polygon.clone(),
+                SupportType::Tree => {
+                    //the thin branches get a wall to keep their round shape
+                    let width = layer_settings.extrusion_width.support;
+
+                    self.fixed_chains.extend(outline_chains(
+                        &tower_polygon.offset_from(width / -2.0).to_float(),
+                        width,
+                    ));
+
+                    tower_polygon.offset_from(-width)
+                }
+            };
+
             self.fixed_chains
-                .extend(tower_polygon.iter().flat_map(|poly| {
+                .extend(fill_area.to_float().iter().flat_map(|poly| {
                     support_linear_fill_polygon(
                         poly,
                         layer_settings,
@@ -70,7 +109,7 @@ impl Supporter for Slice {
         }
     }
 
-    fn get_support_polygon(&self) -> MultiPolygon<f32> {
+    fn get_support_polygon(&self) -> FixedMultiPolygon {
         match (self.support_tower.clone(), self.support_interface.clone()) {
             (None, None) => MultiPolygon(vec![]),
             (Some(tower), None) => tower,
@@ -79,3 +118,239 @@ impl Supporter for Slice {
         }
     }
 }
+
+///Splits the support of every slice into the dense interface touching the part and the sparse support below it.
+///The support on the gap layers directly under and over the part is removed, so it doesn't weld to the part.
+pub fn separate_support_interface(slices: &mut [Slice], support_settings: &SupportSettings) {
+    let models: Vec<FixedMultiPolygon> = slices
+        .iter()
+        .map(|slice| slice.main_polygon.clone())
+        .collect();
+
+    for (index, slice) in slices.iter_mut().enumerate() {
+        let support_area = slice.get_support_polygon();
+
+        if support_area.0.is_empty() {
+            continue;
+        }
+
+        let part_above =
+            |layers: usize| model_area(&models, index + 1..(index + 1 + layers).min(models.len()));
+        let part_below = |layers: usize| model_area(&models, index.saturating_sub(layers)..index);
+
+        let gap = part_above(support_settings.top_gap_layers)
+            .union_with(&part_below(support_settings.bottom_gap_layers));
+        let support_area = support_area.difference_with(&gap);
+
+        let interface_reach =
+            part_above(support_settings.top_gap_layers + support_settings.interface_layers)
+                .union_with(&part_below(
+                    support_settings.bottom_gap_layers + support_settings.interface_layers,
+                ));
+
+        let interface = support_area.intersection_with(&interface_reach);
+        let tower = support_area.difference_with(&interface);
+
+        slice.support_interface = (!interface.0.is_empty()).then_some(interface);
+        slice.support_tower = (!tower.0.is_empty()).then_some(tower);
+    }
+}
+
+///Forces support under the parts of the slices inside of the enforcers, independent of the overhang angle.
+///The enforced support reaches down until it stands on the bed or the part.
+pub fn enforce_support(slices: &mut [Slice], enforcers: &[FixedMultiPolygon]) {
+    let mut carried = FixedMultiPolygon::new(vec![]);
+
+    for index in (0..slices.len().saturating_sub(1)).rev() {
+        let (below, above) = slices.split_at_mut(index + 1);
+        let layer = &mut below[index];
+
+        let overhang = match enforcers.get(index + 1) {
+            Some(enforcer) => above[0]
+                .main_polygon
+                .difference_with(&layer.main_polygon)
+                .intersection_with(enforcer),
+            None => MultiPolygon(vec![]),
+        };
+
+        //the support of the layers above stops where it stands on the part
+        carried = carried.difference_with(&layer.main_polygon);
+
+        if !carried.0.is_empty() {
+            let tower = carried.difference_with(&layer.main_polygon.offset_from(0.2));
+            layer.support_tower = Some(add_area(layer.support_tower.take(), &tower));
+        }
+
+        if !overhang.0.is_empty() {
+            layer.support_interface = Some(add_area(layer.support_interface.take(), &overhang));
+            carried = carried.union_with(&overhang);
+        }
+    }
+}
+
+///Removes the support inside of the blockers
+pub fn block_support(slices: &mut [Slice], blockers: &[FixedMultiPolygon]) {
+    for (slice, blocker) in slices.iter_mut().zip(blockers) {
+        slice.support_tower = remove_area(slice.support_tower.take(), blocker);
+        slice.support_interface = remove_area(slice.support_interface.take(), blocker);
+    }
+}
+
+fn add_area(area: Option<FixedMultiPolygon>, addition: &FixedMultiPolygon) -> FixedMultiPolygon {
+    match area {
+        Some(area) => area.union_with(addition),
+        None => addition.clone(),
+    }
+}
+
+fn remove_area(
+    area: Option<FixedMultiPolygon>,
+    removal: &FixedMultiPolygon,
+) -> Option<FixedMultiPolygon> {
+    area.map(|area| area.difference_with(removal))
+        .filter(|area| !area.0.is_empty())
+}
+
+fn model_area(models: &[FixedMultiPolygon], layers: Range<usize>) -> FixedMultiPolygon {
+    models[layers]
+        .iter()
+        .fold(FixedMultiPolygon::new(vec![]), |area, model| {
+            area.union_with(model)
+        })
+}
+
+fn outline_chains(area: &MultiPolygon<f32>, width: f32) -> Vec<MoveChain> {
+    area.iter()
+        .flat_map(|poly| std::iter::once(poly.exterior()).chain(poly.interiors()))
+        .filter(|ring| ring.0.len() > 3)
+        .map(|ring| MoveChain {
+            start_point: ring.0[0],
+            moves: ring.0[1..]
+                .iter()
+                .map(|end| Move {
+                    end: *end,
+                    move_type: MoveType::WithoutFiber(MovePrintType::Support),
+                    width,
+                    overhang: None,
+                })
+                .collect(),
+            is_loop: true,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plotter::polygon_operations::ToFixed;
+    use crate::settings::Settings;
+    use geo::{Coord, Rect};
+
+    #[test]
+    fn interface_is_below_the_gap_under_the_overhang() {
+        let settings = Settings::default();
+        let support_settings = SupportSettings::default();
+
+        //a ledge standing out from layer 10 on, supported from the bed
+        let mut slices: Vec<Slice> = (0..15)
+            .map(|layer| {
+                let max = if layer < 10 { 5.0 } else { 20.0 };
+
+                let mut slice = Slice::from_single_point_loop(
+                    [(0.0, 0.0), (max, 0.0), (max, 20.0), (0.0, 20.0)].into_iter(),
+                    layer as f32 * 0.2,
+                    (layer + 1) as f32 * 0.2,
+                    layer,
+                    &settings,
+                );
+
+                if layer < 10 {
+                    slice.support_tower = Some(
+                        MultiPolygon::<f32>(vec![Rect::new(
+                            Coord { x: 6.0, y: 0.0 },
+                            Coord { x: 20.0, y: 20.0 },
+                        )
+                        .to_polygon()])
+                        .to_fixed(),
+                    );
+                }
+
+                slice
+            })
+            .collect();
+
+        separate_support_interface(&mut slices, &support_settings);
+
+        assert!(slices[9].support_tower.is_none());
+        assert!(slices[9].support_interface.is_none());
+
+        for slice in slices[7..9].iter() {
+            assert!(slice.support_tower.is_none());
+            assert!(slice.support_interface.is_some());
+        }
+
+        for slice in slices[..7].iter() {
+            assert!(slice.support_tower.is_some());
+            assert!(slice.support_interface.is_none());
+        }
+    }
+
+    #[test]
+    fn enforced_support_stands_on_the_bed_and_is_blocked() {
+        let settings = Settings::default();
+
+        //a bridge, the span between the pillars doesn't need support
+        let mut slices: Vec<Slice> = (0..10)
+            .map(|layer| {
+                let mut slice = Slice::from_single_point_loop(
+                    [(0.0, 0.0), (20.0, 0.0), (20.0, 5.0), (0.0, 5.0)].into_iter(),
+                    layer as f32 * 0.2,
+                    (layer + 1) as f32 * 0.2,
+                    layer,
+                    &settings,
+                );
+
+                if layer < 8 {
+                    slice.main_polygon = MultiPolygon::<f32>(vec![
+                        Rect::new(Coord { x: 0.0, y: 0.0 }, Coord { x: 5.0, y: 5.0 }).to_polygon(),
+                        Rect::new(Coord { x: 15.0, y: 0.0 }, Coord { x: 20.0, y: 5.0 })
+                            .to_polygon(),
+                    ])
+                    .to_fixed();
+                }
+
+                slice
+            })
+            .collect();
+
+        let enforcer = MultiPolygon::<f32>(vec![Rect::new(
+            Coord { x: 5.0, y: -1.0 },
+            Coord { x: 15.0, y: 6.0 },
+        )
+        .to_polygon()])
+        .to_fixed();
+        let blocker = MultiPolygon::<f32>(vec![Rect::new(
+            Coord { x: 0.0, y: -1.0 },
+            Coord { x: 20.0, y: 6.0 },
+        )
+        .to_polygon()])
+        .to_fixed();
+
+        enforce_support(&mut slices, &vec![enforcer; 10]);
+
+        assert!(slices[7].support_interface.is_some());
+        for slice in slices[..7].iter() {
+            assert!(slice.support_tower.is_some());
+        }
+
+        let mut blockers = vec![MultiPolygon(vec![]); 10];
+        blockers[..3].fill(blocker);
+
+        block_support(&mut slices, &blockers);
+
+        assert!(slices[..3]
+            .iter()
+            .all(|slice| slice.support_tower.is_none()));
+        assert!(slices[3].support_tower.is_some());
+    }
+}
@@ -0,0 +1,316 @@
+use std::f32::consts::TAU;
+
+use geo::prelude::*;
+use geo::{Closest, Coord, LineString, MultiPolygon, Point, Polygon};
+use ordered_float::OrderedFloat;
+
+use crate::settings::SupportSettings;
+use crate::Slice;
+
+use super::polygon_operations::{FixedMultiPolygon, PolygonOperations, ToFixed, ToFloat};
+
+///Number of sides of the polygons approximating the round cross-sections of the branches
+const BRANCH_SIDES: usize = 16;
+
+///Growth of the branch radius per mm the branch descends
+const BRANCH_GROWTH: f32 = 0.05;
+
+///The cross-section of a branch on a single layer
+#[derive(Debug, Clone, Copy)]
+struct Branch {
+    position: Coord<f32>,
+    radius: f32,
+}
+
+///Grows tree support from the overhangs down to the bed or the part.
+///The branches start below every overhang and are drawn to each other on the way down, so they merge into trees.
+///Branches keep the clearance to the model, a branch that ends up above the model rests on it.
+///The round cross-sections of the branches become the support towers of the slices.
+pub fn grow_tree_support(slices: &mut [Slice], support: &SupportSettings) {
+    let mut branches: Vec<Branch> = vec![];
+
+    //the overhangs and the number of layers below them that still get a solid roof
+    let mut roofs: Vec<(FixedMultiPolygon, usize)> = vec![];
+    let roof_layers = support.top_gap_layers + support.interface_layers;
+
+    for index in (0..slices.len().saturating_sub(1)).rev() {
+        let (below, above) = slices.split_at_mut(index + 1);
+        let layer = &mut below[index];
+        let above = &above[0];
+
+        let distance_between_layers = above.get_height() - layer.get_height();
+        let max_move = distance_between_layers * support.max_overhang_angle.to_radians().tan();
+
+        branches = descend(&branches, layer, support, distance_between_layers, max_move);
+
+        if !branches.is_empty() {
+            let cross_sections =
+                branches
+                    .iter()
+                    .fold(FixedMultiPolygon::new(vec![]), |area, branch| {
+                        area.union_with(&MultiPolygon(vec![cross_section(branch).to_fixed()]))
+                    });
+
+            layer.support_tower =
+                Some(cross_sections.difference_with(&layer.main_polygon.offset_from(0.2)));
+        }
+
+        //the roof reaches down through the gap, so the interface stays solid below it
+        let clearance = layer.main_polygon.offset_from(0.2);
+        for (roof, _) in roofs.iter() {
+            let area = roof.difference_with(&clearance);
+
+            layer.support_interface = Some(match layer.support_interface.take() {
+                Some(interface) => interface.union_with(&area),
+                None => area,
+            });
+        }
+
+        roofs.retain_mut(|(_, layers)| {
+            *layers -= 1;
+            *layers > 0
+        });
+
+        //new branches start one layer below the interface
+        let overhang = above
+            .main_polygon
+            .difference_with(&layer.main_polygon.offset_from(max_move));
+
+        if !overhang.0.is_empty() {
+            branches.extend(
+                tips(&overhang.to_float(), support.support_spacing)
+                    .into_iter()
+                    .map(|position| Branch {
+                        position,
+                        radius: support.branch_tip_diameter / 2.0,
+                    }),
+            );
+
+            if roof_layers > 1 {
+                roofs.push((overhang.clone(), roof_layers - 1));
+            }
+
+            layer.support_interface = Some(match layer.support_interface.take() {
+                Some(interface) => interface.union_with(&overhang),
+                None => overhang,
+            });
+        }
+    }
+}
+
+///Moves the branches of the layer above onto this layer
+fn descend(
+    branches: &[Branch],
+    layer: &Slice,
+    support: &SupportSettings,
+    distance_between_layers: f32,
+    max_move: f32,
+) -> Vec<Branch> {
+    //the distances to the model are measured on the float polygon
+    let model = layer.main_polygon.to_float();
+
+    let moved: Vec<Branch> = branches
+        .iter()
+        .filter_map(|branch| {
+            let radius = branch.radius + distance_between_layers * BRANCH_GROWTH;
+            let required_distance = radius + support.branch_clearance;
+
+            let nearby = model_distance(&model, branch.position);
+
+            //the branch is above the model and rests on it
+            if matches!(nearby, Some((distance, _)) if distance <= 0.0) {
+                return None;
+            }
+
+            let position = match nearby {
+                //too close to the model, move straight away from it
+                Some((distance, closest)) if distance < required_distance => {
+                    let away = (branch.position - closest) / distance;
+                    branch.position + away * (required_distance - distance).min(max_move)
+                }
+                //free branches are drawn to the closest other branch
+                _ => {
+                    let target = branches
+                        .iter()
+                        .map(|other| other.position)
+                        .filter(|other| *other != branch.position)
+                        .min_by_key(|other| {
+                            OrderedFloat(other.euclidean_distance(&branch.position))
+                        });
+
+                    match target {
+                        Some(target) => {
+                            let moved = step_towards(branch.position, target, max_move);
+
+                            match model_distance(&model, moved) {
+                                Some((distance, _)) if distance < required_distance => {
+                                    branch.position
+                                }
+                                _ => moved,
+                            }
+                        }
+                        None => branch.position,
+                    }
+                }
+            };
+
+            Some(Branch { position, radius })
+        })
+        .collect();
+
+    merge(moved)
+}
+
+///Branches that overlap far enough become a single branch
+fn merge(branches: Vec<Branch>) -> Vec<Branch> {
+    let mut merged: Vec<Branch> = vec![];
+
+    for branch in branches {
+        match merged.iter_mut().find(|other| {
+            other.position.euclidean_distance(&branch.position) < other.radius.max(branch.radius)
+        }) {
+            Some(other) => {
+                let weight = branch.radius * branch.radius
+                    / (branch.radius * branch.radius + other.radius * other.radius);
+
+                other.position = other.position + (branch.position - other.position) * weight;
+                other.radius = other.radius.max(branch.radius);
+            }
+            None => merged.push(branch),
+        }
+    }
+
+    merged
+}
+
+///The distance of the point to the model and the closest point of the model.
+///Returns a distance of 0 for points inside of the model and None if the layer is empty.
+fn model_distance(model: &MultiPolygon<f32>, position: Coord<f32>) -> Option<(f32, Coord<f32>)> {
+    match model.closest_point(&Point::from(position)) {
+        Closest::Intersection(_) => Some((0.0, position)),
+        Closest::SinglePoint(closest) => Some((closest.0.euclidean_distance(&position), closest.0)),
+        Closest::Indeterminate => None,
+    }
+}
+
+fn step_towards(position: Coord<f32>, target: Coord<f32>, max_move: f32) -> Coord<f32> {
+    let distance = position.euclidean_distance(&target);
+
+    if distance <= max_move {
+        target
+    } else {
+        position + (target - position) * (max_move / distance)
+    }
+}
+
+///The start points of the branches on a grid over the overhang, small overhangs get a branch at their center
+fn tips(overhang: &MultiPolygon<f32>, spacing: f32) -> Vec<Coord<f32>> {
+    overhang
+        .iter()
+        .flat_map(|polygon| {
+            let Some(bounds) = polygon.bounding_rect() else {
+                return vec![];
+            };
+
+            let columns = (bounds.min().x / spacing).ceil() as i64
+                ..=(bounds.max().x / spacing).floor() as i64;
+            let rows = (bounds.min().y / spacing).ceil() as i64
+                ..=(bounds.max().y / spacing).floor() as i64;
+
+            let points: Vec<Coord<f32>> = itertools::iproduct!(columns, rows)
+                .map(|(column, row)| Coord {
+                    x: column as f32 * spacing,
+                    y: row as f32 * spacing,
+                })
+                .filter(|point| polygon.intersects(&Point::from(*point)))
+                .collect();
+
+            if points.is_empty() {
+                polygon
+                    .centroid()
+                    .map(|center| center.0)
+                    .into_iter()
+                    .collect()
+            } else {
+                points
+            }
+        })
+        .collect()
+}
+
+fn cross_section(branch: &Branch) -> Polygon<f32> {
+    Polygon::new(
+        LineString::from(
+            (0..BRANCH_SIDES)
+                .map(|side| {
+                    let angle = side as f32 * TAU / BRANCH_SIDES as f32;
+
+                    Coord {
+                        x: branch.position.x + branch.radius * angle.cos(),
+                        y: branch.position.y + branch.radius * angle.sin(),
+                    }
+                })
+                .collect::<Vec<_>>(),
+        ),
+        vec![],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+    use crate::SupportType;
+
+    #[test]
+    fn branches_reach_the_bed_around_the_stem() {
+        let settings = Settings::default();
+        let support = SupportSettings {
+            support_type: SupportType::Tree,
+            ..Default::default()
+        };
+
+        //a mushroom, a wide cap on a thin stem
+        let mut slices: Vec<Slice> = (0..30)
+            .map(|layer| {
+                let (min, max) = if layer < 20 { (8.0, 12.0) } else { (0.0, 20.0) };
+
+                Slice::from_single_point_loop(
+                    [(min, min), (max, min), (max, max), (min, max)].into_iter(),
+                    layer as f32 * 0.2,
+                    (layer + 1) as f32 * 0.2,
+                    layer,
+                    &settings,
+                )
+            })
+            .collect();
+
+        grow_tree_support(&mut slices, &support);
+
+        assert!(slices[19].support_interface.is_some());
+        assert!(slices[20..]
+            .iter()
+            .all(|slice| slice.support_tower.is_none()));
+
+        let bed = slices[0]
+            .support_tower
+            .as_ref()
+            .expect("The branches reach the bed");
+        assert!(bed.to_float().unsigned_area() > 0.0);
+
+        for slice in slices[..19].iter() {
+            let tower = slice
+                .support_tower
+                .as_ref()
+                .expect("Branches are continuous");
+
+            assert!(
+                tower
+                    .intersection_with(&slice.main_polygon)
+                    .to_float()
+                    .unsigned_area()
+                    < 0.001
+            );
+        }
+    }
+}
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     error::SlicerErrors, warning::SlicerWarnings, MovePrintType, MoveType, PartialInfillTypes,
-    SolidInfillTypes,
+    SeamPlacement, SolidInfillTypes, SupportInterfacePattern, SupportType,
 };
 
 macro_rules! setting_less_than_or_equal_to_zero {
@@ -63,6 +63,9 @@ pub struct Settings {
     ///The height of the layers
     pub layer_height: f32,
 
+    ///Adaptive layer height settings, if None the layer height is constant
+    pub adaptive_layer_height: Option<AdaptiveLayerHeightSettings>,
+
     ///The extrusion width of the layers
     pub extrusion_width: MovementParameter,
 
@@ -80,6 +83,9 @@ pub struct Settings {
     ///The support settings, if None no support will be generated
     pub support: Option<SupportSettings>,
 
+    ///The printhead geometry, if None no collision checking will be performed
+    pub printhead: Option<PrintheadSettings>,
+
     ///Diameter of the nozzle in mm
     pub nozzle_diameter: f32,
 
@@ -104,18 +110,33 @@ pub struct Settings {
     ///The percentage of infill to use for partial infill
     pub infill_percentage: f32,
 
+    ///Partial infill is printed on every nth layer with the combined height of the layers
+    pub infill_every_layers: usize,
+
     ///Controls the order of perimeters
     pub inner_perimeters_first: bool,
 
     ///Number of perimeters to use if possible
     pub number_of_perimeters: usize,
 
+    ///Variable width walls for regions too thin for the perimeters, if None these regions are dropped
+    pub thin_walls: Option<ThinWallSettings>,
+
+    ///Randomly jitters the outer walls for a textured surface, if None the walls stay smooth
+    pub fuzzy_skin: Option<FuzzySkinSettings>,
+
+    ///Speeds and fan overrides for perimeters over air, if None overhangs are printed like other perimeters
+    pub overhangs: Option<OverhangSettings>,
+
     ///Number of solid top layers for infill
     pub top_layers: usize,
 
     ///Number of solid bottom layers before infill
     pub bottom_layers: usize,
 
+    ///Prints the layers after the bottom layers as a single outer wall rising in one continuous spiral
+    pub spiral_vase: bool,
+
     ///Size of the printer in x dimension in mm
     pub print_x: f32,
 
@@ -128,6 +149,9 @@ pub struct Settings {
     ///Width of the brim, if None no brim will be generated
     pub brim_width: Option<f32>,
 
+    ///The raft settings, if None the objects are printed directly on the bed
+    pub raft: Option<RaftSettings>,
+
     ///Inset the layer by the provided amount, if None on inset will be performed
     pub layer_shrink_amount: Option<f32>,
 
@@ -143,6 +167,9 @@ pub struct Settings {
     ///Partial Infill type
     pub partial_infill_type: PartialInfillTypes,
 
+    ///Where the loops start and end
+    pub seam_placement: SeamPlacement,
+
     ///The instructions to prepend to the exported instructions
     pub starting_instructions: String,
 
@@ -204,9 +231,14 @@ impl Default for Settings {
     fn default() -> Self {
         Settings {
             layer_height: 0.15,
+            adaptive_layer_height: None,
             number_of_perimeters: 3,
+            thin_walls: Some(ThinWallSettings::default()),
+            fuzzy_skin: None,
+            overhangs: None,
             top_layers: 3,
             bottom_layers: 3,
+            spiral_vase: false,
             extrusion_width: MovementParameter {
                 interior_inner_perimeter: 0.4,
                 interior_surface_perimeter: 0.4,
@@ -217,6 +249,8 @@ impl Default for Settings {
                 travel: 0.4,
                 bridge: 0.4,
                 support: 0.4,
+                gap_fill: 0.4,
+                support_interface: 0.4,
                 exterior_surface_perimeter: 0.4,
                 fiber_factor: 0.5,
             },
@@ -230,6 +264,7 @@ impl Default for Settings {
             retract_speed: 35.0,
 
             support: None,
+            printhead: Some(PrintheadSettings::default()),
 
             speed: MovementParameter {
                 interior_inner_perimeter: 40.0,
@@ -241,6 +276,8 @@ impl Default for Settings {
                 travel: 180.0,
                 bridge: 30.0,
                 support: 50.0,
+                gap_fill: 30.0,
+                support_interface: 40.0,
                 exterior_surface_perimeter: 40.0,
                 fiber_factor: 0.5,
             },
@@ -254,11 +291,14 @@ impl Default for Settings {
                 travel: 1000.0,
                 bridge: 1000.0,
                 support: 1000.0,
+                gap_fill: 900.0,
+                support_interface: 1000.0,
                 exterior_surface_perimeter: 800.0,
                 fiber_factor: 0.5,
             },
 
             infill_percentage: 0.2,
+            infill_every_layers: 1,
 
             print_x: 210.0,
             print_y: 210.0,
@@ -268,9 +308,9 @@ impl Default for Settings {
             infill_perimeter_overlap_percentage: 0.25,
             solid_infill_type: SolidInfillTypes::Rectilinear,
             partial_infill_type: PartialInfillTypes::Linear,
+            seam_placement: SeamPlacement::Aligned,
             starting_instructions: "G90 ; use absolute coordinates \n\
                                 M83 ; extruder relative mode\n\
-                                M106 S255 ; FANNNNN\n\
                                 M104 S[First Layer Extruder Temp] ; set extruder temp\n\
                                 M140 S[First Layer Bed Temp] ; set bed temp\n\
                                 M190 S[First Layer Bed Temp]; wait for bed temp\n\
@@ -305,6 +345,7 @@ impl Default for Settings {
             max_jerk_y: 8.0,
             max_jerk_z: 0.4,
             brim_width: None,
+            raft: None,
             layer_settings: vec![(
                 LayerRange::SingleLayer(0),
                 PartialLayerSettings {
@@ -319,6 +360,8 @@ impl Default for Settings {
                         travel: 5.0,
                         bridge: 20.0,
                         support: 20.0,
+                        gap_fill: 20.0,
+                        support_interface: 20.0,
                         exterior_surface_perimeter: 20.0,
                         fiber_factor: 0.5,
                     }),
@@ -342,19 +385,54 @@ impl Default for Settings {
 }
 
 impl Settings {
-    ///Get the layer settings for a specific layer index and height
+    ///Number of layers printed below the objects for the raft, the raft is always inserted when it has layers
+    pub fn raft_layers(&self) -> usize {
+        self.raft.as_ref().map_or(0, RaftSettings::layer_count)
+    }
+
+    ///Distance the objects are lifted by the raft, the height of the raft layers and the air gap
+    pub fn raft_height(&self) -> f32 {
+        match &self.raft {
+            Some(raft) if raft.layer_count() > 0 => {
+                (0..raft.layer_count())
+                    .map(|layer| self.get_raft_layer_settings(layer).layer_height)
+                    .sum::<f32>()
+                    + raft.air_gap
+            }
+            _ => 0.0,
+        }
+    }
+
+    ///Get the layer settings for a specific layer index and height of the objects
     pub fn get_layer_settings(&self, layer: usize, height: f32) -> LayerSettings {
-        let changes = self
-            .layer_settings
-            .iter()
-            .filter(|(layer_range, _)| match layer_range {
-                LayerRange::LayerCountRange { end, start } => *start <= layer && layer <= *end,
-                LayerRange::HeightRange { end, start } => *start <= height && height <= *end,
-                LayerRange::SingleLayer(filter_layer) => *filter_layer == layer,
-            })
-            .map(|(_lr, pls)| pls)
-            .fold(PartialLayerSettings::default(), |a, b| a.combine(b));
+        self.layer_settings_with_changes(self.get_layer_changes(layer, height))
+    }
+
+    ///Get the layer settings of a raft layer.
+    ///The bottom raft layer lies on the bed and is printed like the first layer,
+    ///the other raft layers don't use the layer ranges as these are meant for the objects.
+    pub fn get_raft_layer_settings(&self, layer: usize) -> LayerSettings {
+        let changes = if layer == 0 {
+            self.get_layer_changes(0, 0.0)
+        } else {
+            PartialLayerSettings::default()
+        };
+
+        self.layer_settings_with_changes(changes)
+    }
 
+    ///Get the layer settings for a printed layer index and height, these count the raft layers below the objects
+    pub fn get_printed_layer_settings(&self, layer: usize, height: f32) -> LayerSettings {
+        let raft_layers = self.raft_layers();
+
+        if layer < raft_layers {
+            self.get_raft_layer_settings(layer)
+        } else {
+            self.get_layer_settings(layer - raft_layers, height - self.raft_height())
+        }
+    }
+
+    fn layer_settings_with_changes(&self, changes: PartialLayerSettings) -> LayerSettings {
         LayerSettings {
             layer_height: changes.layer_height.unwrap_or(self.layer_height),
             layer_shrink_amount: changes.layer_shrink_amount.or(self.layer_shrink_amount),
@@ -369,6 +447,7 @@ impl Settings {
             partial_infill_type: changes
                 .partial_infill_type
                 .unwrap_or(self.partial_infill_type),
+            seam_placement: changes.seam_placement.unwrap_or(self.seam_placement),
             infill_percentage: changes.infill_percentage.unwrap_or(self.infill_percentage),
             infill_perimeter_overlap_percentage: changes
                 .infill_perimeter_overlap_percentage
@@ -382,9 +461,27 @@ impl Settings {
                 .retraction_wipe
                 .or_else(|| self.retraction_wipe.clone()),
             retraction_length: changes.retraction_length.unwrap_or(self.retract_length),
+            overhangs: self.overhangs.clone(),
         }
     }
 
+    ///Get the layer height set by a layer range for a specific layer index and height
+    pub fn get_layer_height_override(&self, layer: usize, height: f32) -> Option<f32> {
+        self.get_layer_changes(layer, height).layer_height
+    }
+
+    fn get_layer_changes(&self, layer: usize, height: f32) -> PartialLayerSettings {
+        self.layer_settings
+            .iter()
+            .filter(|(layer_range, _)| match layer_range {
+                LayerRange::LayerCountRange { end, start } => *start <= layer && layer <= *end,
+                LayerRange::HeightRange { end, start } => *start <= height && height <= *end,
+                LayerRange::SingleLayer(filter_layer) => *filter_layer == layer,
+            })
+            .map(|(_lr, pls)| pls)
+            .fold(PartialLayerSettings::default(), |a, b| a.combine(b))
+    }
+
     ///Validate settings and return any warnings and errors
     pub fn validate_settings(&self) -> SettingsValidationResult {
         setting_less_than_or_equal_to_zero!(self, print_x);
@@ -410,6 +507,7 @@ impl Settings {
         setting_less_than_or_equal_to_zero!(self, maximum_feedrate_e);
         setting_less_than_zero!(self, number_of_perimeters);
         setting_less_than_zero!(self, infill_percentage);
+        setting_less_than_or_equal_to_zero!(self, infill_every_layers);
         setting_less_than_zero!(self, top_layers);
         setting_less_than_zero!(self, bottom_layers);
         setting_less_than_zero!(self, retract_length);
@@ -418,6 +516,67 @@ impl Settings {
         setting_less_than_zero!(self, minimum_feedrate_print);
         setting_less_than_zero!(self, minimum_retract_distance);
 
+        if let Some(printhead) = self.printhead.as_ref() {
+            setting_less_than_or_equal_to_zero!(printhead, nozzle_tip_diameter);
+            setting_less_than_or_equal_to_zero!(printhead, nozzle_cone_diameter);
+            setting_less_than_or_equal_to_zero!(printhead, nozzle_cone_height);
+            setting_less_than_zero!(printhead, heater_block_width);
+            setting_less_than_zero!(printhead, heater_block_depth);
+            setting_less_than_zero!(printhead, heater_block_height);
+            setting_less_than_zero!(printhead, carriage_offset);
+            setting_less_than_zero!(printhead, carriage_width);
+            setting_less_than_zero!(printhead, carriage_depth);
+            setting_less_than_zero!(printhead, carriage_height);
+            setting_less_than_zero!(printhead, tip_clearance);
+        }
+
+        if let Some(support) = self.support.as_ref() {
+            setting_less_than_or_equal_to_zero!(support, branch_tip_diameter);
+            setting_less_than_zero!(support, branch_clearance);
+            setting_less_than_or_equal_to_zero!(support, interface_spacing);
+        }
+
+        if let Some(thin_walls) = self.thin_walls.as_ref() {
+            setting_less_than_or_equal_to_zero!(thin_walls, min_width);
+            setting_less_than_or_equal_to_zero!(thin_walls, max_width);
+        }
+
+        if let Some(fuzzy_skin) = self.fuzzy_skin.as_ref() {
+            setting_less_than_zero!(fuzzy_skin, thickness);
+            setting_less_than_or_equal_to_zero!(fuzzy_skin, point_distance);
+        }
+
+        if let Some(overhangs) = self.overhangs.as_ref() {
+            for overhang_class in overhangs.classes.iter() {
+                setting_less_than_or_equal_to_zero!(overhang_class, speed);
+            }
+        }
+
+        if let Some(raft) = self.raft.as_ref() {
+            setting_less_than_or_equal_to_zero!(raft, spacing);
+            setting_less_than_zero!(raft, margin);
+            setting_less_than_zero!(raft, air_gap);
+        }
+
+        if let Some(adaptive) = self.adaptive_layer_height.as_ref() {
+            setting_less_than_or_equal_to_zero!(adaptive, min_layer_height);
+            setting_less_than_or_equal_to_zero!(adaptive, max_layer_height);
+            setting_less_than_or_equal_to_zero!(adaptive, max_deviation);
+            setting_less_than_or_equal_to_zero!(adaptive, max_height_change);
+
+            if adaptive.min_layer_height < self.nozzle_diameter * 0.2 {
+                return SettingsValidationResult::Warning(SlicerWarnings::LayerSizeTooLow {
+                    layer_height: adaptive.min_layer_height,
+                    nozzle_diameter: self.nozzle_diameter,
+                });
+            } else if adaptive.max_layer_height > self.nozzle_diameter * 0.8 {
+                return SettingsValidationResult::Warning(SlicerWarnings::LayerSizeTooHigh {
+                    layer_height: adaptive.max_layer_height,
+                    nozzle_diameter: self.nozzle_diameter,
+                });
+            }
+        }
+
         if self.layer_height < self.nozzle_diameter * 0.2 {
             return SettingsValidationResult::Warning(SlicerWarnings::LayerSizeTooLow {
                 layer_height: self.layer_height,
@@ -541,7 +700,7 @@ pub enum SettingsValidationResult {
     Error(SlicerErrors),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 ///Settings specific to a Layer
 pub struct LayerSettings {
     ///The height of the layers
@@ -565,6 +724,9 @@ pub struct LayerSettings {
     ///Partial Infill type
     pub partial_infill_type: PartialInfillTypes,
 
+    ///Where the loops start and end
+    pub seam_placement: SeamPlacement,
+
     ///The percentage of infill to use for partial infill
     pub infill_percentage: f32,
 
@@ -585,6 +747,9 @@ pub struct LayerSettings {
 
     ///Retraction Distance
     pub retraction_length: f32,
+
+    ///Speeds and fan overrides of overhanging perimeters
+    pub overhangs: Option<OverhangSettings>,
 }
 
 ///A set of values for different movement types
@@ -620,6 +785,12 @@ pub struct MovementParameter {
     ///Value for support structures
     pub support: f32,
 
+    ///Value for the center line moves filling gaps between the perimeters
+    pub gap_fill: f32,
+
+    ///Value for the dense support layers touching the part
+    pub support_interface: f32,
+
     pub fiber_factor: f32,
 }
 
@@ -648,6 +819,8 @@ impl MovementParameter {
             MovePrintType::InteriorWallInner => self.interior_inner_perimeter,
             MovePrintType::Bridging => self.bridge,
             MovePrintType::Support => self.support,
+            MovePrintType::GapFill => self.gap_fill,
+            MovePrintType::SupportInterface => self.support_interface,
         }
     }
 }
@@ -680,6 +853,15 @@ pub struct FanSettings {
     ///Disable the fan for layers below this value
     pub disable_fan_for_layers: usize,
 
+    ///Number of layers after the disabled layers over which the fan speeds up to the default fan speed
+    pub ramp_up_layers: usize,
+
+    ///Fan speed while printing bridges, if None bridges use the fan speed of the layer
+    pub bridge_fan_speed: Option<f32>,
+
+    ///Fan speed of layers that are slowed down because they print faster than the threshold
+    pub max_fan_speed: f32,
+
     ///Threshold to start slowing down based on layer print time in seconds
     pub slow_down_threshold: f32,
 
@@ -699,11 +881,28 @@ impl Default for FilamentSettings {
     }
 }
 
+impl FanSettings {
+    ///The fan speed of a layer, the fan is off for the first layers and then ramps up to the default fan speed
+    pub fn layer_fan_speed(&self, layer: usize) -> f32 {
+        if layer < self.disable_fan_for_layers {
+            0.0
+        } else {
+            let ramp =
+                (layer - self.disable_fan_for_layers + 1) as f32 / (self.ramp_up_layers + 1) as f32;
+
+            self.fan_speed * ramp.min(1.0)
+        }
+    }
+}
+
 impl Default for FanSettings {
     fn default() -> Self {
         FanSettings {
             fan_speed: 100.0,
             disable_fan_for_layers: 1,
+            ramp_up_layers: 2,
+            bridge_fan_speed: Some(100.0),
+            max_fan_speed: 100.0,
             slow_down_threshold: 15.0,
             min_print_speed: 15.0,
         }
@@ -741,6 +940,222 @@ pub struct SupportSettings {
 
     ///Spacing between the ribs of support
     pub support_spacing: f32,
+
+    ///The structure holding up the overhangs
+    pub support_type: SupportType,
+
+    ///Diameter of the tree branches where they touch the overhang, the branches get wider further down
+    pub branch_tip_diameter: f32,
+
+    ///Distance the tree branches keep from the model
+    pub branch_clearance: f32,
+
+    ///Number of dense layers where the support touches the part
+    pub interface_layers: usize,
+
+    ///Spacing between the lines of the interface layers
+    pub interface_spacing: f32,
+
+    ///The fill of the interface layers
+    pub interface_pattern: SupportInterfacePattern,
+
+    ///Number of layers left out between the top of the support and the part above it
+    pub top_gap_layers: usize,
+
+    ///Number of layers left out between the part and the support standing on it
+    pub bottom_gap_layers: usize,
+}
+
+impl Default for SupportSettings {
+    fn default() -> Self {
+        SupportSettings {
+            max_overhang_angle: 45.0,
+            support_spacing: 2.0,
+            support_type: SupportType::Towers,
+            branch_tip_diameter: 0.8,
+            branch_clearance: 1.0,
+            interface_layers: 2,
+            interface_spacing: 0.6,
+            interface_pattern: SupportInterfacePattern::Rectilinear,
+            top_gap_layers: 1,
+            bottom_gap_layers: 1,
+        }
+    }
+}
+
+///Simplified printhead geometry used for collision checking.
+///All shapes are centered on the tool axis and stacked on top of each other starting at the nozzle tip.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PrintheadSettings {
+    ///Diameter of the flat nozzle tip
+    pub nozzle_tip_diameter: f32,
+
+    ///Diameter of the nozzle cone where it meets the heater block
+    pub nozzle_cone_diameter: f32,
+
+    ///Height of the nozzle cone from the tip to the heater block
+    pub nozzle_cone_height: f32,
+
+    ///Size of the heater block in x dimension
+    pub heater_block_width: f32,
+
+    ///Size of the heater block in y dimension
+    pub heater_block_depth: f32,
+
+    ///Size of the heater block along the tool axis
+    pub heater_block_height: f32,
+
+    ///Gap between the top of the heater block and the bottom of the carriage
+    pub carriage_offset: f32,
+
+    ///Size of the carriage in x dimension
+    pub carriage_width: f32,
+
+    ///Size of the carriage in y dimension
+    pub carriage_depth: f32,
+
+    ///Size of the carriage along the tool axis
+    pub carriage_height: f32,
+
+    ///Material closer than this to the nozzle tip along the tool axis is the material being printed on and is ignored
+    pub tip_clearance: f32,
+}
+
+impl Default for PrintheadSettings {
+    fn default() -> Self {
+        PrintheadSettings {
+            nozzle_tip_diameter: 1.0,
+            nozzle_cone_diameter: 6.0,
+            nozzle_cone_height: 2.5,
+            heater_block_width: 16.0,
+            heater_block_depth: 20.0,
+            heater_block_height: 11.5,
+            carriage_offset: 25.0,
+            carriage_width: 60.0,
+            carriage_depth: 50.0,
+            carriage_height: 40.0,
+            tip_clearance: 0.05,
+        }
+    }
+}
+
+///Settings for adaptive layer heights.
+///The layer height is chosen per layer from the slope of the model surface so the stair-step error stays below the max deviation.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AdaptiveLayerHeightSettings {
+    ///The smallest layer height that will be used
+    pub min_layer_height: f32,
+
+    ///The largest layer height that will be used
+    pub max_layer_height: f32,
+
+    ///Maximum allowed distance between the model surface and the layer edge in mm
+    pub max_deviation: f32,
+
+    ///Maximum difference between the heights of neighbouring layers in mm
+    pub max_height_change: f32,
+}
+
+impl Default for AdaptiveLayerHeightSettings {
+    fn default() -> Self {
+        AdaptiveLayerHeightSettings {
+            min_layer_height: 0.08,
+            max_layer_height: 0.3,
+            max_deviation: 0.05,
+            max_height_change: 0.05,
+        }
+    }
+}
+
+///Settings for the variable width walls of thin regions.
+///Regions narrower than a perimeter are filled with a single bead along their center line, its width follows the local thickness of the region.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ThinWallSettings {
+    ///The narrowest bead that will be extruded, parts of a region thinner than half of it are left out
+    pub min_width: f32,
+
+    ///The widest bead that will be extruded
+    pub max_width: f32,
+}
+
+impl Default for ThinWallSettings {
+    fn default() -> Self {
+        ThinWallSettings {
+            min_width: 0.2,
+            max_width: 0.6,
+        }
+    }
+}
+
+///Settings for the fuzzy skin of the outer walls.
+///The outer loops are split into short moves and every new point is moved along the normal of the wall by a random amount.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FuzzySkinSettings {
+    ///The largest distance a point is moved to either side of the wall
+    pub thickness: f32,
+
+    ///The distance between the points of the jittered walls
+    pub point_distance: f32,
+
+    ///The seed of the random jitter, the same seed always creates the same skin
+    pub seed: u64,
+
+    ///Only the parts of the outer walls inside of a mask get a fuzzy skin
+    pub mask_only: bool,
+}
+
+impl Default for FuzzySkinSettings {
+    fn default() -> Self {
+        FuzzySkinSettings {
+            thickness: 0.3,
+            point_distance: 0.8,
+            seed: 0,
+            mask_only: false,
+        }
+    }
+}
+
+///Speed and fan of one class of overhanging perimeters
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OverhangClass {
+    ///The speed of the perimeters in this class
+    pub speed: f32,
+
+    ///The fan speed while printing the perimeters in this class, if None the fan speed of the layer is kept
+    pub fan_speed: Option<f32>,
+}
+
+///Settings for perimeters printed over air.
+///The perimeters are split where they leave the layer below and sorted into classes by the part of their width hanging over air.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OverhangSettings {
+    ///The classes for up to 25%, 50%, 75% and 100% of the extrusion width over air
+    pub classes: [OverhangClass; 4],
+}
+
+impl Default for OverhangSettings {
+    fn default() -> Self {
+        OverhangSettings {
+            classes: [
+                OverhangClass {
+                    speed: 30.0,
+                    fan_speed: None,
+                },
+                OverhangClass {
+                    speed: 20.0,
+                    fan_speed: None,
+                },
+                OverhangClass {
+                    speed: 15.0,
+                    fan_speed: Some(100.0),
+                },
+                OverhangClass {
+                    speed: 10.0,
+                    fan_speed: Some(100.0),
+                },
+            ],
+        }
+    }
 }
 
 ///The Settings for Skirt generation
@@ -753,6 +1168,44 @@ pub struct SkirtSettings {
     pub distance: f32,
 }
 
+///Settings for the raft printed below the objects
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RaftSettings {
+    ///Number of sparse layers at the bottom of the raft
+    pub base_layers: usize,
+
+    ///Number of dense layers on top of the base layers
+    pub interface_layers: usize,
+
+    ///Spacing between the lines of the base layers
+    pub spacing: f32,
+
+    ///Distance the raft reaches past the first layer of the objects
+    pub margin: f32,
+
+    ///Distance between the top of the raft and the first layer of the objects
+    pub air_gap: f32,
+}
+
+impl RaftSettings {
+    ///Number of layers of the raft
+    pub fn layer_count(&self) -> usize {
+        self.base_layers + self.interface_layers
+    }
+}
+
+impl Default for RaftSettings {
+    fn default() -> Self {
+        RaftSettings {
+            base_layers: 1,
+            interface_layers: 2,
+            spacing: 2.0,
+            margin: 3.0,
+            air_gap: 0.1,
+        }
+    }
+}
+
 ///The Settings for Skirt generation
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RetractionWipeSettings {
@@ -772,6 +1225,9 @@ pub struct PartialSettings {
     ///The height of the layers
     pub layer_height: Option<f32>,
 
+    ///Adaptive layer height settings, if None the layer height is constant
+    pub adaptive_layer_height: Option<AdaptiveLayerHeightSettings>,
+
     ///The extrusion width of the layers
     pub extrusion_width: Option<MovementParameter>,
 
@@ -787,6 +1243,8 @@ pub struct PartialSettings {
     pub skirt: Option<SkirtSettings>,
     ///The support settings, if None no support will be generated
     pub support: Option<SupportSettings>,
+    ///The printhead geometry, if None no collision checking will be performed
+    pub printhead: Option<PrintheadSettings>,
     ///Diameter of the nozzle in mm
     pub nozzle_diameter: Option<f32>,
 
@@ -811,18 +1269,33 @@ pub struct PartialSettings {
     ///The percentage of infill to use for partial infill
     pub infill_percentage: Option<f32>,
 
+    ///Partial infill is printed on every nth layer with the combined height of the layers
+    pub infill_every_layers: Option<usize>,
+
     ///Controls the order of perimeters
     pub inner_perimeters_first: Option<bool>,
 
     ///Number of perimeters to use if possible
     pub number_of_perimeters: Option<usize>,
 
+    ///Variable width walls for regions too thin for the perimeters, if None these regions are dropped
+    pub thin_walls: Option<ThinWallSettings>,
+
+    ///Randomly jitters the outer walls for a textured surface, if None the walls stay smooth
+    pub fuzzy_skin: Option<FuzzySkinSettings>,
+
+    ///Speeds and fan overrides for perimeters over air, if None overhangs are printed like other perimeters
+    pub overhangs: Option<OverhangSettings>,
+
     ///Number of solid top layers before infill
     pub top_layers: Option<usize>,
 
     ///Number of solid bottom layers before infill
     pub bottom_layers: Option<usize>,
 
+    ///Prints the layers after the bottom layers as a single outer wall rising in one continuous spiral
+    pub spiral_vase: Option<bool>,
+
     ///Size of the printer in x dimension in mm
     pub print_x: Option<f32>,
 
@@ -835,6 +1308,9 @@ pub struct PartialSettings {
     ///Width of the brim, if None no brim will be generated
     pub brim_width: Option<f32>,
 
+    ///The raft settings, if None the objects are printed directly on the bed
+    pub raft: Option<RaftSettings>,
+
     ///The minimum travel distance required to perform a retraction
     pub minimum_retract_distance: Option<f32>,
 
@@ -847,6 +1323,9 @@ pub struct PartialSettings {
     ///Partial Infill type
     pub partial_infill_type: Option<PartialInfillTypes>,
 
+    ///Where the loops start and end
+    pub seam_placement: Option<SeamPlacement>,
+
     ///The instructions to prepend to the exported instructions
     pub starting_instructions: Option<String>,
 
@@ -949,6 +1428,10 @@ impl PartialSettings {
     fn combine(&self, other: PartialSettings) -> PartialSettings {
         PartialSettings {
             layer_height: self.layer_height.or(other.layer_height),
+            adaptive_layer_height: self
+                .adaptive_layer_height
+                .clone()
+                .or_else(|| other.adaptive_layer_height.clone()),
             extrusion_width: self
                 .extrusion_width
                 .clone()
@@ -959,6 +1442,7 @@ impl PartialSettings {
             fan: self.fan.clone().or_else(|| other.fan.clone()),
             skirt: self.skirt.clone().or_else(|| other.skirt.clone()),
             support: self.support.clone().or_else(|| other.support.clone()),
+            printhead: self.printhead.clone().or_else(|| other.printhead.clone()),
             nozzle_diameter: self.nozzle_diameter.or(other.nozzle_diameter),
             retract_length: self.retract_length.or(other.retract_length),
             retraction_wipe: self.retraction_wipe.clone().or(other.retraction_wipe),
@@ -970,14 +1454,20 @@ impl PartialSettings {
                 .clone()
                 .or_else(|| other.acceleration.clone()),
             infill_percentage: self.infill_percentage.or(other.infill_percentage),
+            infill_every_layers: self.infill_every_layers.or(other.infill_every_layers),
             inner_perimeters_first: self.inner_perimeters_first.or(other.inner_perimeters_first),
             number_of_perimeters: self.number_of_perimeters.or(other.number_of_perimeters),
+            thin_walls: self.thin_walls.clone().or_else(|| other.thin_walls.clone()),
+            fuzzy_skin: self.fuzzy_skin.clone().or_else(|| other.fuzzy_skin.clone()),
+            overhangs: self.overhangs.clone().or_else(|| other.overhangs.clone()),
             top_layers: self.top_layers.or(other.top_layers),
             bottom_layers: self.bottom_layers.or(other.bottom_layers),
+            spiral_vase: self.spiral_vase.or(other.spiral_vase),
             print_x: self.print_x.or(other.print_x),
             print_y: self.print_y.or(other.print_y),
             print_z: self.print_z.or(other.print_z),
             brim_width: self.brim_width.or(other.brim_width),
+            raft: self.raft.clone().or_else(|| other.raft.clone()),
             minimum_retract_distance: self
                 .minimum_retract_distance
                 .or(other.minimum_retract_distance),
@@ -986,6 +1476,7 @@ impl PartialSettings {
                 .or(other.infill_perimeter_overlap_percentage),
             solid_infill_type: self.solid_infill_type.or(other.solid_infill_type),
             partial_infill_type: self.partial_infill_type.or(other.partial_infill_type),
+            seam_placement: self.seam_placement.or(other.seam_placement),
             starting_instructions: self
                 .starting_instructions
                 .clone()
@@ -1097,6 +1588,9 @@ pub struct PartialLayerSettings {
     ///Partial Infill type
     pub partial_infill_type: Option<PartialInfillTypes>,
 
+    ///Where the loops start and end
+    pub seam_placement: Option<SeamPlacement>,
+
     ///The percentage of infill to use for partial infill
     pub infill_percentage: Option<f32>,
 
@@ -1147,6 +1641,7 @@ impl PartialLayerSettings {
                 .or(other.infill_perimeter_overlap_percentage),
             solid_infill_type: self.solid_infill_type.or(other.solid_infill_type),
             partial_infill_type: self.partial_infill_type.or(other.partial_infill_type),
+            seam_placement: self.seam_placement.or(other.seam_placement),
             layer_shrink_amount: self.layer_shrink_amount.or(other.layer_shrink_amount),
             retraction_length: self.retraction_length.or(other.retraction_length),
         }
@@ -1156,12 +1651,14 @@ impl PartialLayerSettings {
 fn try_convert_partial_to_settings(part: PartialSettings) -> Result<Settings, String> {
     Ok(Settings {
         layer_height: part.layer_height.ok_or("layer_height")?,
+        adaptive_layer_height: part.adaptive_layer_height,
         extrusion_width: part.extrusion_width.ok_or("extrusion_width")?,
         fiber: part.fiber.ok_or("fiber")?,
         filament: part.filament.ok_or("filament")?,
         fan: part.fan.ok_or("fan")?,
         skirt: part.skirt,
         support: part.support,
+        printhead: part.printhead,
         nozzle_diameter: part.nozzle_diameter.ok_or("nozzle_diameter")?,
         retract_length: part.retract_length.ok_or("retract_length")?,
         retract_lift_z: part.retract_lift_z.ok_or("retract_lift_z")?,
@@ -1170,16 +1667,22 @@ fn try_convert_partial_to_settings(part: PartialSettings) -> Result<Settings, St
         speed: part.speed.ok_or("speed")?,
         acceleration: part.acceleration.ok_or("acceleration")?,
         infill_percentage: part.infill_percentage.ok_or("infill_percentage")?,
+        infill_every_layers: part.infill_every_layers.ok_or("infill_every_layers")?,
         inner_perimeters_first: part
             .inner_perimeters_first
             .ok_or("inner_perimeters_first")?,
         number_of_perimeters: part.number_of_perimeters.ok_or("number_of_perimeters")?,
+        thin_walls: part.thin_walls,
+        fuzzy_skin: part.fuzzy_skin,
+        overhangs: part.overhangs,
         top_layers: part.top_layers.ok_or("top_layers")?,
         bottom_layers: part.bottom_layers.ok_or("bottom_layers")?,
+        spiral_vase: part.spiral_vase.ok_or("spiral_vase")?,
         print_x: part.print_x.ok_or("print_x")?,
         print_y: part.print_y.ok_or("print_y")?,
         print_z: part.print_z.ok_or("print_z")?,
         brim_width: part.brim_width,
+        raft: part.raft,
         layer_shrink_amount: part.layer_shrink_amount,
         minimum_retract_distance: part
             .minimum_retract_distance
@@ -1189,6 +1692,7 @@ fn try_convert_partial_to_settings(part: PartialSettings) -> Result<Settings, St
             .ok_or("infill_perimeter_overlap_percentage")?,
         solid_infill_type: part.solid_infill_type.ok_or("solid_infill_type")?,
         partial_infill_type: part.partial_infill_type.ok_or("partial_infill_type")?,
+        seam_placement: part.seam_placement.ok_or("seam_placement")?,
         starting_instructions: part.starting_instructions.ok_or("starting_instructions")?,
         ending_instructions: part.ending_instructions.ok_or("ending_instructions")?,
         before_layer_change_instructions: part
@@ -1301,6 +1805,32 @@ fn check_extrusions(
         });
     }
 
+    //gap fill
+    if extrusion_width.gap_fill < nozzle_diameter * 0.6 {
+        return SettingsValidationResult::Warning(SlicerWarnings::ExtrusionWidthTooLow {
+            extrusion_width: extrusion_width.gap_fill,
+            nozzle_diameter,
+        });
+    } else if extrusion_width.gap_fill > nozzle_diameter * 2.0 {
+        return SettingsValidationResult::Warning(SlicerWarnings::ExtrusionWidthTooHigh {
+            extrusion_width: extrusion_width.gap_fill,
+            nozzle_diameter,
+        });
+    }
+
+    //support interface
+    if extrusion_width.support_interface < nozzle_diameter * 0.6 {
+        return SettingsValidationResult::Warning(SlicerWarnings::ExtrusionWidthTooLow {
+            extrusion_width: extrusion_width.support_interface,
+            nozzle_diameter,
+        });
+    } else if extrusion_width.support_interface > nozzle_diameter * 2.0 {
+        return SettingsValidationResult::Warning(SlicerWarnings::ExtrusionWidthTooHigh {
+            extrusion_width: extrusion_width.support_interface,
+            nozzle_diameter,
+        });
+    }
+
     //interior_surface_perimeter
     if extrusion_width.interior_surface_perimeter < nozzle_diameter * 0.6 {
         return SettingsValidationResult::Warning(SlicerWarnings::ExtrusionWidthTooLow {
@@ -1410,6 +1940,26 @@ fn check_accelerations(
         });
     }
 
+    //gap fill
+    if (speed.gap_fill * speed.gap_fill) / (2.0 * acceleration.gap_fill) > min_bed_dimension {
+        return SettingsValidationResult::Warning(SlicerWarnings::AccelerationTooLow {
+            acceleration: acceleration.gap_fill,
+            speed: speed.gap_fill,
+            bed_size: min_bed_dimension,
+        });
+    }
+
+    //support interface
+    if (speed.support_interface * speed.support_interface) / (2.0 * acceleration.support_interface)
+        > min_bed_dimension
+    {
+        return SettingsValidationResult::Warning(SlicerWarnings::AccelerationTooLow {
+            acceleration: acceleration.support_interface,
+            speed: speed.support_interface,
+            bed_size: min_bed_dimension,
+        });
+    }
+
     //interior_surface_perimeter
     if (speed.interior_surface_perimeter * speed.interior_surface_perimeter)
         / (2.0 * acceleration.interior_surface_perimeter)
@@ -1,14 +1,18 @@
-use crate::plotter::support::Supporter;
+use crate::plotter::support::{separate_support_interface, Supporter};
 
 use crate::error::SlicerErrors;
+use crate::plotter::adaptive_cubic::AdaptiveCubicInfill;
 use crate::plotter::lightning_infill::lightning_infill;
-use crate::plotter::polygon_operations::PolygonOperations;
-use crate::plotter::Plotter;
+use crate::plotter::polygon_operations::{FixedMultiPolygon, PolygonOperations, ToFloat};
+use crate::plotter::raft::{insert_raft, raft_area};
+use crate::plotter::tree_support::grow_tree_support;
+use crate::plotter::{combine_infill_layers, Plotter};
 use crate::settings::Settings;
-use crate::{Object, PartialInfillTypes, Slice};
+use crate::{check_cancelled, Object, PartialInfillTypes, Slice, SupportType};
 use geo::prelude::*;
 use geo::*;
 use rayon::prelude::*;
+use shared::process::Process;
 
 pub trait ObjectPass {
     fn pass(objects: &mut Vec<Object>, settings: &Settings);
@@ -18,11 +22,16 @@ pub struct BrimPass {}
 
 impl ObjectPass for BrimPass {
     fn pass(objects: &mut Vec<Object>, settings: &Settings) {
+        //the raft already holds the objects down, a brim would end up on top of it
+        if settings.raft.is_some() {
+            return;
+        }
+
         if let Some(width) = &settings.brim_width {
             // display_state_update("Generating Moves: Brim", send_messages);
             //Add to first object
 
-            let first_layer_multipolygon: MultiPolygon<f32> = MultiPolygon(
+            let first_layer_multipolygon: FixedMultiPolygon = MultiPolygon(
                 objects
                     .iter()
                     .flat_map(|poly| {
@@ -57,16 +66,31 @@ impl ObjectPass for SupportTowerPass {
             // display_state_update("Generating Support Towers", send_messages);
             //Add to first object
 
-            objects.par_iter_mut().for_each(|obj| {
-                (1..obj.layers.len()).rev().for_each(|q| {
-                    //todo Fix this, it feels hacky
-                    if let [ref mut layer, ref mut above, ..] = &mut obj.layers[q - 1..=q] {
-                        layer.add_support_polygons(above, support);
-                    } else {
-                        unreachable!()
+            objects
+                .par_iter_mut()
+                .for_each(|obj| match support.support_type {
+                    SupportType::Towers => {
+                        (1..obj.layers.len()).rev().for_each(|q| {
+                            //todo Fix this, it feels hacky
+                            if let [ref mut layer, ref mut above, ..] = &mut obj.layers[q - 1..=q] {
+                                layer.add_support_polygons(above, support);
+                            } else {
+                                unreachable!()
+                            }
+                        });
                     }
+                    SupportType::Tree => grow_tree_support(&mut obj.layers, support),
                 });
-            });
+        }
+    }
+}
+
+pub struct RaftPass {}
+
+impl ObjectPass for RaftPass {
+    fn pass(objects: &mut Vec<Object>, settings: &Settings) {
+        if let Some(raft) = &settings.raft {
+            insert_raft(objects, raft, settings);
         }
     }
 }
@@ -78,6 +102,12 @@ impl ObjectPass for SkirtPass {
         //Handle Perimeters
         if let Some(skirt) = &settings.skirt {
             // display_state_update("Generating Moves: Skirt", send_messages);
+            //the skirt goes around the raft, the raft layers themselves have no area
+            let raft_area = match &settings.raft {
+                Some(raft) => raft_area(objects, raft.layer_count(), raft),
+                None => MultiPolygon(vec![]),
+            };
+
             let convex_hull = objects
                 .iter()
                 .flat_map(|object| {
@@ -87,7 +117,8 @@ impl ObjectPass for SkirtPass {
                         .take(skirt.layers)
                         .map(|m| m.main_polygon.union_with(&m.get_support_polygon()))
                 })
-                .fold(MultiPolygon(vec![]), |a, b| a.union_with(&b))
+                .fold(raft_area, |a, b| a.union_with(&b))
+                .to_float()
                 .convex_hull();
 
             //Add to first object
@@ -103,30 +134,124 @@ impl ObjectPass for SkirtPass {
 }
 
 pub trait SlicePass {
-    fn pass(slices: &mut Vec<Slice>, settings: &Settings) -> Result<(), SlicerErrors>;
+    fn pass(
+        slices: &mut Vec<Slice>,
+        settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors>;
 }
 
 pub struct ShrinkPass {}
 
 impl SlicePass for ShrinkPass {
-    fn pass(slices: &mut Vec<Slice>, _settings: &Settings) -> Result<(), SlicerErrors> {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        _settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
         // display_state_update("Generating Moves: Shrink Layers", send_messages);
-        slices.par_iter_mut().for_each(|slice| {
+        slices.par_iter_mut().try_for_each(|slice| {
+            check_cancelled(process)?;
             slice.shrink_layer();
-        });
-
-        Ok(())
+            process.step();
+            Ok(())
+        })
     }
 }
 
 pub struct PerimeterPass {}
 
 impl SlicePass for PerimeterPass {
-    fn pass(slices: &mut Vec<Slice>, settings: &Settings) -> Result<(), SlicerErrors> {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
         // display_state_update("Generating Moves: Perimeters", send_messages);
-        slices.par_iter_mut().for_each(|slice| {
-            slice.slice_perimeters_into_chains(settings.number_of_perimeters);
-        });
+        slices.par_iter_mut().try_for_each(|slice| {
+            check_cancelled(process)?;
+            slice.slice_perimeters_into_chains(
+                settings.number_of_perimeters,
+                settings.thin_walls.as_ref(),
+            );
+            process.step();
+            Ok(())
+        })
+    }
+}
+
+pub struct GapFillPass {}
+
+impl SlicePass for GapFillPass {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        _settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
+        slices.par_iter_mut().try_for_each(|slice| {
+            check_cancelled(process)?;
+            slice.fill_gaps();
+            process.step();
+            Ok(())
+        })
+    }
+}
+
+pub struct SeamPass {}
+
+impl SlicePass for SeamPass {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        _settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
+        //Aligned seams depend on the layer below, so the layers are handled in order
+        let mut previous_seams = vec![];
+
+        for slice in slices.iter_mut() {
+            check_cancelled(process)?;
+
+            let seams = slice.place_seams(&previous_seams);
+            if !seams.is_empty() {
+                previous_seams = seams;
+            }
+
+            process.step();
+        }
+
+        Ok(())
+    }
+}
+
+pub struct OverhangPass {}
+
+impl SlicePass for OverhangPass {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
+        //the first layer above the raft rests on it, the raft layers have no area
+        let first = settings.raft_layers() + 1;
+
+        if settings.overhangs.is_some() && slices.len() > first {
+            let below: Vec<FixedMultiPolygon> = slices
+                .iter()
+                .map(|slice| slice.main_polygon.clone())
+                .collect();
+
+            slices[first..]
+                .par_iter_mut()
+                .zip(below[first - 1..].par_iter())
+                .try_for_each(|(slice, layer_below)| {
+                    check_cancelled(process)?;
+
+                    slice.classify_overhangs(layer_below);
+                    process.step();
+                    Ok(())
+                })?;
+        }
+
         Ok(())
     }
 }
@@ -134,34 +259,53 @@ impl SlicePass for PerimeterPass {
 pub struct BridgingPass {}
 
 impl SlicePass for BridgingPass {
-    fn pass(slices: &mut Vec<Slice>, _settings: &Settings) -> Result<(), SlicerErrors> {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
         // display_state_update("Generating Moves: Bridging", send_messages);
-        (1..slices.len()).for_each(|q| {
+        //the first layer above the raft rests on it, the raft layers have no area
+        (settings.raft_layers() + 1..slices.len()).try_for_each(|q| {
+            check_cancelled(process)?;
+
             let below = slices[q - 1].main_polygon.clone();
 
             slices[q].fill_solid_bridge_area(&below);
-        });
-        Ok(())
+            process.step();
+            Ok(())
+        })
     }
 }
 pub struct TopLayerPass {}
 
 impl SlicePass for TopLayerPass {
-    fn pass(slices: &mut Vec<Slice>, _settings: &Settings) -> Result<(), SlicerErrors> {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        _settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
         // display_state_update("Generating Moves: Top Layer", send_messages);
-        (0..slices.len() - 1).for_each(|q| {
+        (0..slices.len() - 1).try_for_each(|q| {
+            check_cancelled(process)?;
+
             let above = slices[q + 1].main_polygon.clone();
 
             slices[q].fill_solid_top_layer(&above, q);
-        });
-        Ok(())
+            process.step();
+            Ok(())
+        })
     }
 }
 
 pub struct TopAndBottomLayersPass {}
 
 impl SlicePass for TopAndBottomLayersPass {
-    fn pass(slices: &mut Vec<Slice>, settings: &Settings) -> Result<(), SlicerErrors> {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
         let top_layers = settings.top_layers;
         let bottom_layers = settings.bottom_layers;
 
@@ -169,7 +313,9 @@ impl SlicePass for TopAndBottomLayersPass {
         if slices.len() > bottom_layers + top_layers {
             // display_state_update("Generating Moves: Above and below support", send_messages);
 
-            (bottom_layers..slices.len() - top_layers).for_each(|q| {
+            (bottom_layers..slices.len() - top_layers).try_for_each(|q| {
+                check_cancelled(process)?;
+
                 let below = if bottom_layers != 0 {
                     Some(
                         slices[(q - bottom_layers + 1)..q]
@@ -214,7 +360,10 @@ impl SlicePass for TopAndBottomLayersPass {
                         .expect("Bounds Checked above")
                         .fill_solid_subtracted_area(&intersection, q);
                 }
-            });
+
+                process.step();
+                Ok(())
+            })?;
         }
 
         let slice_count = slices.len();
@@ -226,20 +375,29 @@ impl SlicePass for TopAndBottomLayersPass {
                 *layer_num < settings.bottom_layers
                     || settings.top_layers + *layer_num + 1 > slice_count
             })
-            .for_each(|(layer_num, slice)| {
+            .try_for_each(|(layer_num, slice)| {
+                check_cancelled(process)?;
                 slice.fill_remaining_area(true, layer_num);
-            });
-        Ok(())
+                Ok(())
+            })
     }
 }
 
 pub struct SupportPass {}
 
 impl SlicePass for SupportPass {
-    fn pass(slices: &mut Vec<Slice>, settings: &Settings) -> Result<(), SlicerErrors> {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
         if let Some(support) = &settings.support {
+            separate_support_interface(slices, support);
+
             for slice in slices.iter_mut() {
+                check_cancelled(process)?;
                 slice.fill_support_polygons(support);
+                process.step();
             }
         }
         Ok(())
@@ -249,25 +407,45 @@ impl SlicePass for SupportPass {
 pub struct FillAreaPass {}
 
 impl SlicePass for FillAreaPass {
-    fn pass(slices: &mut Vec<Slice>, _settings: &Settings) -> Result<(), SlicerErrors> {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
         // display_state_update("Generating Moves: Fill Areas", send_messages);
 
+        if settings.infill_every_layers > 1 {
+            //the combined infill is limited like the layer height, to 80% of the nozzle diameter
+            combine_infill_layers(
+                slices,
+                settings.infill_every_layers,
+                settings.nozzle_diameter * 0.8,
+            );
+        }
+
         //Fill all remaining areas
         slices
             .par_iter_mut()
             .enumerate()
-            .for_each(|(layer_num, slice)| {
+            .try_for_each(|(layer_num, slice)| {
+                check_cancelled(process)?;
                 slice.fill_remaining_area(false, layer_num);
-            });
-        Ok(())
+                process.step();
+                Ok(())
+            })
     }
 }
 pub struct LightningFillPass {}
 
 impl SlicePass for LightningFillPass {
-    fn pass(slices: &mut Vec<Slice>, settings: &Settings) -> Result<(), SlicerErrors> {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
         if settings.partial_infill_type == PartialInfillTypes::Lightning {
             // display_state_update("Generating Moves: Lightning Infill", send_messages);
+            check_cancelled(process)?;
 
             lightning_infill(slices);
         }
@@ -275,16 +453,75 @@ impl SlicePass for LightningFillPass {
     }
 }
 
+pub struct AdaptiveCubicFillPass {}
+
+impl SlicePass for AdaptiveCubicFillPass {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        _settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
+        //the layers choose the adaptive cubic infill through their layer settings
+        check_cancelled(process)?;
+
+        if let Some(infill) = AdaptiveCubicInfill::new(slices) {
+            check_cancelled(process)?;
+
+            slices.par_iter_mut().try_for_each(|slice| {
+                check_cancelled(process)?;
+                infill.fill_layer(slice);
+                process.step();
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+pub struct SpiralVasePass {}
+
+impl SlicePass for SpiralVasePass {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
+        if settings.spiral_vase {
+            //Every layer starts where the one below ended, so the layers are handled in order
+            let mut start_near = None;
+
+            for slice in slices
+                .iter_mut()
+                .skip(settings.raft_layers() + settings.bottom_layers)
+            {
+                check_cancelled(process)?;
+
+                start_near = slice.spiralize(start_near).or(start_near);
+
+                process.step();
+            }
+        }
+
+        Ok(())
+    }
+}
+
 pub struct OrderPass {}
 
 impl SlicePass for OrderPass {
-    fn pass(slices: &mut Vec<Slice>, _settings: &Settings) -> Result<(), SlicerErrors> {
+    fn pass(
+        slices: &mut Vec<Slice>,
+        _settings: &Settings,
+        process: &Process,
+    ) -> Result<(), SlicerErrors> {
         // display_state_update("Generating Moves: Order Chains", send_messages);
 
         //Fill all remaining areas
-        slices.par_iter_mut().for_each(|slice| {
+        slices.par_iter_mut().try_for_each(|slice| {
+            check_cancelled(process)?;
             slice.order_chains();
-        });
-        Ok(())
+            process.step();
+            Ok(())
+        })
     }
 }
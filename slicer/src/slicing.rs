@@ -1,18 +1,22 @@
 use geo::Coord;
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use shared::object::ObjectVertex;
+use shared::{object::ObjectVertex, process::Process};
 
 use super::{
+    check_cancelled,
     error::SlicerErrors,
     settings::Settings,
     tower::{TriangleTower, TriangleTowerIterator},
     Object, Slice,
 };
 
+///Slices the towers into layers, the layer heights are taken from the settings if none are provided
 pub fn slice(
     towers: &[TriangleTower],
     max_height: f32,
+    layer_heights: Option<&[f32]>,
     settings: &Settings,
+    process: &Process,
 ) -> Result<Vec<Object>, SlicerErrors> {
     towers
         .iter()
@@ -27,9 +31,14 @@ pub fn slice(
                 std::iter::repeat(())
                     .enumerate()
                     .map(|(layer_count, _)| {
+                        check_cancelled(process)?;
+
                         //Advance to the correct height
-                        let layer_height =
-                            settings.get_layer_settings(layer_count, layer).layer_height;
+                        let layer_height = layer_heights
+                            .and_then(|heights| heights.get(layer_count).copied())
+                            .unwrap_or_else(|| {
+                                settings.get_layer_settings(layer_count, layer).layer_height
+                            });
 
                         let bottom_height = layer;
                         layer += layer_height / 2.0;
@@ -40,6 +49,8 @@ pub fn slice(
 
                         first_layer = false;
 
+                        process.step();
+
                         //Get the ordered lists of points
                         Ok((bottom_height, top_height, tower_iter.get_points()))
                     })
@@ -58,6 +69,8 @@ pub fn slice(
                 .par_iter()
                 .enumerate()
                 .map(|(count, (bot, top, layer_loops))| {
+                    check_cancelled(process)?;
+
                     //Add this slice to the
                     let slice = Slice::from_multiple_point_loop(
                         layer_loops
@@ -74,6 +87,7 @@ pub fn slice(
                         count,
                         settings,
                     );
+                    process.step();
                     slice
                 })
                 .collect();
@@ -41,10 +41,20 @@ fn lerp(a: f32, b: f32, f: f32) -> f32 {
     a + f * (b - a)
 }
 
+///Height range and steepness of a triangle of the tower
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TowerFace {
+    pub min_z: f32,
+    pub max_z: f32,
+    ///Absolute z component of the unit normal, 0 for vertical walls and 1 for flat surfaces
+    pub normal_z: f32,
+}
+
 #[derive(Debug)]
 pub struct TriangleTower {
     vertices: Vec<ObjectVertex>,
     tower_vertices: Vec<TowerVertex>,
+    faces: Vec<TowerFace>,
 }
 
 impl TriangleTower {
@@ -79,6 +89,23 @@ impl TriangleTower {
             }
         }
 
+        let faces = triangles
+            .iter()
+            .filter_map(|index_tri| {
+                let v0 = *vertices[index_tri[0]];
+                let v1 = *vertices[index_tri[1]];
+                let v2 = *vertices[index_tri[2]];
+
+                let normal = (v1 - v0).cross(v2 - v0).try_normalize()?;
+
+                Some(TowerFace {
+                    min_z: v0.z.min(v1.z).min(v2.z),
+                    max_z: v0.z.max(v1.z).max(v2.z),
+                    normal_z: normal.z.abs(),
+                })
+            })
+            .collect();
+
         //for each triangle event, add it to the lowest vertex and
         //create a list of all vertices and there above edges
 
@@ -107,9 +134,14 @@ impl TriangleTower {
         Ok(Self {
             vertices,
             tower_vertices,
+            faces,
         })
     }
 
+    pub fn faces(&self) -> &[TowerFace] {
+        &self.faces
+    }
+
     pub fn get_height_of_vertex(&self, index: usize) -> f32 {
         if index >= self.tower_vertices.len() {
             f32::INFINITY
@@ -1,48 +1,93 @@
-use shared::{object::ObjectMesh, SliceInput};
-use slicer::Settings;
-use tokio::task::JoinHandle;
+use shared::{object::ObjectMesh, process::Process, SliceInput};
+use slicer::{Settings, SliceCache, SlicerErrors};
 
 use crate::{
+    prelude::{Shared, SharedMut},
     ui::{api::trim_text, custom_toasts::SLICING_PROGRESS},
+    viewer::server::ModelKind,
     GlobalState, RootEvent,
 };
 
 #[derive(Debug, Default)]
 pub struct Slicer {
     pub settings: Settings,
-    handle: Option<JoinHandle<()>>,
+    process: Option<Shared<Process>>,
+    ///Towers and slices of the last run, reused while the models and their settings don't change
+    cache: SharedMut<SliceCache>,
 }
 
 impl Slicer {
     pub fn slice(&mut self, global_state: &GlobalState<RootEvent>) {
-        if let Some(handle) = self.handle.take() {
-            if !handle.is_finished() {
-                return;
-            }
-        }
+        //A running slice is outdated, it stops at its next check
+        self.cancel();
 
         let model_server_read = global_state.viewer.model_server.read();
 
         let settings = self.settings.clone();
-        let models: Vec<ObjectMesh> = model_server_read.models(&settings);
+        let models: Vec<ObjectMesh> = model_server_read.models(&settings, ModelKind::Object);
+        let support_enforcers = model_server_read.models(&settings, ModelKind::SupportEnforcer);
+        let support_blockers = model_server_read.models(&settings, ModelKind::SupportBlocker);
 
-        let global_state = global_state.clone();
+        let process = global_state
+            .progress_tracker
+            .write()
+            .add(SLICING_PROGRESS, trim_text::<20, 4>("Slicing model"));
 
-        let handle = tokio::spawn(async move {
-            let process = global_state
-                .progress_tracker
-                .write()
-                .add(SLICING_PROGRESS, trim_text::<20, 4>("Slicing model"));
+        self.process = Some(process.clone());
 
-            let result = slicer::slice(
+        let global_state = global_state.clone();
+        let cache = self.cache.clone();
+
+        //Slicing blocks for its whole run while holding the cache, so it runs on the blocking threads
+        tokio::task::spawn_blocking(move || {
+            //A cancelled slice holds the cache until it reaches its next check
+            let result = match slicer::slice(
                 SliceInput {
                     objects: models,
                     masks: vec![],
+                    support_enforcers,
+                    support_blockers,
                 },
                 &settings,
                 &process,
-            )
-            .expect("Failed to slice model");
+                &mut cache.write(),
+            ) {
+                Ok(result) => result,
+                Err(SlicerErrors::Cancelled) => {
+                    process.set_task("Cancelled".to_string());
+
+                    global_state
+                        .ui_event_writer
+                        .send(crate::ui::UiEvent::ShowInfo(
+                            "Slicing cancelled".to_string(),
+                        ));
+
+                    return;
+                }
+                Err(err) => {
+                    let (_, message) = err.get_code_and_message();
+
+                    process.finish();
+
+                    global_state
+                        .ui_event_writer
+                        .send(crate::ui::UiEvent::ShowError(message));
+
+                    return;
+                }
+            };
+
+            if let Some(collision) = result.collisions.first() {
+                let (_, message) = collision.get_code_and_message();
+
+                global_state
+                    .ui_event_writer
+                    .send(crate::ui::UiEvent::ShowError(format!(
+                        "{} ({} printhead collisions in total)",
+                        message,
+                        result.collisions.len()
+                    )));
+            }
 
             global_state
                 .viewer
@@ -56,7 +101,13 @@ impl Slicer {
                     "Slicing finished".to_string(),
                 ));
         });
+    }
 
-        self.handle = Some(handle);
+    pub fn cancel(&mut self) {
+        if let Some(process) = self.process.take() {
+            if !process.is_finished() {
+                process.close();
+            }
+        }
     }
 }
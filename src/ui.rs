@@ -56,7 +56,6 @@ pub struct UiState {
     pub mode: SharedMut<Mode>,
 
     pub layer_max: SharedMut<u32>,
-    pub time_stamp: SharedMut<u16>,
 }
 
 impl Default for UiState {
@@ -67,7 +66,6 @@ impl Default for UiState {
             mode: SharedMut::from_inner(Mode::Prepare),
 
             layer_max: SharedMut::from_inner(u32::MAX),
-            time_stamp: SharedMut::from_inner(u16::MAX),
         }
     }
 }
@@ -154,7 +154,7 @@ impl<'a> Addons<'a> {
     fn show_bottom_addon(
         &mut self,
         ui: &mut Ui,
-        (ui_state, _global_state): &(UiState, GlobalState<RootEvent>),
+        (ui_state, global_state): &(UiState, GlobalState<RootEvent>),
     ) {
         let shaded_color = shaded_color(ui.visuals().dark_mode);
 
@@ -166,20 +166,44 @@ impl<'a> Addons<'a> {
                         Pos2::new(ui.available_width() * 0.75, ui.available_height()),
                     ),
                     |ui| {
-                        ui_state.time_stamp.write_with_fn(|time_stamp| {
-                            ui.spacing_mut().slider_width = ui.available_width();
+                        let mut toolpath_server = global_state.viewer.toolpath_server.write();
 
-                            ui_temp_mut(
-                                ui,
-                                ui.available_width(),
-                                |ui| &mut ui.spacing_mut().slider_width,
-                                |ui| {
-                                    let slider = egui::Slider::new(time_stamp, 0..=120)
-                                        .orientation(egui::SliderOrientation::Horizontal);
-                                    ui.add_sized(ui.available_size(), slider);
-                                },
-                            );
-                        });
+                        let max = toolpath_server
+                            .get_toolpath()
+                            .map(|toolpath| toolpath.tool_positions.len().saturating_sub(1));
+
+                        if let Some(max) = max {
+                            ui.horizontal_centered(|ui| {
+                                let playing = toolpath_server.is_playing();
+
+                                if ui.button(if playing { "⏸" } else { "▶" }).clicked() {
+                                    if !playing && toolpath_server.get_playback_position() >= max {
+                                        toolpath_server.set_playback_position(0);
+                                    }
+
+                                    toolpath_server.set_playing(!playing);
+                                }
+
+                                let mut position = toolpath_server.get_playback_position();
+
+                                ui_temp_mut(
+                                    ui,
+                                    ui.available_width(),
+                                    |ui| &mut ui.spacing_mut().slider_width,
+                                    |ui| {
+                                        let slider = egui::Slider::new(&mut position, 0..=max)
+                                            .orientation(egui::SliderOrientation::Horizontal);
+
+                                        let response = ui.add_sized(ui.available_size(), slider);
+
+                                        if response.changed() {
+                                            toolpath_server.set_playing(false);
+                                            toolpath_server.set_playback_position(position);
+                                        }
+                                    },
+                                );
+                            });
+                        }
                     },
                 );
             }
@@ -10,6 +10,7 @@ use crate::ui::boundary::Boundary;
 use crate::ui::Component;
 use crate::ui::ComponentState;
 use crate::ui::UiState;
+use crate::viewer::server::ModelKind;
 use crate::GlobalState;
 use crate::RootEvent;
 
@@ -122,21 +123,19 @@ fn file_button(ui: &mut Ui, (_ui_state, global_state): &(UiState, GlobalState<Ro
         ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
 
         build_sub_menu(ui, "Import Object", |_ui| {
-            let nfd = Nfd::new().unwrap();
-            let result = nfd.open_file().add_filter("STL", "stl").unwrap().show();
-
-            match result {
-                DialogResult::Ok(path) => {
-                    global_state.viewer.model_server.write().load(path);
-                }
-                _ => {
-                    println!("No file selected")
-                }
-            }
+            import_model(global_state, ModelKind::Object);
         });
 
         build_sub_menu(ui, "Import Intersection Object", |_ui| {});
 
+        build_sub_menu(ui, "Import Support Enforcer", |_ui| {
+            import_model(global_state, ModelKind::SupportEnforcer);
+        });
+
+        build_sub_menu(ui, "Import Support Blocker", |_ui| {
+            import_model(global_state, ModelKind::SupportBlocker);
+        });
+
         build_sub_menu(ui, "Save As", |_ui| {});
 
         build_sub_menu(ui, "Save", |_ui| {});
@@ -147,6 +146,20 @@ fn file_button(ui: &mut Ui, (_ui_state, global_state): &(UiState, GlobalState<Ro
     });
 }
 
+fn import_model(global_state: &GlobalState<RootEvent>, kind: ModelKind) {
+    let nfd = Nfd::new().unwrap();
+    let result = nfd.open_file().add_filter("STL", "stl").unwrap().show();
+
+    match result {
+        DialogResult::Ok(path) => {
+            global_state.viewer.model_server.write().load(path, kind);
+        }
+        _ => {
+            println!("No file selected")
+        }
+    }
+}
+
 fn help_button(ui: &mut Ui, _shared_state: &(UiState, GlobalState<RootEvent>)) {
     ui.menu_button("Help", |ui| {
         ui.set_min_width(220.0);
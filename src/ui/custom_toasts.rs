@@ -16,7 +16,7 @@ pub fn model_load_progress(ui: &mut egui::Ui, toast: &mut Toast) -> egui::Respon
     global_state.progress_tracker.read_with_fn(|tracker| {
         let process = tracker.get(MODEL_LOAD_PROGRESS, toast.get_name()).unwrap();
 
-        show_processes(ui, toast, process)
+        show_processes(ui, toast, process, false)
     })
 }
 
@@ -29,7 +29,7 @@ pub fn slicing_progress(ui: &mut egui::Ui, toast: &mut Toast) -> egui::Response
     global_state.progress_tracker.read_with_fn(|tracker| {
         let process = tracker.get(SLICING_PROGRESS, toast.get_name()).unwrap();
 
-        show_processes(ui, toast, process)
+        show_processes(ui, toast, process, true)
     })
 }
 
@@ -37,6 +37,7 @@ pub fn show_processes(
     ui: &mut egui::Ui,
     toast: &mut Toast,
     process: &Arc<Process>,
+    cancelable: bool,
 ) -> egui::Response {
     egui::Frame::window(ui.style())
         .show(ui, |ui| {
@@ -45,6 +46,10 @@ pub fn show_processes(
                 toast.options.show_progress = false;
 
                 process.close();
+            } else if process.is_closed() && toast.options.show_progress {
+                //cancelled before it finished
+                toast.options = toast.options.duration_in_millis(STAY_DURATION_MS);
+                toast.options.show_progress = false;
             }
 
             let progress = process.get();
@@ -61,6 +66,15 @@ pub fn show_processes(
                     .animate(true)
                     .desired_width(PROGRESS_BAR_WIDTH),
             );
+
+            if cancelable
+                && !process.is_finished()
+                && !process.is_closed()
+                && ui.button("Cancel").clicked()
+            {
+                process.set_task("Cancelling".to_string());
+                process.close();
+            }
         })
         .response
 }
@@ -3,8 +3,9 @@ use std::fmt::Debug;
 use egui::{DragValue, InnerResponse, Response, TextEdit, Ui};
 use egui_code_editor::{ColorTheme, Syntax};
 use slicer::{
-    FanSettings, FiberSettings, FilamentSettings, MovementParameter, RetractionWipeSettings,
-    SkirtSettings, SupportSettings,
+    AdaptiveLayerHeightSettings, FanSettings, FiberSettings, FilamentSettings, FuzzySkinSettings,
+    MovementParameter, OverhangSettings, PrintheadSettings, RaftSettings, RetractionWipeSettings,
+    SkirtSettings, SupportSettings, ThinWallSettings,
 };
 use strum::IntoEnumIterator;
 
@@ -28,6 +29,31 @@ impl UiSetting for slicer::Settings {
     fn show_general(&mut self, ui: &mut egui::Ui) {
         show_f32(&mut self.layer_height, "Layer height", Some("mm"), ui);
 
+        let mut adaptive_layer_height_enabled = self.adaptive_layer_height.is_some();
+
+        show_bool(
+            &mut adaptive_layer_height_enabled,
+            "Adaptive layer height",
+            Some("Enable/Disable adaptive layer heights"),
+            ui,
+        );
+
+        if adaptive_layer_height_enabled {
+            if self.adaptive_layer_height.is_none() {
+                self.adaptive_layer_height = Some(AdaptiveLayerHeightSettings::default());
+            }
+
+            if let Some(adaptive_layer_height) = &mut self.adaptive_layer_height {
+                egui::CollapsingHeader::new("Adaptive Layer Height")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        adaptive_layer_height.show(ui);
+                    });
+            }
+        } else {
+            self.adaptive_layer_height = None;
+        }
+
         egui::CollapsingHeader::new("Extrustion width")
             .default_open(true)
             .show(ui, |ui| {
@@ -82,7 +108,7 @@ impl UiSetting for slicer::Settings {
 
         if support_enabled {
             if self.support.is_none() {
-                self.support = Default::default();
+                self.support = Some(SupportSettings::default());
             }
 
             if let Some(support) = &mut self.support {
@@ -147,6 +173,13 @@ impl UiSetting for slicer::Settings {
             ui,
         );
 
+        show_usize(
+            &mut self.infill_every_layers,
+            "Infill every n layers",
+            None,
+            ui,
+        );
+
         show_bool(
             &mut self.inner_perimeters_first,
             "Inner perimeters first",
@@ -161,10 +194,87 @@ impl UiSetting for slicer::Settings {
             ui,
         );
 
+        let mut thin_walls_enabled = self.thin_walls.is_some();
+
+        show_bool(
+            &mut thin_walls_enabled,
+            "Thin walls",
+            Some("Print regions too thin for the perimeters with variable width walls"),
+            ui,
+        );
+
+        if thin_walls_enabled {
+            if self.thin_walls.is_none() {
+                self.thin_walls = Some(ThinWallSettings::default());
+            }
+
+            if let Some(thin_walls) = &mut self.thin_walls {
+                egui::CollapsingHeader::new("Thin Wall Settings")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        thin_walls.show(ui);
+                    });
+            }
+        } else {
+            self.thin_walls = None;
+        }
+
+        let mut fuzzy_skin_enabled = self.fuzzy_skin.is_some();
+
+        show_bool(
+            &mut fuzzy_skin_enabled,
+            "Fuzzy skin",
+            Some("Enable/Disable the fuzzy skin"),
+            ui,
+        );
+
+        if fuzzy_skin_enabled {
+            if self.fuzzy_skin.is_none() {
+                self.fuzzy_skin = Some(FuzzySkinSettings::default());
+            }
+
+            if let Some(fuzzy_skin) = &mut self.fuzzy_skin {
+                egui::CollapsingHeader::new("Fuzzy Skin Settings")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        fuzzy_skin.show(ui);
+                    });
+            }
+        } else {
+            self.fuzzy_skin = None;
+        }
+
+        let mut overhangs_enabled = self.overhangs.is_some();
+
+        show_bool(
+            &mut overhangs_enabled,
+            "Overhangs",
+            Some("Enable/Disable the overhang speeds"),
+            ui,
+        );
+
+        if overhangs_enabled {
+            if self.overhangs.is_none() {
+                self.overhangs = Some(OverhangSettings::default());
+            }
+
+            if let Some(overhangs) = &mut self.overhangs {
+                egui::CollapsingHeader::new("Overhang Settings")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        overhangs.show(ui);
+                    });
+            }
+        } else {
+            self.overhangs = None;
+        }
+
         show_usize(&mut self.top_layers, "Top layers", None, ui);
 
         show_usize(&mut self.bottom_layers, "Bottom layers", None, ui);
 
+        show_bool(&mut self.spiral_vase, "Spiral vase", None, ui);
+
         let mut brim_width = self.brim_width.is_some();
 
         show_bool(&mut brim_width, "Brim width", None, ui);
@@ -181,6 +291,31 @@ impl UiSetting for slicer::Settings {
             self.brim_width = None;
         }
 
+        let mut raft_enabled = self.raft.is_some();
+
+        show_bool(
+            &mut raft_enabled,
+            "Raft",
+            Some("Enable/Disable the raft"),
+            ui,
+        );
+
+        if raft_enabled {
+            if self.raft.is_none() {
+                self.raft = Some(RaftSettings::default());
+            }
+
+            if let Some(raft) = &mut self.raft {
+                egui::CollapsingHeader::new("Raft Settings")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        raft.show(ui);
+                    });
+            }
+        } else {
+            self.raft = None;
+        }
+
         let mut layer_shrink = self.layer_shrink_amount.is_some();
 
         show_bool(&mut layer_shrink, "Layer shrink", None, ui);
@@ -213,6 +348,14 @@ impl UiSetting for slicer::Settings {
 
         show_combo(&mut self.solid_infill_type, "Solid infill type", ui);
         show_combo(&mut self.partial_infill_type, "Partial infill type", ui);
+
+        show_combo(&mut self.seam_placement, "Seam placement", ui);
+        if let slicer::SeamPlacement::Random(seed) = &mut self.seam_placement {
+            let mut value = *seed as usize;
+            if show_usize(&mut value, "Seam seed", None, ui).changed() {
+                *seed = value as u64;
+            }
+        }
     }
 
     fn show_printer(&mut self, ui: &mut egui::Ui) {
@@ -223,6 +366,31 @@ impl UiSetting for slicer::Settings {
                 show_f32(&mut self.print_y, "Printer Dimension Y", Some("mm"), ui);
                 show_f32(&mut self.print_z, "Printer Dimension Z", Some("mm"), ui);
             });
+
+        let mut collision_check_enabled = self.printhead.is_some();
+
+        show_bool(
+            &mut collision_check_enabled,
+            "Collision check",
+            Some("Enable/Disable printhead collision checking"),
+            ui,
+        );
+
+        if collision_check_enabled {
+            if self.printhead.is_none() {
+                self.printhead = Some(PrintheadSettings::default());
+            }
+
+            if let Some(printhead) = &mut self.printhead {
+                egui::CollapsingHeader::new("Printhead")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        printhead.show(ui);
+                    });
+            }
+        } else {
+            self.printhead = None;
+        }
     }
 
     fn show_layer_specific(&mut self, _ui: &mut egui::Ui) {
@@ -450,6 +618,15 @@ impl WidgetComponent for MovementParameter {
         show_f32(&mut self.bridge, "Bridge", Some("mm/s"), ui);
 
         show_f32(&mut self.support, "Support", Some("mm/s"), ui);
+
+        show_f32(&mut self.gap_fill, "Gap fill", Some("mm/s"), ui);
+
+        show_f32(
+            &mut self.support_interface,
+            "Support interface",
+            Some("mm/s"),
+            ui,
+        );
     }
 }
 
@@ -478,6 +655,25 @@ impl WidgetComponent for FanSettings {
             None,
             ui,
         );
+        show_usize(&mut self.ramp_up_layers, "Ramp up layers", None, ui);
+
+        let mut bridge_fan = self.bridge_fan_speed.is_some();
+
+        show_bool(&mut bridge_fan, "Bridge fan", None, ui);
+
+        if bridge_fan {
+            if self.bridge_fan_speed.is_none() {
+                self.bridge_fan_speed = Some(100.0);
+            }
+
+            if let Some(bridge_fan_speed) = &mut self.bridge_fan_speed {
+                show_f32(bridge_fan_speed, "Bridge fan speed", Some("%"), ui);
+            }
+        } else {
+            self.bridge_fan_speed = None;
+        }
+
+        show_f32(&mut self.max_fan_speed, "Max fan speed", Some("%"), ui);
 
         show_f32(
             &mut self.slow_down_threshold,
@@ -495,6 +691,79 @@ impl WidgetComponent for FanSettings {
     }
 }
 
+impl WidgetComponent for AdaptiveLayerHeightSettings {
+    fn show(&mut self, ui: &mut egui::Ui) {
+        show_f32(
+            &mut self.min_layer_height,
+            "Min layer height",
+            Some("mm"),
+            ui,
+        );
+        show_f32(
+            &mut self.max_layer_height,
+            "Max layer height",
+            Some("mm"),
+            ui,
+        );
+        show_f32(&mut self.max_deviation, "Max deviation", Some("mm"), ui);
+        show_f32(
+            &mut self.max_height_change,
+            "Max height change",
+            Some("mm"),
+            ui,
+        );
+    }
+}
+
+impl WidgetComponent for ThinWallSettings {
+    fn show(&mut self, ui: &mut egui::Ui) {
+        show_f32(&mut self.min_width, "Min width", Some("mm"), ui);
+        show_f32(&mut self.max_width, "Max width", Some("mm"), ui);
+    }
+}
+
+impl WidgetComponent for FuzzySkinSettings {
+    fn show(&mut self, ui: &mut egui::Ui) {
+        show_f32(&mut self.thickness, "Thickness", Some("mm"), ui);
+        show_f32(&mut self.point_distance, "Point distance", Some("mm"), ui);
+
+        let mut value = self.seed as usize;
+        if show_usize(&mut value, "Seed", None, ui).changed() {
+            self.seed = value as u64;
+        }
+
+        show_bool(&mut self.mask_only, "Mask only", None, ui);
+    }
+}
+
+impl WidgetComponent for OverhangSettings {
+    fn show(&mut self, ui: &mut egui::Ui) {
+        let classes = self.classes.len();
+
+        for (index, class) in self.classes.iter_mut().enumerate() {
+            ui.label(format!("Up to {}% over air", (index + 1) * 100 / classes));
+
+            show_f32(&mut class.speed, "Speed", Some("mm/s"), ui);
+
+            let mut fan_override = class.fan_speed.is_some();
+
+            show_bool(&mut fan_override, "Fan override", None, ui);
+
+            if fan_override {
+                if class.fan_speed.is_none() {
+                    class.fan_speed = Some(100.0);
+                }
+
+                if let Some(fan_speed) = &mut class.fan_speed {
+                    show_f32(fan_speed, "Fan speed", Some("%"), ui);
+                }
+            } else {
+                class.fan_speed = None;
+            }
+        }
+    }
+}
+
 impl WidgetComponent for SkirtSettings {
     fn show(&mut self, ui: &mut egui::Ui) {
         show_usize(&mut self.layers, "Layers", None, ui);
@@ -502,6 +771,16 @@ impl WidgetComponent for SkirtSettings {
     }
 }
 
+impl WidgetComponent for RaftSettings {
+    fn show(&mut self, ui: &mut egui::Ui) {
+        show_usize(&mut self.base_layers, "Base layers", None, ui);
+        show_usize(&mut self.interface_layers, "Interface layers", None, ui);
+        show_f32(&mut self.spacing, "Spacing", Some("mm"), ui);
+        show_f32(&mut self.margin, "Margin", Some("mm"), ui);
+        show_f32(&mut self.air_gap, "Air gap", Some("mm"), ui);
+    }
+}
+
 impl WidgetComponent for SupportSettings {
     fn show(&mut self, ui: &mut egui::Ui) {
         show_f32(
@@ -511,6 +790,79 @@ impl WidgetComponent for SupportSettings {
             ui,
         );
         show_f32(&mut self.support_spacing, "Support spacing", Some("mm"), ui);
+
+        show_combo(&mut self.support_type, "Support type", ui);
+        if self.support_type == slicer::SupportType::Tree {
+            show_f32(
+                &mut self.branch_tip_diameter,
+                "Branch tip diameter",
+                Some("mm"),
+                ui,
+            );
+            show_f32(
+                &mut self.branch_clearance,
+                "Branch clearance",
+                Some("mm"),
+                ui,
+            );
+        }
+
+        show_usize(&mut self.interface_layers, "Interface layers", None, ui);
+        show_f32(
+            &mut self.interface_spacing,
+            "Interface spacing",
+            Some("mm"),
+            ui,
+        );
+        show_combo(&mut self.interface_pattern, "Interface pattern", ui);
+        show_usize(&mut self.top_gap_layers, "Top gap layers", None, ui);
+        show_usize(&mut self.bottom_gap_layers, "Bottom gap layers", None, ui);
+    }
+}
+
+impl WidgetComponent for PrintheadSettings {
+    fn show(&mut self, ui: &mut egui::Ui) {
+        show_f32(
+            &mut self.nozzle_tip_diameter,
+            "Nozzle tip diameter",
+            Some("mm"),
+            ui,
+        );
+        show_f32(
+            &mut self.nozzle_cone_diameter,
+            "Nozzle cone diameter",
+            Some("mm"),
+            ui,
+        );
+        show_f32(
+            &mut self.nozzle_cone_height,
+            "Nozzle cone height",
+            Some("mm"),
+            ui,
+        );
+        show_f32(
+            &mut self.heater_block_width,
+            "Heater block width",
+            Some("mm"),
+            ui,
+        );
+        show_f32(
+            &mut self.heater_block_depth,
+            "Heater block depth",
+            Some("mm"),
+            ui,
+        );
+        show_f32(
+            &mut self.heater_block_height,
+            "Heater block height",
+            Some("mm"),
+            ui,
+        );
+        show_f32(&mut self.carriage_offset, "Carriage offset", Some("mm"), ui);
+        show_f32(&mut self.carriage_width, "Carriage width", Some("mm"), ui);
+        show_f32(&mut self.carriage_depth, "Carriage depth", Some("mm"), ui);
+        show_f32(&mut self.carriage_height, "Carriage height", Some("mm"), ui);
+        show_f32(&mut self.tip_clearance, "Tip clearance", Some("mm"), ui);
     }
 }
 
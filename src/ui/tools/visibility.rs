@@ -14,6 +14,8 @@ pub struct VisibilityToolState {
     print_types: [bool; MovePrintType::COUNT],
     travel: bool,
     setup: bool,
+    tool_axis: bool,
+    head: bool,
 }
 
 impl Default for VisibilityToolState {
@@ -24,6 +26,8 @@ impl Default for VisibilityToolState {
             print_types: [true; MovePrintType::COUNT],
             travel: false,
             setup: false,
+            tool_axis: true,
+            head: false,
         }
     }
 }
@@ -81,6 +85,8 @@ impl Tool for VisibilityTool<'_> {
                     let old_print_types = self.state.print_types;
                     let old_travel = self.state.travel;
                     let old_setup = self.state.setup;
+                    let old_tool_axis = self.state.tool_axis;
+                    let old_head = self.state.head;
 
                     if let Some(count_map) = global_state
                         .viewer
@@ -156,6 +162,35 @@ impl Tool for VisibilityTool<'_> {
                                     .color(Color32::BLACK),
                             );
                         });
+
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                            ui.checkbox(
+                                &mut self.state.tool_axis,
+                                RichText::new("Tool Axis")
+                                    .font(FontId::monospace(15.0))
+                                    .strong()
+                                    .color(Color32::BLACK),
+                            );
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.checkbox(
+                                &mut self.state.head,
+                                RichText::new("Printhead")
+                                    .font(FontId::monospace(15.0))
+                                    .strong()
+                                    .color(Color32::BLACK),
+                            );
+                        });
+                    }
+
+                    if old_tool_axis != self.state.tool_axis || old_head != self.state.head {
+                        let mut toolpath_server = global_state.viewer.toolpath_server.write();
+
+                        toolpath_server.set_show_tool_axis(self.state.tool_axis);
+                        toolpath_server.set_show_head(self.state.head);
                     }
 
                     if old_print_types != self.state.print_types
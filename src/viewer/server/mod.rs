@@ -3,5 +3,5 @@ pub mod model;
 mod toolpath;
 
 pub use env::EnvironmentServer;
-pub use model::CADModelServer;
+pub use model::{CADModelServer, ModelKind};
 pub use toolpath::ToolpathServer;
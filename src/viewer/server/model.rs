@@ -54,10 +54,21 @@ pub enum Error {
     NoGeometryObject,
 }
 
+///What a loaded model is used for when slicing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelKind {
+    Object,
+    ///Support is forced under the objects inside of it
+    SupportEnforcer,
+    ///No support is generated inside of it
+    SupportBlocker,
+}
+
 #[derive(Debug)]
 pub struct LoadResult {
     model: CADModel,
     mesh: ObjectMesh,
+    kind: ModelKind,
 
     process: Arc<Process>,
     origin_path: String,
@@ -67,6 +78,7 @@ pub struct LoadResult {
 pub struct CADModelHandle {
     model: Arc<CADModel>,
     mesh: ObjectMesh,
+    kind: ModelKind,
 }
 
 type CADModelResult = Result<LoadResult, CADModelError>;
@@ -158,7 +170,7 @@ impl Server for CADModelServer {
 }
 
 impl CADModelServer {
-    pub fn load<P>(&mut self, path: P)
+    pub fn load<P>(&mut self, path: P, kind: ModelKind)
     where
         P: AsRef<Path>,
     {
@@ -166,7 +178,7 @@ impl CADModelServer {
         let (tx, rx) = tokio::sync::oneshot::channel();
 
         let handle = tokio::spawn(async move {
-            let mesh = match (shared::loader::STLLoader {}).load(&path) {
+            let mut mesh = match (shared::loader::STLLoader {}).load(&path) {
                 Ok(model) => model,
                 Err(e) => {
                     tx.send(Err(CADModelError::LoadError(e))).unwrap();
@@ -175,8 +187,6 @@ impl CADModelServer {
                 }
             };
 
-            let (min, max) = mesh.min_max();
-
             let global_state = GLOBAL_STATE.read();
             let global_state = global_state.as_ref().unwrap();
 
@@ -185,6 +195,21 @@ impl CADModelServer {
                 .write()
                 .add(MODEL_LOAD_PROGRESS, trim_text::<20, 4>(&path));
 
+            process_tracking.set_task("Repairing mesh".to_string());
+            let report = mesh.repair();
+
+            if !report.is_clean() {
+                global_state
+                    .ui_event_writer
+                    .send(crate::ui::UiEvent::ShowInfo(format!(
+                        "Repaired {}: {}",
+                        trim_text::<20, 4>(&path),
+                        report
+                    )));
+            }
+
+            let (min, max) = mesh.min_max();
+
             let vertices: Vec<Vec3> = mesh.vertices().iter().map(|v| v.xzy()).collect();
 
             let mut triangles: Vec<(shared::IndexedTriangle, Vec3)> = mesh
@@ -294,6 +319,7 @@ Clustering models"
                 process: process_tracking,
                 model: root,
                 mesh,
+                kind,
                 origin_path: path,
             }))
             .unwrap();
@@ -331,6 +357,7 @@ Clustering models"
         let ctx = CADModelHandle {
             model: handle.clone(),
             mesh: model_handle.mesh,
+            kind: model_handle.kind,
         };
 
         self.models.insert(name.clone(), ctx);
@@ -416,9 +443,11 @@ Clustering models"
         &self.root_hitbox
     }
 
-    pub fn models<'a>(&'a self, settings: &'a Settings) -> Vec<ObjectMesh> {
+    ///The meshes of the given kind, transformed into the coordinates of the printer
+    pub fn models<'a>(&'a self, settings: &'a Settings, kind: ModelKind) -> Vec<ObjectMesh> {
         self.models
             .values()
+            .filter(|model| model.kind == kind)
             .map(|model| {
                 let transform = model.model.get_transform();
 
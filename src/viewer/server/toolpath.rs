@@ -2,15 +2,20 @@ use std::fs::File;
 use std::io::BufWriter;
 use std::sync::Arc;
 
+use glam::Vec4;
 use nfde::{DialogResult, Nfd, SingleFileDialogBuilder};
 use shared::process::Process;
-use slicer::{convert, MovePrintType, SliceResult};
+use slicer::{convert, MovePrintType, PrintheadSettings, SliceResult};
 use tokio::sync::oneshot::Receiver;
 use tokio::task::JoinHandle;
 use wgpu::util::DeviceExt;
 
+use crate::geometry::mesh::Mesh;
 use crate::input::hitbox::HitboxRoot;
+use crate::prelude::LockModel;
+use crate::render::model::Model;
 use crate::render::Renderable;
+use crate::viewer::toolpath::mesh::HeadMesh;
 use crate::viewer::toolpath::vertex::{ToolpathContext, ToolpathVertex};
 use crate::viewer::toolpath::Toolpath;
 use crate::viewer::Server;
@@ -19,6 +24,11 @@ use crate::{prelude::WgpuContext, GlobalState, RootEvent};
 
 use crate::viewer::toolpath::tree::ToolpathTree;
 
+const HEAD_COLOR: Vec4 = Vec4::new(0.6, 0.6, 0.65, 0.5);
+
+/// Amount of frames a full playback of the toolpath takes
+const PLAYBACK_FRAMES: usize = 2000;
+
 // const MAIN_LOADED_TOOLPATH: &str = "main"; // HACK: This is a solution to ease the dev when only one toolpath is loaded which is the only supported(for now)
 
 #[derive(thiserror::Error, Debug)]
@@ -40,6 +50,12 @@ pub struct ToolpathServer {
     toolpath_context_buffer: wgpu::Buffer,
     toolpath_context: ToolpathContext,
     toolpath_context_bind_group: wgpu::BindGroup,
+
+    head_model: LockModel<ToolpathVertex>,
+    show_tool_axis: bool,
+    show_head: bool,
+    playback_position: usize,
+    playing: bool,
 }
 
 impl Server for ToolpathServer {
@@ -170,6 +186,11 @@ impl Server for ToolpathServer {
             toolpath_context,
             toolpath_context_bind_group,
             toolpath_context_buffer,
+            head_model: LockModel::new(Model::create()),
+            show_tool_axis: true,
+            show_head: false,
+            playback_position: 0,
+            playing: false,
         }
     }
 
@@ -178,6 +199,14 @@ impl Server for ToolpathServer {
             render_pass.set_pipeline(&self.pipeline);
             render_pass.set_bind_group(4, &self.toolpath_context_bind_group, &[]);
             toolpath.model.render(render_pass);
+
+            if self.show_tool_axis && toolpath.has_orientation {
+                toolpath.model.render_tool_axis(render_pass);
+            }
+
+            if self.show_head {
+                self.head_model.render(render_pass);
+            }
         }
     }
 }
@@ -190,9 +219,13 @@ impl ToolpathServer {
             process.set_task("Loading toolpath".to_string());
             process.set_progress(0.8);
 
-            let toolpath =
-                Toolpath::from_commands(&slice_result.moves, &slice_result.settings, &process)
-                    .expect("Failed to load toolpath");
+            let toolpath = Toolpath::from_commands(
+                &slice_result.moves,
+                &slice_result.settings,
+                &slice_result.collisions,
+                &process,
+            )
+            .expect("Failed to load toolpath");
 
             tx.send((toolpath, process)).unwrap();
         });
@@ -242,13 +275,87 @@ impl ToolpathServer {
 
                 self.hitbox.add_node(toolpath.model.clone());
 
+                let last_position = toolpath.tool_positions.len().saturating_sub(1);
+
                 self.toolpath = Some(toolpath);
+                self.playing = false;
+                self.set_playback_position(last_position);
             }
         }
 
+        if self.playing {
+            let len = self
+                .toolpath
+                .as_ref()
+                .map_or(0, |toolpath| toolpath.tool_positions.len());
+
+            let position = self.playback_position + (len / PLAYBACK_FRAMES).max(1);
+
+            if position + 1 >= len {
+                self.playing = false;
+            }
+
+            self.set_playback_position(position.min(len.saturating_sub(1)));
+        }
+
         Ok(())
     }
 
+    pub fn set_playback_position(&mut self, position: usize) {
+        self.playback_position = position;
+
+        let toolpath = match self.toolpath.as_ref() {
+            Some(toolpath) => toolpath,
+            None => return,
+        };
+
+        if let Some(tool_position) = toolpath.tool_positions.get(position) {
+            let printhead = toolpath
+                .settings
+                .printhead
+                .clone()
+                .unwrap_or_else(PrintheadSettings::default);
+
+            let vertices: Vec<ToolpathVertex> = HeadMesh::from_printhead(printhead)
+                .with_pose(tool_position.position, tool_position.axis)
+                .with_color(HEAD_COLOR)
+                .to_triangle_vertices()
+                .into_iter()
+                .map(|v| ToolpathVertex::from_vertex(v, u32::MAX, tool_position.layer as u32))
+                .collect();
+
+            self.head_model.write().awaken(&vertices);
+        }
+    }
+
+    pub fn get_playback_position(&self) -> usize {
+        self.playback_position
+    }
+
+    pub fn set_playing(&mut self, playing: bool) {
+        self.playing = playing;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn set_show_tool_axis(&mut self, show: bool) {
+        self.show_tool_axis = show;
+    }
+
+    pub fn is_showing_tool_axis(&self) -> bool {
+        self.show_tool_axis
+    }
+
+    pub fn set_show_head(&mut self, show: bool) {
+        self.show_head = show;
+    }
+
+    pub fn is_showing_head(&self) -> bool {
+        self.show_head
+    }
+
     pub fn set_visibility(&mut self, value: u32) {
         self.toolpath_context.visibility = value;
 
@@ -1,4 +1,5 @@
-use glam::{Vec3, Vec4};
+use glam::{Quat, Vec3, Vec4};
+use slicer::PrintheadSettings;
 
 use crate::{
     geometry::{
@@ -275,6 +276,169 @@ impl Mesh<12> for MoveConnectionMesh {
     }
 }
 
+const HEAD_CONE_SEGMENTS: usize = 16;
+
+pub const HEAD_MESH_VERTICES: usize = HEAD_CONE_SEGMENTS * 9 + 2 * 36;
+
+/// Simplified printhead made of the nozzle cone, the heater block and the carriage.
+/// The nozzle tip sits at the position and the head extends along the tool axis.
+pub struct HeadMesh {
+    printhead: PrintheadSettings,
+    position: Vec3,
+    axis: Vec3,
+    color: Option<Vec4>,
+}
+
+impl HeadMesh {
+    pub fn from_printhead(printhead: PrintheadSettings) -> Self {
+        Self {
+            printhead,
+            position: Vec3::ZERO,
+            axis: Vec3::Y,
+            color: None,
+        }
+    }
+
+    pub fn with_pose(mut self, position: Vec3, axis: Vec3) -> Self {
+        self.position = position;
+        self.axis = axis.try_normalize().unwrap_or(Vec3::Y);
+        self
+    }
+
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+/// Pushes a quad as 2 triangles whose normals point in the outward direction
+fn push_quad(raw: &mut Vec<Vec3>, [p0, p1, p2, p3]: [Vec3; 4], outward: Vec3) {
+    if (p1 - p0).cross(p2 - p0).dot(outward) >= 0.0 {
+        raw.extend([p0, p1, p2, p0, p2, p3]);
+    } else {
+        raw.extend([p0, p2, p1, p0, p3, p2]);
+    }
+}
+
+fn push_box(raw: &mut Vec<Vec3>, min: Vec3, max: Vec3) {
+    let center = (min + max) / 2.0;
+    let corner = |x: f32, y: f32, z: f32| Vec3::new(x, y, z);
+
+    let faces = [
+        [
+            corner(min.x, min.y, min.z),
+            corner(max.x, min.y, min.z),
+            corner(max.x, min.y, max.z),
+            corner(min.x, min.y, max.z),
+        ],
+        [
+            corner(min.x, max.y, min.z),
+            corner(max.x, max.y, min.z),
+            corner(max.x, max.y, max.z),
+            corner(min.x, max.y, max.z),
+        ],
+        [
+            corner(min.x, min.y, min.z),
+            corner(min.x, max.y, min.z),
+            corner(min.x, max.y, max.z),
+            corner(min.x, min.y, max.z),
+        ],
+        [
+            corner(max.x, min.y, min.z),
+            corner(max.x, max.y, min.z),
+            corner(max.x, max.y, max.z),
+            corner(max.x, min.y, max.z),
+        ],
+        [
+            corner(min.x, min.y, min.z),
+            corner(max.x, min.y, min.z),
+            corner(max.x, max.y, min.z),
+            corner(min.x, max.y, min.z),
+        ],
+        [
+            corner(min.x, min.y, max.z),
+            corner(max.x, min.y, max.z),
+            corner(max.x, max.y, max.z),
+            corner(min.x, max.y, max.z),
+        ],
+    ];
+
+    for face in faces {
+        let face_center = (face[0] + face[2]) / 2.0;
+        push_quad(raw, face, face_center - center);
+    }
+}
+
+impl Mesh<HEAD_MESH_VERTICES> for HeadMesh {
+    fn to_triangle_vertices(&self) -> [Vertex; HEAD_MESH_VERTICES] {
+        let printhead = &self.printhead;
+        let mut raw = Vec::with_capacity(HEAD_MESH_VERTICES);
+
+        let tip_radius = printhead.nozzle_tip_diameter / 2.0;
+        let cone_radius = printhead.nozzle_cone_diameter / 2.0;
+        let cone_height = printhead.nozzle_cone_height;
+
+        let ring = |index: usize, radius: f32, height: f32| {
+            let angle = index as f32 * std::f32::consts::TAU / HEAD_CONE_SEGMENTS as f32;
+            Vec3::new(angle.cos() * radius, height, angle.sin() * radius)
+        };
+
+        for i in 0..HEAD_CONE_SEGMENTS {
+            let bottom_0 = ring(i, tip_radius, 0.0);
+            let bottom_1 = ring(i + 1, tip_radius, 0.0);
+            let top_0 = ring(i, cone_radius, cone_height);
+            let top_1 = ring(i + 1, cone_radius, cone_height);
+
+            let outward = ring(i, 1.0, 0.0) + ring(i + 1, 1.0, 0.0);
+            push_quad(&mut raw, [bottom_0, bottom_1, top_1, top_0], outward);
+
+            raw.extend([Vec3::ZERO, bottom_0, bottom_1]);
+        }
+
+        let heater_block_top = cone_height + printhead.heater_block_height;
+        push_box(
+            &mut raw,
+            Vec3::new(
+                -printhead.heater_block_width / 2.0,
+                cone_height,
+                -printhead.heater_block_depth / 2.0,
+            ),
+            Vec3::new(
+                printhead.heater_block_width / 2.0,
+                heater_block_top,
+                printhead.heater_block_depth / 2.0,
+            ),
+        );
+
+        let carriage_bottom = heater_block_top + printhead.carriage_offset;
+        push_box(
+            &mut raw,
+            Vec3::new(
+                -printhead.carriage_width / 2.0,
+                carriage_bottom,
+                -printhead.carriage_depth / 2.0,
+            ),
+            Vec3::new(
+                printhead.carriage_width / 2.0,
+                carriage_bottom + printhead.carriage_height,
+                printhead.carriage_depth / 2.0,
+            ),
+        );
+
+        let rotation = Quat::from_rotation_arc(Vec3::Y, self.axis);
+        let raw: Vec<Vec3> = raw
+            .into_iter()
+            .map(|v| self.position + rotation * v)
+            .collect();
+
+        construct_triangle_vertices(
+            raw.try_into()
+                .expect("Head mesh has a fixed number of vertices"),
+            self.color.unwrap_or(Vec4::new(0.0, 0.0, 0.0, 1.0)),
+        )
+    }
+}
+
 impl From<MoveMesh> for MoveHitbox {
     fn from(val: MoveMesh) -> Self {
         let north_west = QuadFace {
@@ -1,12 +1,12 @@
 use std::{fmt::Debug, sync::Arc};
 
-use egui::ahash::{HashMap, HashMapExt};
+use egui::ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
 use glam::{Vec3, Vec4};
 use mesh::{
     MoveConnectionMesh, MoveHitbox, MoveMesh, ProfileCross, ProfileCrossMesh, MOVE_MESH_VERTICES,
 };
 use shared::process::Process;
-use slicer::{Command, MovePrintType, StateChange};
+use slicer::{Command, MovePrintType, SlicerErrors, StateChange};
 use tree::ToolpathTree;
 use vertex::ToolpathVertex;
 use wgpu::BufferAddress;
@@ -37,6 +37,9 @@ pub fn bit_representation(print_type: &MovePrintType) -> u32 {
     0x01 << ((*print_type as u32) + 0x02)
 }
 
+/// Color of moves where the printhead collides with the print or the bed
+const COLLISION_COLOR: Vec4 = Vec4::new(1.0, 0.0, 1.0, 1.0);
+
 pub const fn bit_representation_travel() -> u32 {
     0x02
 }
@@ -45,6 +48,20 @@ pub const fn bit_representation_setup() -> u32 {
     0x01
 }
 
+/// Distance between two tool axis needles along the extrusion path
+const NEEDLE_SPACING: f32 = 2.0;
+const NEEDLE_LENGTH: f32 = 3.0;
+const NEEDLE_WIDTH: f32 = 0.1;
+const NEEDLE_COLOR: Vec4 = Vec4::new(0.1, 0.1, 0.1, 1.0);
+
+/// Position and orientation of the nozzle tip at the end of a move in viewer space
+#[derive(Debug, Clone, Copy)]
+pub struct ToolPosition {
+    pub position: Vec3,
+    pub axis: Vec3,
+    pub layer: usize,
+}
+
 #[derive(Debug)]
 pub struct Toolpath {
     pub model: Arc<ToolpathTree>,
@@ -52,6 +69,8 @@ pub struct Toolpath {
     pub max_layer: usize,
     pub moves: Vec<Command>,
     pub settings: slicer::Settings,
+    pub tool_positions: Vec<ToolPosition>,
+    pub has_orientation: bool,
 }
 
 unsafe impl Sync for Toolpath {}
@@ -61,12 +80,32 @@ impl Toolpath {
     pub fn from_commands(
         commands: &[slicer::Command],
         settings: &slicer::Settings,
+        collisions: &[SlicerErrors],
         _process: &Process,
     ) -> Result<Self, ()> {
         let mut current_state = StateChange::default();
         let mut current_type = None;
         let mut current_layer = 0;
+        let mut current_move_index = 0;
         let mut current_height_z = 0.0;
+        let mut current_axis = Vec3::Y;
+
+        let has_orientation = commands
+            .iter()
+            .any(|command| matches!(command, slicer::Command::SetToolAxis { .. }));
+
+        let colliding_moves: HashSet<(usize, usize)> = collisions
+            .iter()
+            .filter_map(|collision| match collision {
+                SlicerErrors::PrintheadCollision {
+                    layer, move_index, ..
+                }
+                | SlicerErrors::PrintheadBedCollision {
+                    layer, move_index, ..
+                } => Some((*layer, *move_index)),
+                _ => None,
+            })
+            .collect();
 
         let mut last_position = Vec3::ZERO;
 
@@ -77,6 +116,9 @@ impl Toolpath {
         let mut last_extrusion_profile = None;
 
         let mut move_vertices = Vec::new();
+        let mut needle_vertices = Vec::new();
+        let mut needle_distance = 0.0;
+        let mut tool_positions = Vec::new();
         // let mut travel_vertices = Vec::new();
         // let mut fiber_vertices = Vec::new();
 
@@ -86,9 +128,15 @@ impl Toolpath {
                 None => bit_representation_setup(),
             };
 
-            let color = current_type
-                .unwrap_or(MovePrintType::Infill)
-                .into_color_vec4();
+            let color = if colliding_moves.contains(&(current_layer, current_move_index))
+                && is_move(command)
+            {
+                COLLISION_COLOR
+            } else {
+                current_type
+                    .unwrap_or(MovePrintType::Infill)
+                    .into_color_vec4()
+            };
 
             match command {
                 slicer::Command::MoveTo { end } => {
@@ -102,6 +150,12 @@ impl Toolpath {
                     // travel_vertices.push(start);
                     // travel_vertices.push(end);
 
+                    tool_positions.push(ToolPosition {
+                        position: end,
+                        axis: current_axis,
+                        layer: current_layer,
+                    });
+
                     last_position = end;
                 }
                 slicer::Command::MoveAndExtrude {
@@ -109,15 +163,30 @@ impl Toolpath {
                     end,
                     thickness,
                     width,
+                }
+                | slicer::Command::MoveAndExtrudeSpiral {
+                    start,
+                    end,
+                    thickness,
+                    width,
+                    ..
                 } => {
+                    let (start_z, end_z) = match command {
+                        slicer::Command::MoveAndExtrudeSpiral { start_z, end_z, .. } => {
+                            current_height_z = *end_z;
+                            (*start_z, *end_z)
+                        }
+                        _ => (current_height_z, current_height_z),
+                    };
+
                     let start = Vec3::new(
                         start.x - settings.print_x / 2.0,
-                        current_height_z - thickness / 2.0,
+                        start_z - thickness / 2.0,
                         start.y - settings.print_y / 2.0,
                     );
                     let end = Vec3::new(
                         end.x - settings.print_x / 2.0,
-                        current_height_z - thickness / 2.0,
+                        end_z - thickness / 2.0,
                         end.y - settings.print_y / 2.0,
                     );
 
@@ -162,6 +231,26 @@ impl Toolpath {
                         .and_modify(|e| *e += 1)
                         .or_insert(1);
 
+                    let tip_offset = Vec3::new(0.0, thickness / 2.0, 0.0);
+
+                    if has_orientation {
+                        extend_needle_vertices(
+                            start + tip_offset,
+                            end + tip_offset,
+                            current_axis,
+                            &mut needle_distance,
+                            print_type_bit,
+                            current_layer,
+                            &mut needle_vertices,
+                        );
+                    }
+
+                    tool_positions.push(ToolPosition {
+                        position: end + tip_offset,
+                        axis: current_axis,
+                        layer: current_layer,
+                    });
+
                     last_position = end;
                 }
                 slicer::Command::MoveAndExtrudeFiber {
@@ -226,16 +315,42 @@ impl Toolpath {
 
                     root.push(tree_move);
 
+                    let tip_offset = Vec3::new(0.0, thickness / 2.0, 0.0);
+
+                    if has_orientation {
+                        extend_needle_vertices(
+                            start + tip_offset,
+                            end + tip_offset,
+                            current_axis,
+                            &mut needle_distance,
+                            print_type_bit,
+                            current_layer,
+                            &mut needle_vertices,
+                        );
+                    }
+
+                    tool_positions.push(ToolPosition {
+                        position: end + tip_offset,
+                        axis: current_axis,
+                        layer: current_layer,
+                    });
+
                     last_position = end;
                 }
                 slicer::Command::LayerChange { z, index } => {
                     current_layer = *index;
+                    current_move_index = 0;
                     current_height_z = *z;
                 }
                 slicer::Command::SetState { new_state } => {
                     current_state = new_state.clone();
                 }
                 slicer::Command::ChangeType { print_type } => current_type = Some(*print_type),
+                slicer::Command::SetToolAxis { axis } => {
+                    current_axis = Vec3::new(axis.x, axis.z, axis.y)
+                        .try_normalize()
+                        .unwrap_or(Vec3::Y);
+                }
                 _ => {}
             }
 
@@ -253,9 +368,13 @@ impl Toolpath {
 
                 last_extrusion_profile = None;
             }
+
+            if is_move(command) {
+                current_move_index += 1;
+            }
         }
 
-        root.awaken(&move_vertices, &[], &[]);
+        root.awaken(&move_vertices, &[], &[], &needle_vertices);
 
         root.update_offset(0);
 
@@ -265,6 +384,8 @@ impl Toolpath {
             max_layer: current_layer,
             moves: commands.to_vec(),
             settings: settings.clone(),
+            tool_positions,
+            has_orientation,
         })
     }
 
@@ -273,6 +394,54 @@ impl Toolpath {
     }
 }
 
+/// Moves that are counted for the move index of printhead collisions
+fn is_move(command: &slicer::Command) -> bool {
+    matches!(
+        command,
+        slicer::Command::MoveTo { .. }
+            | slicer::Command::MoveAndExtrude { .. }
+            | slicer::Command::MoveAndExtrudeFiber { .. }
+            | slicer::Command::MoveAndExtrudeSpiral { .. }
+            | slicer::Command::Arc { .. }
+    )
+}
+
+/// Places needles along the tool axis every NEEDLE_SPACING mm of the path,
+/// the distance since the last needle is carried over between moves
+fn extend_needle_vertices(
+    start: Vec3,
+    end: Vec3,
+    axis: Vec3,
+    needle_distance: &mut f32,
+    print_type_bit: u32,
+    current_layer: usize,
+    needle_vertices: &mut Vec<ToolpathVertex>,
+) {
+    let length = start.distance(end);
+    let mut position = NEEDLE_SPACING - *needle_distance;
+
+    while position <= length {
+        let base = start.lerp(end, position / length);
+
+        let mesh = MoveMesh::from_profiles(
+            ProfileCross::from_direction(axis, NEEDLE_WIDTH, NEEDLE_WIDTH).with_offset(base),
+            ProfileCross::from_direction(axis, NEEDLE_WIDTH, NEEDLE_WIDTH)
+                .with_offset(base + axis * NEEDLE_LENGTH),
+        )
+        .with_color(NEEDLE_COLOR);
+
+        needle_vertices.extend(
+            mesh.to_triangle_vertices()
+                .into_iter()
+                .map(|v| ToolpathVertex::from_vertex(v, print_type_bit, current_layer as u32)),
+        );
+
+        position += NEEDLE_SPACING;
+    }
+
+    *needle_distance = length - (position - NEEDLE_SPACING);
+}
+
 fn extend_connection_vertices(
     last_extrusion_profile: Option<ProfileCross>,
     start_profile: ProfileCross,
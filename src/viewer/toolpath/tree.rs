@@ -22,6 +22,7 @@ pub enum ToolpathTree {
         model: LockModel<ToolpathVertex>,
         travel_model: LockModel<ToolpathVertex>,
         fiber_model: LockModel<ToolpathVertex>,
+        tool_axis_model: LockModel<ToolpathVertex>,
         bounding_box: RwLock<BoundingBox>,
         children: Vec<Arc<Self>>,
         size: BufferAddress,
@@ -53,6 +54,7 @@ impl ToolpathTree {
             model: LockModel::new(Model::create()),
             travel_model: LockModel::new(Model::create()),
             fiber_model: LockModel::new(Model::create()),
+            tool_axis_model: LockModel::new(Model::create()),
 
             children: Vec::new(),
             bounding_box: RwLock::new(BoundingBox::default()),
@@ -150,23 +152,37 @@ impl ToolpathTree {
         data: &[ToolpathVertex],
         travel: &[ToolpathVertex],
         fiber: &[ToolpathVertex],
+        tool_axis: &[ToolpathVertex],
     ) {
         match self {
             Self::Root {
                 model,
                 travel_model,
                 fiber_model,
+                tool_axis_model,
                 ..
             } => {
                 model.write().awaken(data);
                 travel_model.write().awaken(travel);
                 fiber_model.write().awaken(fiber);
+                tool_axis_model.write().awaken(tool_axis);
             }
             Self::Travel { .. } => panic!("Cannot awaken travel"),
             Self::Fiber { .. } => panic!("Cannot awaken fiber"),
             Self::Move { .. } => panic!("Cannot awaken path"),
         }
     }
+
+    pub fn render_tool_axis<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        match self {
+            Self::Root {
+                tool_axis_model, ..
+            } => tool_axis_model.render(render_pass),
+            Self::Travel { .. } => panic!("Cannot render travel"),
+            Self::Fiber { .. } => panic!("Cannot render fiber"),
+            Self::Move { .. } => panic!("Cannot render path"),
+        }
+    }
 }
 
 impl Renderable for ToolpathTree {